@@ -0,0 +1,289 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::entities::EntityPosition;
+use crate::map::{column_label, legend_entries, legend_shape, Map};
+use crate::points::Point;
+use crate::shapes::Shape;
+
+/// Number of characters across each grid cell, including one of its corners
+pub const CELL_COLUMNS: usize = 3;
+/// Number of lines down each grid cell, including one of its corners
+pub const CELL_ROWS: usize = 2;
+
+/// Draw a map as text, with its walls drawn with box-drawing characters and
+/// its entities as letters, followed by a key to the letters. With labels,
+/// the columns and rows are labelled in the same way as in an SVG.
+pub fn map_to_ascii(map: &Map, labels: bool) -> String {
+    let mut canvas = Canvas::new(map.width() * CELL_COLUMNS + 1, map.height() * CELL_ROWS + 1);
+
+    for x in 0..=map.width() {
+        for y in 0..=map.height() {
+            let p = Point::new(x, y);
+            let (column, row) = (x * CELL_COLUMNS, y * CELL_ROWS);
+            canvas.set(column, row, junction(map, p));
+            if x < map.width() && map.are_connected(p, p.right()) {
+                for i in 1..CELL_COLUMNS {
+                    canvas.set(column + i, row, '─');
+                }
+            }
+            if y < map.height() && map.are_connected(p, p.down()) {
+                for j in 1..CELL_ROWS {
+                    canvas.set(column, row + j, '│');
+                }
+            }
+        }
+    }
+
+    // Entities are drawn over the walls, as they are in an SVG
+    let key = key_letters(map);
+    for entity in map.entities() {
+        let shape = legend_shape(map, entity.shape());
+        let letter = key
+            .iter()
+            .find(|(s, _, _)| *s == shape)
+            .map_or('?', |(_, _, c)| *c);
+        let p = entity.point();
+        match entity.position() {
+            EntityPosition::Within => {
+                for x in p.x()..p.x() + entity.width() {
+                    for y in p.y()..p.y() + entity.height() {
+                        canvas.set(x * CELL_COLUMNS + 1, y * CELL_ROWS + 1, letter);
+                    }
+                }
+            }
+            EntityPosition::At => canvas.set(p.x() * CELL_COLUMNS, p.y() * CELL_ROWS, letter),
+        }
+    }
+
+    let mut lines = canvas.lines();
+    if labels {
+        lines = label_lines(map, lines);
+    }
+
+    let key: Vec<String> = key
+        .iter()
+        .map(|(_, label, letter)| format!("{}  {}", letter, label))
+        .collect();
+    if !key.is_empty() {
+        lines.push(String::new());
+        lines.extend(key);
+    }
+
+    let mut s: String = lines
+        .iter()
+        .map(|line| line.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    s.push('\n');
+    s
+}
+
+/// The shapes in the map's key, with the label and letter for each. Built-in shapes
+/// have their own letters, and each glyph and stamp gets the first letter of
+/// its name that no other shape in the key has, so that they can be told
+/// apart.
+fn key_letters(map: &Map) -> Vec<(Shape, String, char)> {
+    let entries = legend_entries(map);
+    let mut taken: Vec<char> = entries
+        .iter()
+        .filter_map(|(shape, _)| shape.letter())
+        .collect();
+    entries
+        .into_iter()
+        .map(|(shape, label)| {
+            let letter = shape.letter().unwrap_or_else(|| {
+                let letter = label
+                    .chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .map(|c| c.to_ascii_uppercase())
+                    .chain('A'..='Z')
+                    .find(|c| !taken.contains(c))
+                    .unwrap_or('?');
+                taken.push(letter);
+                letter
+            });
+            (shape, label, letter)
+        })
+        .collect()
+}
+
+/// Add a row of column letters above the map, and row numbers to its left
+fn label_lines(map: &Map, lines: Vec<String>) -> Vec<String> {
+    let margin = map.height().to_string().len() + 1;
+    let mut header = Canvas::new(margin + map.width() * CELL_COLUMNS + 1, 1);
+    for x in 0..map.width() {
+        for (i, c) in column_label(x).chars().enumerate() {
+            header.set(margin + x * CELL_COLUMNS + 1 + i, 0, c);
+        }
+    }
+    let mut labelled = header.lines();
+    for (i, line) in lines.into_iter().enumerate() {
+        // Rows are numbered beside the middle of each cell
+        let label = if i % CELL_ROWS == 1 {
+            (i / CELL_ROWS + 1).to_string()
+        } else {
+            String::new()
+        };
+        labelled.push(format!("{:>width$} {}", label, line, width = margin - 1));
+    }
+    labelled
+}
+
+/// The box-drawing character for a grid point, joining up the walls that
+/// meet there
+fn junction(map: &Map, p: Point) -> char {
+    let up = p.y() > 0 && map.are_connected(p, p.up());
+    let down = p.y() < map.height() && map.are_connected(p, p.down());
+    let left = p.x() > 0 && map.are_connected(p, p.left());
+    let right = p.x() < map.width() && map.are_connected(p, p.right());
+    match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (_, _, false, false) => '│',
+        (false, false, _, _) => '─',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
+struct Canvas {
+    width: usize,
+    chars: Vec<char>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            chars: vec![' '; width * height],
+        }
+    }
+
+    fn set(&mut self, column: usize, row: usize, c: char) {
+        if column < self.width {
+            if let Some(cell) = self.chars.get_mut(row * self.width + column) {
+                *cell = c;
+            }
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.chars
+            .chunks(self.width)
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::glyphs::Glyph;
+    use crate::stamps::Stamp;
+
+    #[test]
+    fn test_room() {
+        let mut map = Map::new(2, 1);
+        connect_path(&mut map, &[(0, 0), (2, 0), (2, 1), (0, 1), (0, 0)]);
+        connect_path(&mut map, &[(1, 0), (1, 1)]);
+        let expected = "\
+┌──┬──┐
+│  │  │
+└──┴──┘
+";
+        assert_eq!(map_to_ascii(&map, false), expected);
+    }
+
+    #[test]
+    fn test_dangling_walls() {
+        let mut map = Map::new(2, 2);
+        connect_path(&mut map, &[(0, 1), (1, 1), (1, 2)]);
+        let expected = "\n\n───┐\n   │\n   │\n";
+        assert_eq!(map_to_ascii(&map, false), expected);
+    }
+
+    #[test]
+    fn test_entities() {
+        let mut map = Map::new(2, 2);
+        map.add_entity(Entity::spanning(Shape::Table, Point::new(0, 0), 2, 1));
+        let circle = Entity::new(Shape::Circle(1), Point::new(1, 1), EntityPosition::At);
+        map.add_entity(circle);
+        let expected = "\n T  T\n   C\n\n\n\nT  Table\nC  Circle\n";
+        assert_eq!(map_to_ascii(&map, false), expected);
+    }
+
+    #[test]
+    fn test_glyphs_and_stamps_are_lettered_by_name() {
+        let mut map = Map::new(4, 1);
+        for name in ["crate", "cask"] {
+            map.add_glyph(Glyph::new(name.to_string(), Vec::new()));
+        }
+        let svg = "<svg viewBox=\"0 0 1 1\"/>";
+        map.add_stamp(Stamp::parse("art/throne.svg", svg).unwrap());
+        let shapes = [
+            Shape::Table,
+            Shape::Glyph(0),
+            Shape::Glyph(1),
+            Shape::Stamp(0),
+        ];
+        for (x, shape) in shapes.into_iter().enumerate() {
+            let entity = Entity::new(shape, Point::new(x, 0), EntityPosition::Within);
+            map.add_entity(entity);
+        }
+        let expected = "\n T  C  A  H\n\n\nT  Table\nC  Crate\nA  Cask\nH  Throne\n";
+        assert_eq!(map_to_ascii(&map, false), expected);
+    }
+
+    #[test]
+    fn test_glyph_named_after_a_shape_has_its_own_letter() {
+        let mut map = Map::new(2, 1);
+        map.add_glyph(Glyph::new("Square".to_string(), Vec::new()));
+        for (x, shape) in [Shape::Square, Shape::Glyph(0)].into_iter().enumerate() {
+            let entity = Entity::new(shape, Point::new(x, 0), EntityPosition::Within);
+            map.add_entity(entity);
+        }
+        let expected = "\n Q  S\n\n\nQ  Square\nS  Square\n";
+        assert_eq!(map_to_ascii(&map, false), expected);
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut map = Map::new(2, 1);
+        connect_path(&mut map, &[(0, 0), (2, 0), (2, 1), (0, 1), (0, 0)]);
+        let expected = "   A  B\n  ┌─────┐\n1 │     │\n  └─────┘\n";
+        assert_eq!(map_to_ascii(&map, true), expected);
+    }
+
+    fn connect_path(map: &mut Map, points: &[(usize, usize)]) {
+        for pair in points.windows(2) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            let line: Vec<Point> = if x1 == x2 {
+                (y1.min(y2)..=y1.max(y2))
+                    .map(|y| Point::new(x1, y))
+                    .collect()
+            } else {
+                (x1.min(x2)..=x1.max(x2))
+                    .map(|x| Point::new(x, y1))
+                    .collect()
+            };
+            for segment in line.windows(2) {
+                map.connect(segment[0], segment[1]);
+            }
+        }
+    }
+}
@@ -8,19 +8,25 @@
  * Copyright (c) 2024 David Jackson
  */
 
+use super::compass::Compass;
 use super::entities::EntityPosition;
+use super::glyphs::Glyph;
 use super::points::Point;
 use super::shapes::{Line, Rect, Shape};
 use crate::source_location::SourceLocation;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct AbstractSyntaxTree {
     nodes: Vec<AstNode>,
+    trailing: Vec<Trivia>,
 }
 
 impl AbstractSyntaxTree {
     pub fn new() -> AbstractSyntaxTree {
-        AbstractSyntaxTree { nodes: Vec::new() }
+        AbstractSyntaxTree {
+            nodes: Vec::new(),
+            trailing: Vec::new(),
+        }
     }
 
     pub fn add_node(&mut self, node: AstNode) {
@@ -30,12 +36,31 @@ impl AbstractSyntaxTree {
     pub fn nodes(&self) -> std::slice::Iter<'_, AstNode> {
         self.nodes.iter()
     }
+
+    /// Set the comments after the last node
+    pub fn set_trailing(&mut self, trailing: Vec<Trivia>) {
+        self.trailing = trailing;
+    }
+
+    pub fn trailing(&self) -> &[Trivia] {
+        &self.trailing
+    }
+}
+
+/// The comments and blank lines around the nodes, which don't affect the map
+/// but are kept for formatting
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trivia {
+    Comment(String),
+    BlankLine,
 }
 
 #[derive(Debug)]
 pub struct AstNode {
     location: SourceLocation,
     node_type: AstNodeType,
+    leading: Vec<Trivia>,
+    trailing_comment: Option<String>,
 }
 
 impl AstNode {
@@ -43,9 +68,27 @@ impl AstNode {
         AstNode {
             location,
             node_type,
+            leading: Vec::new(),
+            trailing_comment: None,
         }
     }
 
+    /// Attach the comments and blank lines before the node, and the comment
+    /// at the end of its last line
+    pub fn with_comments(mut self, leading: Vec<Trivia>, trailing: Option<String>) -> AstNode {
+        self.leading = leading;
+        self.trailing_comment = trailing;
+        self
+    }
+
+    pub fn leading(&self) -> &[Trivia] {
+        &self.leading
+    }
+
+    pub fn trailing_comment(&self) -> Option<&str> {
+        self.trailing_comment.as_deref()
+    }
+
     pub fn node_type(&self) -> &AstNodeType {
         &self.node_type
     }
@@ -60,6 +103,11 @@ pub enum AstNodeType {
     GridDimensions(GridDimensionsNode),
     Shape(ShapeNode),
     Entity(EntityNode),
+    Glyph(Glyph),
+    Stamp(StampNode),
+    Title(String),
+    Scale(ScaleNode),
+    Compass(Compass),
 }
 
 #[derive(Debug)]
@@ -96,4 +144,20 @@ pub struct EntityNode {
     pub shape: Shape,
     pub point: Point,
     pub position: EntityPosition,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// How much distance one grid cell represents, e.g. 5 ft
+#[derive(Debug)]
+pub struct ScaleNode {
+    pub amount: f64,
+    pub unit: String,
+}
+
+#[derive(Debug)]
+pub struct StampNode {
+    pub path: String,
+    pub point: Point,
+    pub size: usize,
 }
@@ -0,0 +1,190 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::ast::EntityNode;
+use crate::compile_error::{CompileError, CompileErrorType};
+use crate::compiler::error_message;
+use crate::entities::{Entity, EntityPosition};
+use crate::generator::{handle_entity, handle_line, handle_rect};
+use crate::map::{Map, MapScale};
+use crate::shapes::{Line, Rect, Shape};
+use crate::source_location::SourceLocation;
+
+/// Build a map in code, with the same commands as the map language. Each
+/// command is checked in the same way as it is when compiling a map file, and
+/// the first one that fails is reported by `build`.
+pub struct MapBuilder {
+    map: Map,
+    commands: usize,
+    error: Option<(usize, CompileErrorType)>,
+}
+
+impl MapBuilder {
+    /// Start an empty map, `width` cells across and `height` cells down
+    pub fn new(width: usize, height: usize) -> MapBuilder {
+        MapBuilder {
+            map: Map::new(width, height),
+            commands: 0,
+            error: None,
+        }
+    }
+
+    pub fn rect(self, rect: Rect) -> Self {
+        self.command(|map, location| handle_rect(map, &rect, location))
+    }
+
+    pub fn line(self, line: Line) -> Self {
+        self.command(|map, location| handle_line(map, &line, location))
+    }
+
+    /// Add an entity. Glyphs and stamps can only be added in map files, as
+    /// they refer to definitions elsewhere in the file. As in map files, only
+    /// circles can be drawn at a grid point.
+    pub fn entity(self, entity: Entity) -> Self {
+        self.command(|map, location| {
+            let error =
+                |error_type| Err(CompileError::new(error_type, location.line, location.col));
+            if let Shape::Glyph(_) | Shape::Stamp(_) = entity.shape() {
+                return error(CompileErrorType::InvalidShape);
+            }
+            if matches!(entity.position(), EntityPosition::At)
+                && !matches!(entity.shape(), Shape::Circle(_))
+            {
+                return error(CompileErrorType::InvalidPosition);
+            }
+            if entity.width() == 0 || entity.height() == 0 {
+                return error(CompileErrorType::InvalidSize);
+            }
+            let node = EntityNode {
+                shape: entity.shape(),
+                point: entity.point(),
+                position: entity.position(),
+                width: entity.width(),
+                height: entity.height(),
+            };
+            handle_entity(map, &node, location)
+        })
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.map.set_title(title.to_string());
+        self
+    }
+
+    /// Set how much distance one grid cell represents, e.g. 5 ft
+    pub fn scale(mut self, amount: f64, unit: &str) -> Self {
+        self.map.set_scale(MapScale::new(amount, unit));
+        self
+    }
+
+    /// Finish the map, or return a message saying which command (counting
+    /// from 1) was invalid and why
+    pub fn build(self) -> Result<Map, String> {
+        match self.error {
+            Some((command, error_type)) => Err(format!(
+                "Command {}: {}",
+                command,
+                error_message(&error_type)
+            )),
+            None => Ok(self.map),
+        }
+    }
+
+    /// Run a command, unless an earlier one has already failed
+    fn command<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Map, SourceLocation) -> Result<(), CompileError>,
+    {
+        self.commands += 1;
+        if self.error.is_none() {
+            let location = SourceLocation {
+                line: self.commands,
+                col: 0,
+            };
+            if let Err(e) = f(&mut self.map, location) {
+                self.error = Some((self.commands, e.error_type));
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::points::Point;
+    use crate::shapes::{LineOrientation, ShapeBoolean};
+
+    #[test]
+    fn test_build() {
+        let map = MapBuilder::new(4, 4)
+            .title("Vault")
+            .rect(Rect::new(Point::new(0, 0), 2, 2, ShapeBoolean::Or))
+            .line(Line::new(
+                LineOrientation::Top,
+                Point::new(0, 0),
+                1,
+                ShapeBoolean::Xor,
+            ))
+            .entity(Entity::spanning(Shape::Table, Point::new(2, 2), 2, 1))
+            .build()
+            .expect("Bad map");
+        assert_eq!(map.title(), Some("Vault"));
+        assert!(!map.are_connected(Point::new(0, 0), Point::new(1, 0)));
+        assert!(map.are_connected(Point::new(1, 0), Point::new(2, 0)));
+        assert_eq!(map.entities()[0].width(), 2);
+    }
+
+    #[test]
+    fn test_first_error_is_reported() {
+        let result = MapBuilder::new(2, 2)
+            .rect(Rect::new(Point::new(0, 0), 1, 1, ShapeBoolean::Or))
+            .rect(Rect::new(Point::new(1, 1), 2, 2, ShapeBoolean::Or))
+            .entity(Entity::new(
+                Shape::Pillar,
+                Point::new(5, 5),
+                EntityPosition::Within,
+            ))
+            .build();
+        assert_eq!(result.unwrap_err(), "Command 2: Out-of-bounds point");
+    }
+
+    #[test]
+    fn test_glyphs_are_rejected() {
+        let result = MapBuilder::new(2, 2)
+            .entity(Entity::new(
+                Shape::Glyph(0),
+                Point::new(0, 0),
+                EntityPosition::Within,
+            ))
+            .build();
+        assert_eq!(result.unwrap_err(), "Command 1: Invalid shape");
+    }
+
+    #[test]
+    fn test_bad_entities_are_rejected() {
+        let build = |entity| MapBuilder::new(4, 4).entity(entity).build();
+        let too_wide = Entity::spanning(Shape::Table, Point::new(1, 0), usize::MAX, 1);
+        assert_eq!(
+            build(too_wide).unwrap_err(),
+            "Command 1: Out-of-bounds point"
+        );
+        let empty = Entity::spanning(Shape::Table, Point::new(1, 0), 0, 1);
+        assert_eq!(build(empty).unwrap_err(), "Command 1: Invalid size");
+        let square = Entity::new(Shape::Square, Point::new(1, 1), EntityPosition::At);
+        assert_eq!(build(square).unwrap_err(), "Command 1: Invalid position");
+        let huge = Entity::new(
+            Shape::Circle(usize::MAX),
+            Point::new(1, 1),
+            EntityPosition::At,
+        );
+        assert_eq!(build(huge).unwrap_err(), "Command 1: Out-of-bounds point");
+    }
+}
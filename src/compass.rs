@@ -0,0 +1,111 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// A compass rose drawn in a corner of the map, showing which way north is
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Compass {
+    corner: Corner,
+    rotation: f64,
+    /// Whether the rose is drawn beside the map, rather than over it
+    #[cfg_attr(feature = "serde", serde(default))]
+    outside: bool,
+}
+
+impl Compass {
+    /// The rotation is the direction of north, in degrees clockwise from
+    /// the top of the map
+    pub fn new(corner: Corner, rotation: f64) -> Compass {
+        Compass {
+            corner,
+            rotation: rotation.rem_euclid(360.0),
+            outside: false,
+        }
+    }
+
+    /// Draw the rose in a margin beside the corner of the map, so that it
+    /// doesn't cover anything on the map
+    pub fn with_outside(mut self, outside: bool) -> Compass {
+        self.outside = outside;
+        self
+    }
+
+    pub fn corner(&self) -> Corner {
+        self.corner
+    }
+
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    pub fn is_outside(&self) -> bool {
+        self.outside
+    }
+
+    /// Whether the rose is on the left of the map
+    pub fn is_left(&self) -> bool {
+        matches!(self.corner, Corner::TopLeft | Corner::BottomLeft)
+    }
+
+    /// Turn an offset from the centre of the rose, drawn with north at the
+    /// top, so that north points the right way
+    pub fn rotate(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (x * cos - y * sin, x * sin + y * cos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rotation_is_normalised() {
+        assert_eq!(Compass::new(Corner::TopLeft, 450.0).rotation(), 90.0);
+        assert_eq!(Compass::new(Corner::TopLeft, 360.0).rotation(), 0.0);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let north = (0.0, -1.0);
+        assert_close(Compass::new(Corner::TopLeft, 0.0).rotate(north), north);
+        assert_close(
+            Compass::new(Corner::TopLeft, 90.0).rotate(north),
+            (1.0, 0.0),
+        );
+        assert_close(
+            Compass::new(Corner::TopLeft, 180.0).rotate(north),
+            (0.0, 1.0),
+        );
+        assert_close(
+            Compass::new(Corner::TopLeft, 270.0).rotate(north),
+            (-1.0, 0.0),
+        );
+    }
+}
@@ -38,9 +38,16 @@ pub enum CompileErrorType {
     NoGridDimensions,
     OutOfBounds,
     InvalidOrientation,
+    InvalidRange,
+    UndefinedGlyph(String),
+    DuplicateGlyph(String),
+    UnterminatedString,
+    StampNotFound(String),
+    InvalidStamp(String),
+    InvalidSize,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct SyntaxError {
     expected: TokenType,
     actual: TokenType,
@@ -51,11 +58,11 @@ impl SyntaxError {
         SyntaxError { expected, actual }
     }
 
-    pub fn expected(&self) -> TokenType {
-        self.expected
+    pub fn expected(&self) -> &TokenType {
+        &self.expected
     }
 
-    pub fn actual(&self) -> TokenType {
-        self.actual
+    pub fn actual(&self) -> &TokenType {
+        &self.actual
     }
 }
@@ -8,23 +8,146 @@
  * Copyright (c) 2024 David Jackson
  */
 
+use crate::ascii::map_to_ascii;
+use crate::ast::AbstractSyntaxTree;
 use crate::compile_error::{CompileError, CompileErrorType};
+use crate::dot::{map_to_dot, rooms_to_dot};
+use crate::formatter::print_ast;
 use crate::generator::generate_map;
-use crate::map::map_to_svg;
+use crate::json::map_to_json;
+use crate::map::{map_to_pdf, map_to_pdf_tiles, map_to_svg_with_options, Map, RenderOptions};
 use crate::parser::parse;
+use crate::print::Paper;
+use crate::tmx::map_to_tmx;
+use std::path::Path;
 
 pub fn compile_svg(input: &str, dim: usize) -> String {
-    match parse(input) {
-        Ok(ast) => match generate_map(&ast) {
-            Ok(map) => map_to_svg(&map, dim),
-            Err(e) => format_compile_error(e),
-        },
-        Err(e) => format_compile_error(e),
+    compile_svg_relative_to(input, dim, Path::new(""))
+}
+
+/// Compile a map, resolving the files referenced by stamps relative to
+/// `base_dir`, which is normally the directory containing the map file
+pub fn compile_svg_relative_to(input: &str, dim: usize, base_dir: &Path) -> String {
+    compile_svg_with_options(input, dim, base_dir, &RenderOptions::default())
+}
+
+pub fn compile_svg_with_options(
+    input: &str,
+    dim: usize,
+    base_dir: &Path,
+    options: &RenderOptions,
+) -> String {
+    match compile_map(input, base_dir) {
+        Ok(map) => map_to_svg_with_options(&map, dim, options),
+        Err(e) => e,
+    }
+}
+
+/// Compile a map to a single-page PDF, where a cell is `dim` points across,
+/// or return the compile error message
+pub fn compile_pdf(
+    input: &str,
+    dim: usize,
+    base_dir: &Path,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, String> {
+    let map = compile_map(input, base_dir)?;
+    check_no_stamps(&map)?;
+    Ok(map_to_pdf(&map, dim, options))
+}
+
+/// Compile a map to a PDF for printing at the paper's cell size, tiled
+/// across as many pages as it needs, or return the compile error message
+pub fn compile_pdf_tiles(
+    input: &str,
+    base_dir: &Path,
+    options: &RenderOptions,
+    paper: Paper,
+) -> Result<Vec<u8>, String> {
+    let map = compile_map(input, base_dir)?;
+    check_no_stamps(&map)?;
+    Ok(map_to_pdf_tiles(&map, options, paper))
+}
+
+/// Stamps are SVG documents, which can't be drawn in PDFs, so refuse to
+/// print a map with any rather than silently leave them out
+fn check_no_stamps(map: &Map) -> Result<(), String> {
+    match map.stamps().first() {
+        Some(stamp) => Err(format!(
+            "ERROR: Stamps can't be drawn in PDFs: \"{}\"",
+            stamp.path()
+        )),
+        None => Ok(()),
     }
 }
 
+/// Compile a map to a text drawing, with its columns and rows labelled if
+/// `labels` is set, or return the compile error message
+pub fn compile_ascii(input: &str, base_dir: &Path, labels: bool) -> Result<String, String> {
+    let map = compile_map(input, base_dir)?;
+    Ok(map_to_ascii(&map, labels))
+}
+
+/// Compile a map to a JSON description of its walls and entities, or return
+/// the compile error message
+pub fn compile_json(input: &str, base_dir: &Path) -> Result<String, String> {
+    let map = compile_map(input, base_dir)?;
+    Ok(map_to_json(&map))
+}
+
+/// Compile a map to a Tiled (TMX) map with tiles `dim` pixels square, or
+/// return the compile error message
+pub fn compile_tmx(input: &str, dim: usize, base_dir: &Path) -> Result<String, String> {
+    let map = compile_map(input, base_dir)?;
+    Ok(map_to_tmx(&map, dim))
+}
+
+/// Compile a map to a Graphviz DOT graph of its walls, or return the compile
+/// error message
+pub fn compile_dot(input: &str, base_dir: &Path) -> Result<String, String> {
+    let map = compile_map(input, base_dir)?;
+    Ok(map_to_dot(&map))
+}
+
+/// Compile a map to a Graphviz DOT graph of which of its rooms lead to which,
+/// or return the compile error message
+pub fn compile_rooms_dot(input: &str, base_dir: &Path) -> Result<String, String> {
+    let map = compile_map(input, base_dir)?;
+    Ok(rooms_to_dot(&map))
+}
+
+/// Rewrite map source in the canonical format, keeping its comments, or
+/// return the compile error message if it can't be parsed
+pub fn format_source(input: &str) -> Result<String, String> {
+    let ast = parse_source(input)?;
+    Ok(print_ast(&ast))
+}
+
+/// Parse map source into a syntax tree, keeping its comments so that it can
+/// be printed again with `print_ast`, or return the compile error message
+pub fn parse_source(input: &str) -> Result<AbstractSyntaxTree, String> {
+    parse(input).map_err(format_compile_error)
+}
+
+/// Compile a map without drawing it, so that it can be drawn with any
+/// renderer, or return the compile error message
+pub fn compile_map(input: &str, base_dir: &Path) -> Result<Map, String> {
+    let ast = parse(input).map_err(format_compile_error)?;
+    generate_map(&ast, base_dir).map_err(format_compile_error)
+}
+
 fn format_compile_error(err: CompileError) -> String {
-    let message = match err.error_type {
+    format!(
+        "[{},{}] ERROR: {}",
+        err.location.line,
+        err.location.col,
+        error_message(&err.error_type)
+    )
+}
+
+/// Describe a compile error, without saying where it is
+pub(crate) fn error_message(error_type: &CompileErrorType) -> String {
+    let message = match error_type {
         CompileErrorType::SyntaxError(e) => {
             &format!("Expected {:?}, got {:?}", e.expected(), e.actual())
         }
@@ -39,9 +162,13 @@ fn format_compile_error(err: CompileError) -> String {
         CompileErrorType::NoGridDimensions => "No grid dimensions",
         CompileErrorType::OutOfBounds => "Out-of-bounds point",
         CompileErrorType::InvalidOrientation => "Invalid orientation",
+        CompileErrorType::InvalidRange => "Invalid cell range",
+        CompileErrorType::UndefinedGlyph(name) => &format!("Undefined glyph: \"{}\"", name),
+        CompileErrorType::DuplicateGlyph(name) => &format!("Duplicate glyph: \"{}\"", name),
+        CompileErrorType::UnterminatedString => "Unterminated string",
+        CompileErrorType::StampNotFound(path) => &format!("Stamp not found: \"{}\"", path),
+        CompileErrorType::InvalidStamp(path) => &format!("Invalid stamp: \"{}\"", path),
+        CompileErrorType::InvalidSize => "Invalid size",
     };
-    format!(
-        "[{},{}] ERROR: {}",
-        err.location.line, err.location.col, message
-    )
+    message.to_string()
 }
@@ -0,0 +1,413 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::ast::{
+    AbstractSyntaxTree, AstNode, AstNodeType, EntityNode, GridDimensionsNode, ScaleNode, ShapeNode,
+    StampNode,
+};
+use crate::formatter::print_ast;
+use crate::map::Map;
+use crate::points::Point;
+use crate::shapes::{Line, LineOrientation, Rect, Shape, ShapeBoolean};
+use crate::source_location::SourceLocation;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Write the source for a map, drawing its walls with as few `rect` and
+/// `line` commands as it can find
+pub fn map_to_source(map: &Map) -> String {
+    print_ast(&decompile(map))
+}
+
+/// Build a syntax tree that generates the same map. Walls are covered with
+/// whole rooms where their four sides are all walls, and straight lines for
+/// the rest.
+pub fn decompile(map: &Map) -> AbstractSyntaxTree {
+    let mut ast = AbstractSyntaxTree::new();
+    let add =
+        |ast: &mut AbstractSyntaxTree, node_type| ast.add_node(AstNode::new(node_type, NOWHERE));
+
+    add(
+        &mut ast,
+        AstNodeType::GridDimensions(GridDimensionsNode::new(
+            map.width() as u32,
+            map.height() as u32,
+        )),
+    );
+    if let Some(title) = map.title() {
+        add(&mut ast, AstNodeType::Title(title.to_string()));
+    }
+    if let Some(scale) = map.scale() {
+        let scale = ScaleNode {
+            amount: scale.amount(),
+            unit: scale.unit().to_string(),
+        };
+        add(&mut ast, AstNodeType::Scale(scale));
+    }
+    if let Some(compass) = map.compass() {
+        add(&mut ast, AstNodeType::Compass(*compass));
+    }
+
+    let mut index = 0;
+    while let Some(glyph) = map.glyph(index) {
+        add(&mut ast, AstNodeType::Glyph(glyph.clone()));
+        index += 1;
+    }
+
+    for cover in cover_walls(map) {
+        add(&mut ast, AstNodeType::Shape(cover.shape_node(map)));
+    }
+
+    for entity in map.entities() {
+        let node_type = match entity.shape() {
+            Shape::Stamp(index) => AstNodeType::Stamp(StampNode {
+                path: map.stamp(index).unwrap().path().to_string(),
+                point: entity.point(),
+                size: entity.width(),
+            }),
+            shape => AstNodeType::Entity(EntityNode {
+                shape,
+                point: entity.point(),
+                position: entity.position(),
+                width: entity.width(),
+                height: entity.height(),
+            }),
+        };
+        add(&mut ast, node_type);
+    }
+    ast
+}
+
+/// Decompiled statements don't come from any source
+const NOWHERE: SourceLocation = SourceLocation { line: 0, col: 0 };
+
+/// A wall one cell long, from a grid point to the point to its right or
+/// below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Edge {
+    Across(usize, usize),
+    Down(usize, usize),
+}
+
+/// A command that draws some of the walls
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cover {
+    Rect {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    Across {
+        x: usize,
+        y: usize,
+        length: usize,
+    },
+    Down {
+        x: usize,
+        y: usize,
+        length: usize,
+    },
+}
+
+impl Cover {
+    fn edges(&self) -> Vec<Edge> {
+        match *self {
+            Cover::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let mut edges = Vec::new();
+                for i in x..x + width {
+                    edges.push(Edge::Across(i, y));
+                    edges.push(Edge::Across(i, y + height));
+                }
+                for j in y..y + height {
+                    edges.push(Edge::Down(x, j));
+                    edges.push(Edge::Down(x + width, j));
+                }
+                edges
+            }
+            Cover::Across { x, y, length } => (x..x + length).map(|i| Edge::Across(i, y)).collect(),
+            Cover::Down { x, y, length } => (y..y + length).map(|j| Edge::Down(x, j)).collect(),
+        }
+    }
+
+    /// Shorten a line to the part that draws the walls that are left
+    fn trim(self, uncovered: &HashSet<Edge>) -> Cover {
+        let edges = self.edges();
+        let first = edges.iter().position(|e| uncovered.contains(e));
+        let last = edges.iter().rposition(|e| uncovered.contains(e));
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self,
+        };
+        let length = last - first + 1;
+        match self {
+            Cover::Across { x, y, .. } => Cover::Across {
+                x: x + first,
+                y,
+                length,
+            },
+            Cover::Down { x, y, .. } => Cover::Down {
+                x,
+                y: y + first,
+                length,
+            },
+            rect => rect,
+        }
+    }
+
+    /// The command for the walls. Lines along the bottom and right edges of
+    /// the map are drawn along the cells inside it.
+    fn shape_node(&self, map: &Map) -> ShapeNode {
+        let line = |orientation, x, y, length| {
+            ShapeNode::Line(Line::new(
+                orientation,
+                Point::new(x, y),
+                length,
+                ShapeBoolean::Or,
+            ))
+        };
+        match *self {
+            Cover::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => ShapeNode::Rect(Rect::new(Point::new(x, y), width, height, ShapeBoolean::Or)),
+            Cover::Across { x, y, length } if y == map.height() && y > 0 => {
+                line(LineOrientation::Bottom, x, y - 1, length)
+            }
+            Cover::Across { x, y, length } => line(LineOrientation::Top, x, y, length),
+            Cover::Down { x, y, length } if x == map.width() && x > 0 => {
+                line(LineOrientation::Right, x - 1, y, length)
+            }
+            Cover::Down { x, y, length } => line(LineOrientation::Left, x, y, length),
+        }
+    }
+
+    /// Where the command starts, for ordering the commands: rooms first, then
+    /// lines across, then lines down, each from the top-left
+    fn sort_key(&self) -> (u8, usize, usize) {
+        match *self {
+            Cover::Rect { x, y, .. } => (0, y, x),
+            Cover::Across { x, y, .. } => (1, y, x),
+            Cover::Down { x, y, .. } => (2, x, y),
+        }
+    }
+}
+
+/// Choose commands that draw exactly the walls of a map. Finding the fewest
+/// commands is a set cover problem, so this takes the usual greedy
+/// approach of repeatedly choosing the command that draws the most walls
+/// that haven't been drawn yet.
+fn cover_walls(map: &Map) -> Vec<Cover> {
+    let walls = wall_edges(map);
+    let candidates = candidates(map, &walls);
+
+    // The number of walls a command draws only goes down as others are
+    // chosen, so counts in the queue are upper bounds that are checked
+    // before a command is chosen. Lines come before rooms in the candidates
+    // so that they win ties.
+    let mut uncovered = walls;
+    let mut queue: BinaryHeap<(usize, Reverse<usize>)> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, cover)| (cover.edges().len(), Reverse(i)))
+        .collect();
+    let mut chosen = Vec::new();
+    while let Some((count, Reverse(i))) = queue.pop() {
+        if uncovered.is_empty() {
+            break;
+        }
+        let cover = candidates[i];
+        let current = cover
+            .edges()
+            .iter()
+            .filter(|e| uncovered.contains(e))
+            .count();
+        if current == 0 {
+            continue;
+        }
+        if current < count {
+            queue.push((current, Reverse(i)));
+            continue;
+        }
+        let cover = cover.trim(&uncovered);
+        for edge in cover.edges() {
+            uncovered.remove(&edge);
+        }
+        chosen.push(cover);
+    }
+    chosen.sort_by_key(|cover| cover.sort_key());
+    chosen
+}
+
+fn wall_edges(map: &Map) -> HashSet<Edge> {
+    map.edges()
+        .into_iter()
+        .map(|(p1, p2)| {
+            let (p1, p2) = if (p1.y(), p1.x()) <= (p2.y(), p2.x()) {
+                (p1, p2)
+            } else {
+                (p2, p1)
+            };
+            if p1.y() == p2.y() {
+                Edge::Across(p1.x(), p1.y())
+            } else {
+                Edge::Down(p1.x(), p1.y())
+            }
+        })
+        .collect()
+}
+
+/// The commands worth considering: the longest straight lines of walls, and
+/// every room whose four sides are all walls
+fn candidates(map: &Map, walls: &HashSet<Edge>) -> Vec<Cover> {
+    let across = |x, y| walls.contains(&Edge::Across(x, y));
+    let down = |x, y| walls.contains(&Edge::Down(x, y));
+    let mut candidates = Vec::new();
+
+    for (start, end) in map.straight_walls() {
+        let (x, y) = (start.x(), start.y());
+        candidates.push(if y == end.y() {
+            Cover::Across {
+                x,
+                y,
+                length: end.x() - x,
+            }
+        } else {
+            Cover::Down {
+                x,
+                y,
+                length: end.y() - y,
+            }
+        });
+    }
+
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let mut width = 1;
+            while x + width <= map.width() && across(x + width - 1, y) {
+                let mut height = 1;
+                while y + height <= map.height()
+                    && down(x, y + height - 1)
+                    && down(x + width, y + height - 1)
+                {
+                    if (x..x + width).all(|i| across(i, y + height)) {
+                        candidates.push(Cover::Rect {
+                            x,
+                            y,
+                            width,
+                            height,
+                        });
+                    }
+                    height += 1;
+                }
+                width += 1;
+            }
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityPosition};
+    use crate::generator::generate_map;
+    use crate::parser::parse;
+    use std::path::Path;
+
+    fn compile(source: &str) -> Map {
+        generate_map(&parse(source).expect("Bad parse"), Path::new(".")).expect("Bad map")
+    }
+
+    fn assert_same_walls(a: &Map, b: &Map) {
+        assert_eq!(wall_edges(a), wall_edges(b));
+    }
+
+    #[test]
+    fn test_room_is_one_rect() {
+        let map = compile("grid 4, 4\nline along top from 1, 1 length 2\nline along bottom from 1, 2 length 2\nline along left from 1, 1 length 2\nline along right from 2, 1 length 2\n");
+        assert_eq!(
+            map_to_source(&map),
+            "grid 4, 4\nrect at 1, 1 width 2 height 2\n"
+        );
+    }
+
+    #[test]
+    fn test_divided_room() {
+        let map = compile(
+            "grid 2, 1\nrect at 0, 0 width 2 height 1\nline along left from 1, 0 length 1\n",
+        );
+        let expected =
+            "grid 2, 1\nrect at 0, 0 width 2 height 1\nline along left from 1, 0 length 1\n";
+        assert_eq!(map_to_source(&map), expected);
+    }
+
+    #[test]
+    fn test_xor_walls() {
+        let source = "grid 5, 5\nrect at 0, 0 width 5 height 5\nrect at 1, 1 width 2 height 2\nxor line along top from 1, 1 length 1\nline along bottom from 4, 4 length 1\n";
+        let map = compile(source);
+        let decompiled = map_to_source(&map);
+        assert!(!decompiled.contains("xor"));
+        assert_same_walls(&compile(&decompiled), &map);
+    }
+
+    #[test]
+    fn test_walls_along_the_edges() {
+        let map = compile("grid 3, 2\nline along bottom from 0, 1 length 3\nline along right from 2, 0 length 2\n");
+        let decompiled = map_to_source(&map);
+        assert!(decompiled.contains("line along bottom from 0, 1 length 3"));
+        assert!(decompiled.contains("line along right from 2, 0 length 2"));
+        assert_same_walls(&compile(&decompiled), &map);
+    }
+
+    #[test]
+    fn test_entities_and_header() {
+        let source = "\
+grid 3, 3
+title \"Crypt\"
+scale 5 ft
+compass at top left rotate 45
+
+glyph coffin { path 0,0 1,0 1,1 0,1 close }
+
+entity coffin within 0, 0 to 1, 0
+entity circle at 2, 2 radius 1
+entity pillar within 2, 2
+";
+        let map = compile(source);
+        assert_eq!(map_to_source(&map), source);
+    }
+
+    #[test]
+    fn test_built_map() {
+        let mut map = Map::new(2, 2);
+        map.connect(Point::new(0, 0), Point::new(1, 0));
+        map.connect(Point::new(1, 0), Point::new(1, 1));
+        map.add_entity(Entity::new(
+            Shape::Bed,
+            Point::new(1, 1),
+            EntityPosition::Within,
+        ));
+        let expected = "\
+grid 2, 2
+line along top from 0, 0 length 1
+line along left from 1, 0 length 1
+entity bed within 1, 1
+";
+        assert_eq!(map_to_source(&map), expected);
+    }
+}
@@ -0,0 +1,333 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::entities::EntityPosition;
+use crate::map::{column_label, Map};
+use crate::points::Point;
+use crate::shapes::Shape;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Write the wall graph of a map in Graphviz's DOT language, with each grid
+/// point pinned to its place on the grid so that `neato` lays it out as it
+/// is drawn. Only the points that walls meet at are included.
+pub fn map_to_dot(map: &Map) -> String {
+    let mut edges: Vec<(Point, Point)> = map
+        .edges()
+        .into_iter()
+        .map(|(p1, p2)| {
+            if (p1.y(), p1.x()) <= (p2.y(), p2.x()) {
+                (p1, p2)
+            } else {
+                (p2, p1)
+            }
+        })
+        .collect();
+    edges.sort_by_key(|(p1, p2)| (p1.y(), p1.x(), p2.y(), p2.x()));
+    let mut points: Vec<Point> = edges.iter().flat_map(|(p1, p2)| [*p1, *p2]).collect();
+    points.sort_by_key(|p| (p.y(), p.x()));
+    points.dedup();
+
+    let mut dot = String::from("graph walls {\n  layout=neato\n  node [shape=point]\n");
+    for p in points {
+        // DOT's y axis points up
+        dot.push_str(&format!(
+            "  \"{},{}\" [pos=\"{},-{}!\"]\n",
+            p.x(),
+            p.y(),
+            p.x(),
+            p.y()
+        ));
+    }
+    for (p1, p2) in edges {
+        dot.push_str(&format!(
+            "  \"{},{}\" -- \"{},{}\"\n",
+            p1.x(),
+            p1.y(),
+            p2.x(),
+            p2.y()
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Write which rooms of a map lead to which in Graphviz's DOT language.
+/// Rooms are the areas of cells that can be reached from each other without
+/// crossing a wall, and everything that isn't walled off from the edge of the
+/// map is outside. Cells with doors in them join the rooms on either side,
+/// shown with solid lines, and rooms that are only next to each other are
+/// joined with dashed lines.
+pub fn rooms_to_dot(map: &Map) -> String {
+    let rooms = Rooms::new(map);
+
+    let mut dot = String::from("graph rooms {\n  node [shape=box]\n");
+    for (room, first) in rooms.first_cells.iter() {
+        let label = match room {
+            Room::Outside => "Outside".to_string(),
+            Room::Inside(_) => {
+                let size = rooms.sizes[room];
+                format!(
+                    "{}{}\\n{} {}",
+                    column_label(first.x()),
+                    first.y() + 1,
+                    size,
+                    if size == 1 { "cell" } else { "cells" }
+                )
+            }
+        };
+        dot.push_str(&format!("  {} [label=\"{}\"]\n", room.id(), label));
+    }
+    for (r1, r2) in rooms.doors.iter() {
+        dot.push_str(&format!("  {} -- {} [label=\"door\"]\n", r1.id(), r2.id()));
+    }
+    for (r1, r2) in rooms.walls.difference(&rooms.doors) {
+        dot.push_str(&format!("  {} -- {} [style=dashed]\n", r1.id(), r2.id()));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Room {
+    Outside,
+    /// Rooms are numbered from 1, in the order their top-left cells come in
+    /// from the top-left of the map
+    Inside(usize),
+}
+
+impl Room {
+    fn id(&self) -> String {
+        match self {
+            Room::Outside => "outside".to_string(),
+            Room::Inside(n) => format!("room{}", n),
+        }
+    }
+}
+
+/// The rooms of a map, and how they are joined
+struct Rooms {
+    first_cells: BTreeMap<Room, Point>,
+    sizes: BTreeMap<Room, usize>,
+    doors: BTreeSet<(Room, Room)>,
+    walls: BTreeSet<(Room, Room)>,
+}
+
+impl Rooms {
+    fn new(map: &Map) -> Rooms {
+        let (width, height) = (map.width(), map.height());
+        let door_cells: HashSet<Point> = map
+            .entities()
+            .iter()
+            .filter(|e| e.shape() == Shape::Door && matches!(e.position(), EntityPosition::Within))
+            .flat_map(|e| {
+                let p = e.point();
+                (p.x()..p.x() + e.width())
+                    .flat_map(move |x| (p.y()..p.y() + e.height()).map(move |y| Point::new(x, y)))
+            })
+            .collect();
+
+        // Flood fill the cells, stopping at walls and doors
+        let mut areas: Vec<Option<usize>> = vec![None; width * height];
+        let mut outside_areas = HashSet::new();
+        let mut area_count = 0;
+        for y in 0..height {
+            for x in 0..width {
+                let start = Point::new(x, y);
+                if areas[y * width + x].is_some() || door_cells.contains(&start) {
+                    continue;
+                }
+                let area = area_count;
+                area_count += 1;
+                areas[y * width + x] = Some(area);
+                let mut stack = vec![start];
+                while let Some(cell) = stack.pop() {
+                    if leads_off_map(map, cell) {
+                        outside_areas.insert(area);
+                    }
+                    for next in open_neighbours(map, cell) {
+                        let i = next.y() * width + next.x();
+                        if areas[i].is_none() && !door_cells.contains(&next) {
+                            areas[i] = Some(area);
+                            stack.push(next);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Number the rooms in the order they were found
+        let mut room_numbers = BTreeMap::new();
+        let room = |area: usize, room_numbers: &mut BTreeMap<usize, usize>| {
+            if outside_areas.contains(&area) {
+                Room::Outside
+            } else {
+                let next = room_numbers.len() + 1;
+                Room::Inside(*room_numbers.entry(area).or_insert(next))
+            }
+        };
+        let mut rooms = Rooms {
+            first_cells: BTreeMap::new(),
+            sizes: BTreeMap::new(),
+            doors: BTreeSet::new(),
+            walls: BTreeSet::new(),
+        };
+        let mut cell_rooms: Vec<Option<Room>> = vec![None; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(area) = areas[y * width + x] {
+                    let r = room(area, &mut room_numbers);
+                    cell_rooms[y * width + x] = Some(r);
+                    rooms.first_cells.entry(r).or_insert(Point::new(x, y));
+                    *rooms.sizes.entry(r).or_insert(0) += 1;
+                }
+            }
+        }
+        let cell_room = |p: Point| cell_rooms[p.y() * width + p.x()];
+
+        for y in 0..height {
+            for x in 0..width {
+                let cell = Point::new(x, y);
+                if door_cells.contains(&cell) {
+                    let joined: BTreeSet<Room> = open_neighbours(map, cell)
+                        .into_iter()
+                        .filter_map(cell_room)
+                        .collect();
+                    let joined: Vec<Room> = joined.into_iter().collect();
+                    for (i, r1) in joined.iter().enumerate() {
+                        for r2 in joined[i + 1..].iter() {
+                            rooms.doors.insert((*r1, *r2));
+                        }
+                    }
+                    continue;
+                }
+                // Rooms on either side of a wall
+                for next in [Point::new(x + 1, y), Point::new(x, y + 1)] {
+                    if next.x() >= width || next.y() >= height {
+                        continue;
+                    }
+                    if let (Some(r1), Some(r2)) = (cell_room(cell), cell_room(next)) {
+                        if r1 != r2 {
+                            rooms.walls.insert((r1.min(r2), r1.max(r2)));
+                        }
+                    }
+                }
+            }
+        }
+        rooms
+    }
+}
+
+/// The cells next to a cell that aren't walled off from it
+fn open_neighbours(map: &Map, cell: Point) -> Vec<Point> {
+    let (x, y) = (cell.x(), cell.y());
+    let mut neighbours = Vec::new();
+    if x > 0 && !map.are_connected(Point::new(x, y), Point::new(x, y + 1)) {
+        neighbours.push(Point::new(x - 1, y));
+    }
+    if x + 1 < map.width() && !map.are_connected(Point::new(x + 1, y), Point::new(x + 1, y + 1)) {
+        neighbours.push(Point::new(x + 1, y));
+    }
+    if y > 0 && !map.are_connected(Point::new(x, y), Point::new(x + 1, y)) {
+        neighbours.push(Point::new(x, y - 1));
+    }
+    if y + 1 < map.height() && !map.are_connected(Point::new(x, y + 1), Point::new(x + 1, y + 1)) {
+        neighbours.push(Point::new(x, y + 1));
+    }
+    neighbours
+}
+
+/// Whether a cell is on the edge of the map without a wall along that edge
+fn leads_off_map(map: &Map, cell: Point) -> bool {
+    let (x, y) = (cell.x(), cell.y());
+    (x == 0 && !map.are_connected(Point::new(x, y), Point::new(x, y + 1)))
+        || (x + 1 == map.width()
+            && !map.are_connected(Point::new(x + 1, y), Point::new(x + 1, y + 1)))
+        || (y == 0 && !map.are_connected(Point::new(x, y), Point::new(x + 1, y)))
+        || (y + 1 == map.height()
+            && !map.are_connected(Point::new(x, y + 1), Point::new(x + 1, y + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Entity;
+
+    fn rect(map: &mut Map, x: usize, y: usize, width: usize, height: usize) {
+        for i in x..x + width {
+            map.connect(Point::new(i, y), Point::new(i + 1, y));
+            map.connect(Point::new(i, y + height), Point::new(i + 1, y + height));
+        }
+        for j in y..y + height {
+            map.connect(Point::new(x, j), Point::new(x, j + 1));
+            map.connect(Point::new(x + width, j), Point::new(x + width, j + 1));
+        }
+    }
+
+    #[test]
+    fn test_wall_graph() {
+        let mut map = Map::new(2, 2);
+        map.connect(Point::new(1, 0), Point::new(0, 0));
+        map.connect(Point::new(1, 0), Point::new(1, 1));
+        let expected = "\
+graph walls {
+  layout=neato
+  node [shape=point]
+  \"0,0\" [pos=\"0,-0!\"]
+  \"1,0\" [pos=\"1,-0!\"]
+  \"1,1\" [pos=\"1,-1!\"]
+  \"0,0\" -- \"1,0\"
+  \"1,0\" -- \"1,1\"
+}
+";
+        assert_eq!(map_to_dot(&map), expected);
+    }
+
+    #[test]
+    fn test_rooms_joined_by_a_door() {
+        // Two rooms with a corridor between them, blocked by a door
+        let mut map = Map::new(7, 3);
+        rect(&mut map, 0, 0, 3, 3);
+        rect(&mut map, 4, 0, 3, 3);
+        map.disconnect(Point::new(3, 1), Point::new(3, 2));
+        map.disconnect(Point::new(4, 1), Point::new(4, 2));
+        map.connect(Point::new(3, 1), Point::new(4, 1));
+        map.connect(Point::new(3, 2), Point::new(4, 2));
+        map.add_entity(Entity::new(
+            Shape::Door,
+            Point::new(3, 1),
+            EntityPosition::Within,
+        ));
+        let dot = rooms_to_dot(&map);
+        assert!(dot.contains("room1 [label=\"A1\\n9 cells\"]"));
+        assert!(dot.contains("room2 [label=\"E1\\n9 cells\"]"));
+        assert!(dot.contains("room1 -- room2 [label=\"door\"]"));
+        assert!(!dot.contains("room1 -- room2 [style=dashed]"));
+    }
+
+    #[test]
+    fn test_neighbouring_rooms() {
+        let mut map = Map::new(4, 4);
+        rect(&mut map, 1, 1, 2, 2);
+        map.connect(Point::new(2, 1), Point::new(2, 2));
+        map.connect(Point::new(2, 2), Point::new(2, 3));
+        let expected = "\
+graph rooms {
+  node [shape=box]
+  outside [label=\"Outside\"]
+  room1 [label=\"B2\\n2 cells\"]
+  room2 [label=\"C2\\n2 cells\"]
+  outside -- room1 [style=dashed]
+  outside -- room2 [style=dashed]
+  room1 -- room2 [style=dashed]
+}
+";
+        assert_eq!(rooms_to_dot(&map), expected);
+    }
+}
@@ -11,11 +11,14 @@
 use super::points::Point;
 use super::shapes::Shape;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
     shape: Shape,
     point: Point,
     position: EntityPosition,
+    width: usize,
+    height: usize,
 }
 
 impl Entity {
@@ -24,6 +27,20 @@ impl Entity {
             shape,
             point,
             position,
+            width: 1,
+            height: 1,
+        }
+    }
+
+    /// Create an entity that fills the block of cells starting at `point`,
+    /// `width` cells across and `height` cells down
+    pub fn spanning(shape: Shape, point: Point, width: usize, height: usize) -> Entity {
+        Entity {
+            shape,
+            point,
+            position: EntityPosition::Within,
+            width,
+            height,
         }
     }
 
@@ -38,9 +55,19 @@ impl Entity {
     pub fn position(&self) -> EntityPosition {
         self.position
     }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum EntityPosition {
     Within,
     At,
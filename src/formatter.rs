@@ -0,0 +1,301 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::ast::{AbstractSyntaxTree, AstNode, AstNodeType, ShapeNode, Trivia};
+use crate::compass::Corner;
+use crate::entities::EntityPosition;
+use crate::shapes::{LineOrientation, Shape, ShapeBoolean};
+
+/// The sections of a formatted map file, in order. Statements that only
+/// describe the map come first, then glyph definitions, then everything that
+/// is drawn, which keeps its order because walls drawn with `xor` depend on
+/// what was drawn before them.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Section {
+    Header,
+    Glyphs,
+    Body,
+}
+
+/// Print the source for a syntax tree in the canonical format, keeping its
+/// comments
+pub fn print_ast(ast: &AbstractSyntaxTree) -> String {
+    let glyph_names: Vec<&str> = ast
+        .nodes()
+        .filter_map(|node| match node.node_type() {
+            AstNodeType::Glyph(glyph) => Some(glyph.name()),
+            _ => None,
+        })
+        .collect();
+
+    let mut nodes: Vec<&AstNode> = ast.nodes().collect();
+    nodes.sort_by_key(|node| section(node) as u8);
+
+    // A header that is only the grid size isn't set apart, unless the map
+    // already does that itself
+    let lone_grid = nodes
+        .iter()
+        .filter(|node| section(node) == Section::Header)
+        .count()
+        == 1;
+
+    let mut source = String::new();
+    let mut previous: Option<Section> = None;
+    for node in nodes {
+        let section = section(node);
+        let mut leading = node.leading();
+        let after_lone_grid = lone_grid && previous == Some(Section::Header);
+        if previous != Some(section) && !after_lone_grid {
+            // Sections are always separated by exactly one blank line
+            if previous.is_some() {
+                source.push('\n');
+            }
+            while let Some((Trivia::BlankLine, rest)) = leading.split_first() {
+                leading = rest;
+            }
+        }
+        print_trivia(&mut source, leading);
+        source.push_str(&statement(node.node_type(), &glyph_names));
+        if let Some(comment) = node.trailing_comment() {
+            source.push_str(&format!(" #{}", comment.trim_end()));
+        }
+        source.push('\n');
+        previous = Some(section);
+    }
+    print_trivia(&mut source, ast.trailing());
+    source
+}
+
+fn section(node: &AstNode) -> Section {
+    match node.node_type() {
+        AstNodeType::GridDimensions(_)
+        | AstNodeType::Title(_)
+        | AstNodeType::Scale(_)
+        | AstNodeType::Compass(_) => Section::Header,
+        AstNodeType::Glyph(_) => Section::Glyphs,
+        AstNodeType::Shape(_) | AstNodeType::Entity(_) | AstNodeType::Stamp(_) => Section::Body,
+    }
+}
+
+fn print_trivia(source: &mut String, trivia: &[Trivia]) {
+    for t in trivia {
+        match t {
+            Trivia::Comment(text) => source.push_str(&format!("#{}\n", text.trim_end())),
+            Trivia::BlankLine => source.push('\n'),
+        }
+    }
+}
+
+fn statement(node_type: &AstNodeType, glyph_names: &[&str]) -> String {
+    match node_type {
+        AstNodeType::GridDimensions(dims) => format!("grid {}, {}", dims.width(), dims.height()),
+        AstNodeType::Shape(ShapeNode::Rect(rect)) => format!(
+            "{}rect at {}, {} width {} height {}",
+            boolean_op(rect.boolean_op()),
+            rect.point().x(),
+            rect.point().y(),
+            rect.width(),
+            rect.height()
+        ),
+        AstNodeType::Shape(ShapeNode::Line(line)) => {
+            let side = match line.orientation() {
+                LineOrientation::Left => "left",
+                LineOrientation::Right => "right",
+                LineOrientation::Top => "top",
+                LineOrientation::Bottom => "bottom",
+            };
+            format!(
+                "{}line along {} from {}, {} length {}",
+                boolean_op(line.boolean_op()),
+                side,
+                line.start().x(),
+                line.start().y(),
+                line.length()
+            )
+        }
+        AstNodeType::Entity(entity) => {
+            let shape = match entity.shape {
+                Shape::Glyph(index) => glyph_names[index],
+                shape => shape.keyword().unwrap(),
+            };
+            let (x, y) = (entity.point.x(), entity.point.y());
+            match (entity.position, entity.shape) {
+                (EntityPosition::At, Shape::Circle(radius)) => {
+                    format!("entity {} at {}, {} radius {}", shape, x, y, radius)
+                }
+                _ if entity.width > 1 || entity.height > 1 => format!(
+                    "entity {} within {}, {} to {}, {}",
+                    shape,
+                    x,
+                    y,
+                    x + entity.width - 1,
+                    y + entity.height - 1
+                ),
+                _ => format!("entity {} within {}, {}", shape, x, y),
+            }
+        }
+        AstNodeType::Glyph(glyph) => {
+            let paths: Vec<String> = glyph
+                .paths()
+                .iter()
+                .map(|path| {
+                    let mut s = String::from("path");
+                    for (x, y) in path.points() {
+                        s.push_str(&format!(" {},{}", x, y));
+                    }
+                    if path.is_closed() {
+                        s.push_str(" close");
+                    }
+                    s
+                })
+                .collect();
+            format!("glyph {} {{ {} }}", glyph.name(), paths.join("; "))
+        }
+        AstNodeType::Stamp(stamp) => {
+            let mut s = format!(
+                "stamp \"{}\" within {}, {}",
+                stamp.path,
+                stamp.point.x(),
+                stamp.point.y()
+            );
+            if stamp.size != 1 {
+                s.push_str(&format!(" size {}", stamp.size));
+            }
+            s
+        }
+        AstNodeType::Title(title) => format!("title \"{}\"", title),
+        AstNodeType::Scale(scale) => format!("scale {} {}", scale.amount, scale.unit),
+        AstNodeType::Compass(compass) => {
+            let corner = match compass.corner() {
+                Corner::TopLeft => "top left",
+                Corner::TopRight => "top right",
+                Corner::BottomLeft => "bottom left",
+                Corner::BottomRight => "bottom right",
+            };
+            let mut s = format!("compass at {}", corner);
+            if compass.is_outside() {
+                s.push_str(" outside");
+            }
+            if compass.rotation() != 0.0 {
+                s.push_str(&format!(" rotate {}", compass.rotation()));
+            }
+            s
+        }
+    }
+}
+
+fn boolean_op(op: ShapeBoolean) -> &'static str {
+    match op {
+        ShapeBoolean::Or => "",
+        ShapeBoolean::Xor => "xor ",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn format(input: &str) -> String {
+        print_ast(&parse(input).expect("Bad parse"))
+    }
+
+    #[test]
+    fn test_spacing() {
+        let input = "grid   4,4\nrect at 1,1 width 2   height 2\n  entity circle at 2,2 radius 1\n";
+        let expected = "grid 4, 4\nrect at 1, 1 width 2 height 2\nentity circle at 2, 2 radius 1\n";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "\
+# The map
+grid 4, 4
+
+# Walls
+rect at 1, 1 width 2 height 2 # the room
+xor line along top from 1, 1 length 1
+
+
+# The end
+";
+        let expected = "\
+# The map
+grid 4, 4
+
+# Walls
+rect at 1, 1 width 2 height 2 # the room
+xor line along top from 1, 1 length 1
+
+# The end
+";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn test_lone_grid_keeps_its_own_spacing() {
+        let input = "grid 2, 2\nrect at 0, 0 width 1 height 1\n";
+        assert_eq!(format(input), input);
+        let input = "grid 2, 2\n\nrect at 0, 0 width 1 height 1\n";
+        assert_eq!(format(input), input);
+    }
+
+    #[test]
+    fn test_header_comes_first() {
+        let input = "\
+grid 4, 4
+rect at 0, 0 width 4 height 4
+glyph crate { path 0,0 1,0 1,1 0,1 close; path 0,0 1,1 }
+entity crate within 1,1 to 2,2
+# North is to the right
+compass at top right outside rotate 90
+title \"Hall\"
+";
+        let expected = "\
+grid 4, 4
+# North is to the right
+compass at top right outside rotate 90
+title \"Hall\"
+
+glyph crate { path 0,0 1,0 1,1 0,1 close; path 0,0 1,1 }
+
+rect at 0, 0 width 4 height 4
+entity crate within 1, 1 to 2, 2
+";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn test_glyph_comments_move_before_it() {
+        let input = "\
+grid 2, 2
+glyph g {
+  # left
+  path 0,0 0,1;
+  path 0.5,0 0.25,1 # right
+}
+";
+        let expected = "\
+grid 2, 2
+# left
+# right
+glyph g { path 0,0 0,1; path 0.5,0 0.25,1 }
+";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let input = "grid 3,3 # small\ntitle \"T\"\nscale 2.5 m\nstamp \"a.svg\" within 0,0 size 2\nentity x within 1,1\n";
+        let once = format(input);
+        assert_eq!(format(&once), once);
+    }
+}
@@ -10,15 +10,21 @@
 
 use crate::ast::AstNodeType;
 
-use crate::ast::{AbstractSyntaxTree, EntityNode, GridDimensionsNode, ShapeNode};
+use crate::ast::{AbstractSyntaxTree, EntityNode, GridDimensionsNode, ShapeNode, StampNode};
 use crate::compile_error::{CompileError, CompileErrorType};
-use crate::entities::Entity;
-use crate::map::Map;
+use crate::entities::{Entity, EntityPosition};
+use crate::map::{Map, MapScale};
 use crate::points::Point;
 use crate::shapes::{Line, LineOrientation, Rect, Shape, ShapeBoolean};
 use crate::source_location::SourceLocation;
-
-pub fn generate_map(ast: &AbstractSyntaxTree) -> Result<Map, CompileError> {
+use crate::stamps::Stamp;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Generate a map, resolving the files referenced by stamps relative to
+/// `base_dir`
+pub fn generate_map(ast: &AbstractSyntaxTree, base_dir: &Path) -> Result<Map, CompileError> {
     let dims = find_grid_dimensions(ast);
     if dims.is_none() {
         return Err(CompileError::new(CompileErrorType::NoGridDimensions, 1, 1));
@@ -41,6 +47,21 @@ pub fn generate_map(ast: &AbstractSyntaxTree) -> Result<Map, CompileError> {
             AstNodeType::Entity(entity_node) => {
                 handle_entity(&mut map, entity_node, ast_node.location())?;
             }
+            AstNodeType::Glyph(glyph) => {
+                map.add_glyph(glyph.clone());
+            }
+            AstNodeType::Stamp(stamp_node) => {
+                handle_stamp(&mut map, stamp_node, base_dir, ast_node.location())?;
+            }
+            AstNodeType::Title(title) => {
+                map.set_title(title.clone());
+            }
+            AstNodeType::Scale(scale) => {
+                map.set_scale(MapScale::new(scale.amount, &scale.unit));
+            }
+            AstNodeType::Compass(compass) => {
+                map.set_compass(*compass);
+            }
         }
     }
 
@@ -60,7 +81,11 @@ fn find_grid_dimensions(ast: &AbstractSyntaxTree) -> Option<&GridDimensionsNode>
     }
 }
 
-fn handle_rect(map: &mut Map, rect: &Rect, location: SourceLocation) -> Result<(), CompileError> {
+pub(crate) fn handle_rect(
+    map: &mut Map,
+    rect: &Rect,
+    location: SourceLocation,
+) -> Result<(), CompileError> {
     // Connect all the points on the perimiter of the rectangle
     let x = rect.point().x();
     let y = rect.point().y();
@@ -96,7 +121,11 @@ fn handle_rect(map: &mut Map, rect: &Rect, location: SourceLocation) -> Result<(
     Ok(())
 }
 
-fn handle_line(map: &mut Map, line: &Line, location: SourceLocation) -> Result<(), CompileError> {
+pub(crate) fn handle_line(
+    map: &mut Map,
+    line: &Line,
+    location: SourceLocation,
+) -> Result<(), CompileError> {
     let start = match line.orientation() {
         LineOrientation::Left | LineOrientation::Top => line.start(),
         LineOrientation::Right => line.start().right(),
@@ -129,13 +158,22 @@ fn handle_line(map: &mut Map, line: &Line, location: SourceLocation) -> Result<(
     Ok(())
 }
 
-fn handle_entity(
+pub(crate) fn handle_entity(
     map: &mut Map,
     entity_node: &EntityNode,
     location: SourceLocation,
 ) -> Result<(), CompileError> {
+    if matches!(entity_node.position, EntityPosition::Within) {
+        check_cells_in_bounds(
+            map,
+            entity_node.point,
+            entity_node.width,
+            entity_node.height,
+            location,
+        )?;
+    }
     match entity_node.shape {
-        Shape::Circle(r) => {
+        Shape::Circle(r) if matches!(entity_node.position, EntityPosition::At) => {
             // Check for out-of-bounds
             let center = entity_node.point;
             if r > center.x() {
@@ -146,20 +184,85 @@ fn handle_entity(
                 return Err(out_of_bounds(location));
             }
             let top = Point::new(center.x(), center.y() - r);
-            let right = Point::new(center.x() + r, center.y());
-            let bottom = Point::new(center.x() + r, center.y());
+            let (Some(right), Some(bottom)) =
+                (center.x().checked_add(r), center.y().checked_add(r))
+            else {
+                return Err(out_of_bounds(location));
+            };
+            let right = Point::new(right, center.y());
+            let bottom = Point::new(center.x(), bottom);
             let points = [center, left, top, right, bottom];
             if points.iter().any(|p| !map.point_exists(*p)) {
                 return Err(out_of_bounds(location));
             }
         }
-        Shape::Square | Shape::Stair | Shape::Ladder | Shape::X => (),
+        _ => (),
     }
-    let entity = Entity::new(entity_node.shape, entity_node.point, entity_node.position);
+    let entity = match entity_node.position {
+        EntityPosition::Within => Entity::spanning(
+            entity_node.shape,
+            entity_node.point,
+            entity_node.width,
+            entity_node.height,
+        ),
+        EntityPosition::At => {
+            Entity::new(entity_node.shape, entity_node.point, entity_node.position)
+        }
+    };
+    map.add_entity(entity);
+    Ok(())
+}
+
+fn handle_stamp(
+    map: &mut Map,
+    stamp_node: &StampNode,
+    base_dir: &Path,
+    location: SourceLocation,
+) -> Result<(), CompileError> {
+    let size = stamp_node.size;
+    check_cells_in_bounds(map, stamp_node.point, size, size, location)?;
+    let svg = match fs::read_to_string(base_dir.join(&stamp_node.path)) {
+        Ok(svg) => svg,
+        Err(e) => {
+            let error_type = if e.kind() == io::ErrorKind::NotFound {
+                CompileErrorType::StampNotFound(stamp_node.path.clone())
+            } else {
+                CompileErrorType::InvalidStamp(stamp_node.path.clone())
+            };
+            return Err(CompileError::new(error_type, location.line, location.col));
+        }
+    };
+    let stamp = match Stamp::parse(&stamp_node.path, &svg) {
+        Some(stamp) => stamp,
+        None => {
+            return Err(CompileError::new(
+                CompileErrorType::InvalidStamp(stamp_node.path.clone()),
+                location.line,
+                location.col,
+            ))
+        }
+    };
+    let index = map.add_stamp(stamp);
+    let entity = Entity::spanning(Shape::Stamp(index), stamp_node.point, size, size);
     map.add_entity(entity);
     Ok(())
 }
 
+/// Check that a block of cells lies within the grid, which it does if the
+/// far corner of its last cell does
+fn check_cells_in_bounds(
+    map: &Map,
+    p: Point,
+    width: usize,
+    height: usize,
+    location: SourceLocation,
+) -> Result<(), CompileError> {
+    match (p.x().checked_add(width), p.y().checked_add(height)) {
+        (Some(x), Some(y)) if map.point_exists(Point::new(x, y)) => Ok(()),
+        _ => Err(out_of_bounds(location)),
+    }
+}
+
 fn out_of_bounds(location: SourceLocation) -> CompileError {
     CompileError::new(CompileErrorType::OutOfBounds, location.line, location.col)
 }
@@ -201,15 +304,14 @@ fn point(x: usize, y: usize) -> Point {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{AstNode, EntityNode};
-    use crate::entities::EntityPosition;
+    use crate::ast::{AstNode, EntityNode, ScaleNode};
     use crate::shapes::{LineOrientation, Shape, ShapeBoolean};
 
     #[test]
     fn test_generate_empty_map() {
         let mut ast = AbstractSyntaxTree::new();
         ast.add_node(dimensions(4, 3));
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
         assert_eq!(map.width(), 4);
         assert_eq!(map.height(), 3);
     }
@@ -220,7 +322,7 @@ mod tests {
         ast.add_node(dimensions(1, 1));
         let rect = Rect::new(Point::new(0, 0), 1, 1, ShapeBoolean::Or);
         ast.add_node(rect_node(rect));
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
         assert!(map.are_connected(point(0, 0), point(1, 0)));
         assert!(map.are_connected(point(0, 0), point(0, 1)));
         assert!(map.are_connected(point(0, 1), point(1, 1)));
@@ -233,7 +335,7 @@ mod tests {
         ast.add_node(dimensions(10, 10));
         let rect = Rect::new(Point::new(2, 1), 3, 2, ShapeBoolean::Or);
         ast.add_node(rect_node(rect));
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
 
         // Top
         assert!(map.are_connected(point(2, 1), point(3, 1)));
@@ -278,7 +380,7 @@ mod tests {
         ast.add_node(rect_node(rect1));
         let rect2 = Rect::new(Point::new(5, 1), 2, 2, ShapeBoolean::Xor);
         ast.add_node(rect_node(rect2));
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
 
         // Check that the overlapped lines are not connected because of the XOR
         assert!(!map.are_connected(point(5, 1), point(5, 2)));
@@ -291,7 +393,7 @@ mod tests {
         ast.add_node(dimensions(5, 5));
         let rect = Rect::new(Point::new(2, 2), 10, 10, ShapeBoolean::Or);
         ast.add_node(rect_node(rect));
-        match generate_map(&ast) {
+        match generate_map(&ast, Path::new("")) {
             Ok(_) => panic!("Should fail"),
             Err(e) => {
                 assert!(matches!(e.error_type, CompileErrorType::OutOfBounds));
@@ -304,7 +406,70 @@ mod tests {
         let mut ast = AbstractSyntaxTree::new();
         ast.add_node(dimensions(5, 5));
         ast.add_node(circle_entity(Point::new(4, 3), 4));
-        match generate_map(&ast) {
+        match generate_map(&ast, Path::new("")) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::OutOfBounds));
+            }
+        }
+    }
+
+    #[test]
+    fn test_entity_spanning_cells() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(5, 5));
+        ast.add_node(square_entity(Point::new(1, 1), 3, 2));
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
+        let entity = &map.entities()[0];
+        assert_eq!(entity.width(), 3);
+        assert_eq!(entity.height(), 2);
+    }
+
+    #[test]
+    fn test_entity_spanning_cells_out_of_bounds() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(5, 5));
+        ast.add_node(square_entity(Point::new(3, 3), 3, 2));
+        match generate_map(&ast, Path::new("")) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::OutOfBounds));
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_stamp() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(5, 5));
+        ast.add_node(stamp("does/not/exist.svg", Point::new(1, 1), 1));
+        match generate_map(&ast, Path::new("")) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::StampNotFound(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_stamp() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(5, 5));
+        ast.add_node(stamp("Cargo.toml", Point::new(1, 1), 1));
+        match generate_map(&ast, Path::new(env!("CARGO_MANIFEST_DIR"))) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::InvalidStamp(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stamp_out_of_bounds() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(5, 5));
+        ast.add_node(stamp("Cargo.toml", Point::new(4, 4), 2));
+        match generate_map(&ast, Path::new("")) {
             Ok(_) => panic!("Should fail"),
             Err(e) => {
                 assert!(matches!(e.error_type, CompileErrorType::OutOfBounds));
@@ -321,7 +486,7 @@ mod tests {
         let location = SourceLocation { line: 1, col: 1 };
         let ast_node = AstNode::new(AstNodeType::Shape(shape_node), location);
         ast.add_node(ast_node);
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
         assert!(map.are_connected(Point::new(1, 3), Point::new(1, 4)));
     }
 
@@ -339,10 +504,27 @@ mod tests {
         let location = SourceLocation { line: 1, col: 1 };
         let ast_node = AstNode::new(AstNodeType::Shape(shape_node), location);
         ast.add_node(ast_node);
-        let map = generate_map(&ast).expect("Bad generate");
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
         assert!(map.are_connected(Point::new(3, 3), Point::new(4, 3)));
     }
 
+    #[test]
+    fn test_title_and_scale() {
+        let mut ast = AbstractSyntaxTree::new();
+        ast.add_node(dimensions(10, 10));
+        let location = SourceLocation { line: 2, col: 1 };
+        let title = AstNodeType::Title("Goblin Caves".to_string());
+        ast.add_node(AstNode::new(title, location));
+        let scale = ScaleNode {
+            amount: 5.0,
+            unit: "ft".to_string(),
+        };
+        ast.add_node(AstNode::new(AstNodeType::Scale(scale), location));
+        let map = generate_map(&ast, Path::new("")).expect("Bad generate");
+        assert_eq!(map.title(), Some("Goblin Caves"));
+        assert_eq!(map.scale(), Some(&MapScale::new(5.0, "ft")));
+    }
+
     fn dimensions(width: u32, height: u32) -> AstNode {
         let grid_dimensions_node = GridDimensionsNode::new(width, height);
         let node_type = AstNodeType::GridDimensions(grid_dimensions_node);
@@ -362,9 +544,34 @@ mod tests {
             shape: Shape::Circle(radius),
             point,
             position: EntityPosition::At,
+            width: 1,
+            height: 1,
+        };
+        let node_type = AstNodeType::Entity(entity_node);
+        let location = SourceLocation { line: 1, col: 1 };
+        AstNode::new(node_type, location)
+    }
+
+    fn square_entity(point: Point, width: usize, height: usize) -> AstNode {
+        let entity_node = EntityNode {
+            shape: Shape::Square,
+            point,
+            position: EntityPosition::Within,
+            width,
+            height,
         };
         let node_type = AstNodeType::Entity(entity_node);
         let location = SourceLocation { line: 1, col: 1 };
         AstNode::new(node_type, location)
     }
+
+    fn stamp(path: &str, point: Point, size: usize) -> AstNode {
+        let stamp_node = StampNode {
+            path: path.to_string(),
+            point,
+            size,
+        };
+        let location = SourceLocation { line: 1, col: 1 };
+        AstNode::new(AstNodeType::Stamp(stamp_node), location)
+    }
 }
@@ -0,0 +1,53 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+/// An entity glyph defined in a map file, drawn from paths whose points are
+/// given in unit-cell coordinates
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Glyph {
+    name: String,
+    paths: Vec<GlyphPath>,
+}
+
+impl Glyph {
+    pub fn new(name: String, paths: Vec<GlyphPath>) -> Glyph {
+        Glyph { name, paths }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn paths(&self) -> &[GlyphPath] {
+        &self.paths
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlyphPath {
+    points: Vec<(f64, f64)>,
+    closed: bool,
+}
+
+impl GlyphPath {
+    pub fn new(points: Vec<(f64, f64)>, closed: bool) -> GlyphPath {
+        GlyphPath { points, closed }
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
@@ -86,6 +86,20 @@ impl<T> Graph<T> {
         self.nodes.get(h.0)
     }
 
+    /// Every edge in the graph, once each, with the lower handle first
+    pub fn edges(&self) -> Vec<(NodeHandle, NodeHandle)> {
+        let mut edges = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            for (k, e) in node.edges.iter().enumerate() {
+                // The same edge can be added more than once
+                if i < e.0 && !node.edges[..k].contains(e) {
+                    edges.push((NodeHandle(i), *e));
+                }
+            }
+        }
+        edges
+    }
+
     pub fn find_cycles(&self) -> Vec<Vec<NodeHandle>> {
         let mut cycles = Vec::new();
         let mut visited = vec![false; self.nodes.len()];
@@ -118,7 +132,7 @@ impl<T> Graph<T> {
         stack: &mut Vec<NodeHandle>,
         cycles: &mut Vec<Vec<NodeHandle>>,
     ) -> bool {
-        if stack.iter().any(|h| *h == handle) {
+        if stack.contains(&handle) {
             // Cycle found
             let mut cycle = Vec::new();
             for h in stack.iter().rev() {
@@ -277,6 +291,18 @@ mod tests {
         assert!(g.is_edge_between(n2, n1));
     }
 
+    #[test]
+    fn test_edges() {
+        let mut g: Graph<i32> = Graph::new();
+        let n1 = g.add_node(1);
+        let n2 = g.add_node(2);
+        let n3 = g.add_node(3);
+        g.add_edge(n2, n1);
+        g.add_edge(n2, n3);
+        g.add_edge(n1, n2);
+        assert_eq!(g.edges(), vec![(n1, n2), (n2, n3)]);
+    }
+
     #[test]
     fn test_remove_edge() {
         let mut g: Graph<i32> = Graph::new();
@@ -337,7 +363,7 @@ mod tests {
 
         match cycles.iter().find(|c| c.len() == 6) {
             Some(c) => {
-                let node_set: HashSet<NodeHandle> = c.iter().map(|x| *x).collect();
+                let node_set: HashSet<NodeHandle> = c.iter().copied().collect();
                 let correct_nodes = [0, 1, 2, 3, 4, 5];
                 for x in correct_nodes.into_iter() {
                     assert!(node_set.contains(&NodeHandle(x)));
@@ -348,7 +374,7 @@ mod tests {
 
         match cycles.iter().find(|c| c.len() == 4) {
             Some(c) => {
-                let node_set: HashSet<NodeHandle> = c.iter().map(|x| *x).collect();
+                let node_set: HashSet<NodeHandle> = c.iter().copied().collect();
                 let correct_nodes = [5, 4, 6, 7];
                 for x in correct_nodes.into_iter() {
                     assert!(node_set.contains(&NodeHandle(x)));
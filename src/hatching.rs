@@ -0,0 +1,209 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::points::Point;
+use crate::random::Random;
+use std::collections::{HashSet, VecDeque};
+use std::f64::consts::PI;
+
+/// The number of parallel strokes drawn in each hatched cell
+const STROKES_PER_CELL: usize = 3;
+
+/// Find the cells of a `width` by `height` map that should be hatched: those
+/// outside every room that touch a wall, even if only at a corner. A cell is
+/// outside if it can be reached from beyond the edge of the map without
+/// crossing a wall.
+pub fn hatched_cells(edges: &[(Point, Point)], width: usize, height: usize) -> Vec<Point> {
+    let walls: HashSet<(Point, Point)> = edges
+        .iter()
+        .flat_map(|(p1, p2)| [(*p1, *p2), (*p2, *p1)])
+        .collect();
+    let is_wall = |x1, y1, x2, y2| walls.contains(&(Point::new(x1, y1), Point::new(x2, y2)));
+
+    // Flood fill inwards from the edges of the map
+    let mut outside = vec![vec![false; height]; width];
+    let mut queue = VecDeque::new();
+    for (i, column) in outside.iter_mut().enumerate() {
+        for (j, cell) in column.iter_mut().enumerate() {
+            let open_to_edge = (i == 0 && !is_wall(0, j, 0, j + 1))
+                || (j == 0 && !is_wall(i, 0, i + 1, 0))
+                || (i == width - 1 && !is_wall(width, j, width, j + 1))
+                || (j == height - 1 && !is_wall(i, height, i + 1, height));
+            if open_to_edge {
+                *cell = true;
+                queue.push_back((i, j));
+            }
+        }
+    }
+    while let Some((i, j)) = queue.pop_front() {
+        let mut neighbours = Vec::new();
+        if i > 0 && !is_wall(i, j, i, j + 1) {
+            neighbours.push((i - 1, j));
+        }
+        if i + 1 < width && !is_wall(i + 1, j, i + 1, j + 1) {
+            neighbours.push((i + 1, j));
+        }
+        if j > 0 && !is_wall(i, j, i + 1, j) {
+            neighbours.push((i, j - 1));
+        }
+        if j + 1 < height && !is_wall(i, j + 1, i + 1, j + 1) {
+            neighbours.push((i, j + 1));
+        }
+        for (ni, nj) in neighbours {
+            if !outside[ni][nj] {
+                outside[ni][nj] = true;
+                queue.push_back((ni, nj));
+            }
+        }
+    }
+
+    let wall_points: HashSet<Point> = edges.iter().flat_map(|(p1, p2)| [*p1, *p2]).collect();
+    (0..height)
+        .flat_map(|j| (0..width).map(move |i| Point::new(i, j)))
+        .filter(|cell| {
+            let (i, j) = (cell.x(), cell.y());
+            let corners = [
+                Point::new(i, j),
+                Point::new(i + 1, j),
+                Point::new(i, j + 1),
+                Point::new(i + 1, j + 1),
+            ];
+            outside[i][j] && corners.iter().any(|c| wall_points.contains(c))
+        })
+        .collect()
+}
+
+/// Generate the hatching strokes for a cell, in pixels. Neighbouring cells
+/// are hatched at roughly right angles to each other, with some randomness
+/// in the angle and spacing of the strokes, giving a cross-hatched look.
+pub fn hatch_strokes(cell: Point, dim: usize, random: &mut Random) -> Vec<(Point, Point)> {
+    let d = dim as f64;
+    let (x0, y0) = ((cell.x() * dim) as f64, (cell.y() * dim) as f64);
+    let base = if (cell.x() + cell.y()).is_multiple_of(2) {
+        PI / 4.0
+    } else {
+        3.0 * PI / 4.0
+    };
+    let angle = base + random.jitter(PI / 12.0);
+    let (dx, dy) = (angle.cos(), angle.sin());
+    let spacing = d / (STROKES_PER_CELL + 1) as f64;
+    let (cx, cy) = (x0 + d / 2.0, y0 + d / 2.0);
+
+    let mut strokes = Vec::new();
+    for k in 0..STROKES_PER_CELL {
+        let offset = (k as f64 - (STROKES_PER_CELL - 1) as f64 / 2.0) * spacing
+            + random.jitter(spacing / 4.0);
+        // Move perpendicular to the stroke direction to find its centre
+        let (px, py) = (cx - dy * offset, cy + dx * offset);
+        if let Some((t1, t2)) = clip(px, py, dx, dy, (x0, y0, x0 + d, y0 + d)) {
+            let start = Point::new(
+                (px + dx * t1).round() as usize,
+                (py + dy * t1).round() as usize,
+            );
+            let end = Point::new(
+                (px + dx * t2).round() as usize,
+                (py + dy * t2).round() as usize,
+            );
+            if start != end {
+                strokes.push((start, end));
+            }
+        }
+    }
+    strokes
+}
+
+/// Clip the line through `(px, py)` in the direction `(dx, dy)` to a box,
+/// returning the range of distances along the line that lie inside it
+fn clip(px: f64, py: f64, dx: f64, dy: f64, bounds: (f64, f64, f64, f64)) -> Option<(f64, f64)> {
+    let (x1, y1, x2, y2) = bounds;
+    let mut t_min = f64::NEG_INFINITY;
+    let mut t_max = f64::INFINITY;
+    for (p, d, lo, hi) in [(px, dx, x1, x2), (py, dy, y1, y2)] {
+        if d.abs() < f64::EPSILON {
+            if p < lo || p > hi {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((lo - p) / d, (hi - p) / d);
+        t_min = t_min.max(a.min(b));
+        t_max = t_max.min(a.max(b));
+    }
+    if t_min < t_max {
+        Some((t_min, t_max))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x1: usize, y1: usize, x2: usize, y2: usize) -> Vec<(Point, Point)> {
+        let mut edges = Vec::new();
+        for x in x1..x2 {
+            edges.push((Point::new(x, y1), Point::new(x + 1, y1)));
+            edges.push((Point::new(x, y2), Point::new(x + 1, y2)));
+        }
+        for y in y1..y2 {
+            edges.push((Point::new(x1, y), Point::new(x1, y + 1)));
+            edges.push((Point::new(x2, y), Point::new(x2, y + 1)));
+        }
+        edges
+    }
+
+    #[test]
+    fn test_band_around_room() {
+        let cells = hatched_cells(&square(2, 2, 3, 3), 5, 5);
+        assert_eq!(cells.len(), 8);
+        assert!(!cells.contains(&Point::new(2, 2)));
+        assert!(cells.contains(&Point::new(1, 1)));
+        assert!(!cells.contains(&Point::new(0, 0)));
+    }
+
+    #[test]
+    fn test_room_against_map_edge_is_not_hatched() {
+        let cells = hatched_cells(&square(0, 0, 2, 2), 3, 3);
+        assert_eq!(
+            cells,
+            vec![
+                Point::new(2, 0),
+                Point::new(2, 1),
+                Point::new(0, 2),
+                Point::new(1, 2),
+                Point::new(2, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strokes_stay_in_cell() {
+        let mut random = Random::new(1);
+        for i in 0..20 {
+            let cell = Point::new(i % 5, i / 5);
+            for (start, end) in hatch_strokes(cell, 10, &mut random) {
+                for p in [start, end] {
+                    assert!(p.x() >= cell.x() * 10 && p.x() <= cell.x() * 10 + 10);
+                    assert!(p.y() >= cell.y() * 10 && p.y() <= cell.y() * 10 + 10);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_strokes_are_deterministic() {
+        let cell = Point::new(3, 4);
+        let strokes1 = hatch_strokes(cell, 20, &mut Random::new(5));
+        let strokes2 = hatch_strokes(cell, 20, &mut Random::new(5));
+        assert_eq!(strokes1, strokes2);
+        assert_eq!(strokes1.len(), STROKES_PER_CELL);
+    }
+}
@@ -0,0 +1,363 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::ascii::{CELL_COLUMNS, CELL_ROWS};
+use crate::ast::EntityNode;
+use crate::decompiler::map_to_source;
+use crate::entities::EntityPosition;
+use crate::generator::handle_entity;
+use crate::map::Map;
+use crate::points::Point;
+use crate::shapes::Shape;
+use crate::source_location::SourceLocation;
+
+const CORNER_CHARS: &str = "+┌┐└┘├┤┬┴┼";
+const HORIZONTAL_CHARS: &str = "-─=";
+const VERTICAL_CHARS: &str = "|│";
+
+/// Convert a text drawing of a map into map source. Walls are drawn with
+/// `-` and `|`, meeting at `+` corners (or with box-drawing characters, as in
+/// `--format ascii`), and entities are drawn as letters inside cells. The
+/// size of the grid cells is worked out from the spacing of the corners.
+pub fn import_ascii(input: &str) -> Result<String, String> {
+    let map = ascii_to_map(input)?;
+    Ok(map_to_source(&map))
+}
+
+fn ascii_to_map(input: &str) -> Result<Map, String> {
+    let drawing = Drawing::new(input);
+    let corners = drawing.corners();
+    if corners.is_empty() {
+        return Err("No walls found: corners are drawn with '+'".to_string());
+    }
+
+    let columns: Vec<usize> = corners.iter().map(|(column, _)| *column).collect();
+    let rows: Vec<usize> = corners.iter().map(|(_, row)| *row).collect();
+    let cell_columns = cell_size(&columns, CELL_COLUMNS);
+    let cell_rows = cell_size(&rows, CELL_ROWS);
+    let grid = Grid {
+        left: columns.iter().min().unwrap() % cell_columns,
+        top: rows.iter().min().unwrap() % cell_rows,
+        cell_columns,
+        cell_rows,
+    };
+
+    let width = (drawing.width().max(1) - 1)
+        .saturating_sub(grid.left)
+        .div_ceil(cell_columns)
+        .max(1);
+    let height = (drawing.height().max(1) - 1)
+        .saturating_sub(grid.top)
+        .div_ceil(cell_rows)
+        .max(1);
+    let mut map = Map::new(width, height);
+
+    for x in 0..=width {
+        for y in 0..=height {
+            let p = Point::new(x, y);
+            if x < width
+                && drawing.is_wall(grid.column(x), grid.row(y), grid.column(x + 1), grid.row(y))
+            {
+                map.connect(p, p.right());
+            }
+            if y < height
+                && drawing.is_wall(grid.column(x), grid.row(y), grid.column(x), grid.row(y + 1))
+            {
+                map.connect(p, p.down());
+            }
+        }
+    }
+
+    if map.edges().is_empty() {
+        return Err("No walls found: walls are drawn with '-' and '|'".to_string());
+    }
+
+    // Circles can be centred on a grid point, but other entities have to be
+    // inside a cell. Only letters on their own count, so words written on
+    // the map aren't mistaken for entities.
+    for y in 0..=height {
+        for x in 0..=width {
+            let (column, row) = (grid.column(x), grid.row(y));
+            let c = drawing.get(column, row);
+            let alone = !drawing.get(column + 1, row).is_alphanumeric()
+                && (column == 0 || !drawing.get(column - 1, row).is_alphanumeric());
+            if let (Some(Shape::Circle(_)), true) = (Shape::from_letter(c), alone) {
+                add_entity(
+                    &mut map,
+                    Shape::Circle(1),
+                    Point::new(x, y),
+                    EntityPosition::At,
+                );
+            }
+            if x == width || y == height {
+                continue;
+            }
+            let mut marks = grid
+                .cell_interior(x, y)
+                .map(|(column, row)| drawing.get(column, row))
+                .filter(|c| !c.is_whitespace());
+            let shape = match (marks.next(), marks.next()) {
+                (Some(c), None) => Shape::from_letter(c),
+                _ => None,
+            };
+            if let Some(shape) = shape {
+                add_entity(&mut map, shape, Point::new(x, y), EntityPosition::Within);
+            }
+        }
+    }
+    Ok(map)
+}
+
+/// Add an entity if it would compile, leaving out circles that don't fit on
+/// the grid, such as ones drawn on its edge
+fn add_entity(map: &mut Map, shape: Shape, point: Point, position: EntityPosition) {
+    let node = EntityNode {
+        shape,
+        point,
+        position,
+        width: 1,
+        height: 1,
+    };
+    let location = SourceLocation { line: 0, col: 0 };
+    let _ = handle_entity(map, &node, location);
+}
+
+/// The size of a cell along one axis. Corners that are a multiple of the
+/// usual size apart are more likely to be the corners of rooms several cells
+/// across than of one big cell, so the usual size is preferred.
+fn cell_size(positions: &[usize], usual: usize) -> usize {
+    match spacing(positions) {
+        Some(size) if !size.is_multiple_of(usual) => size,
+        _ => usual,
+    }
+}
+
+/// The distance between grid lines, as the greatest common divisor of the
+/// distances between the positions of the corners
+fn spacing(positions: &[usize]) -> Option<usize> {
+    let first = *positions.iter().min()?;
+    positions
+        .iter()
+        .map(|p| p - first)
+        .filter(|d| *d > 0)
+        .reduce(gcd)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Where the grid lines fall in a drawing
+struct Grid {
+    left: usize,
+    top: usize,
+    cell_columns: usize,
+    cell_rows: usize,
+}
+
+impl Grid {
+    fn column(&self, x: usize) -> usize {
+        self.left + x * self.cell_columns
+    }
+
+    fn row(&self, y: usize) -> usize {
+        self.top + y * self.cell_rows
+    }
+
+    /// The positions of the characters inside a cell, not on its walls
+    fn cell_interior(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let columns = self.column(x) + 1..self.column(x + 1);
+        let rows = self.row(y) + 1..self.row(y + 1);
+        rows.flat_map(move |row| columns.clone().map(move |column| (column, row)))
+    }
+}
+
+struct Drawing {
+    lines: Vec<Vec<char>>,
+}
+
+impl Drawing {
+    /// Read the drawing, leaving out the key to the letters that
+    /// `--format ascii` writes beneath it
+    fn new(input: &str) -> Drawing {
+        let mut lines: Vec<Vec<char>> = input
+            .lines()
+            .map(|line| line.trim_end().chars().collect())
+            .collect();
+        if let Some(blank) = lines.iter().rposition(|line| line.is_empty()) {
+            let key = &lines[blank + 1..];
+            if !key.is_empty() && key.iter().all(|line| is_key_line(line)) {
+                lines.truncate(blank);
+            }
+        }
+        Drawing { lines }
+    }
+
+    fn width(&self) -> usize {
+        self.lines.iter().map(|line| line.len()).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn get(&self, column: usize, row: usize) -> char {
+        self.lines
+            .get(row)
+            .and_then(|line| line.get(column))
+            .copied()
+            .unwrap_or(' ')
+    }
+
+    fn corners(&self) -> Vec<(usize, usize)> {
+        let mut corners = Vec::new();
+        for (row, line) in self.lines.iter().enumerate() {
+            for (column, c) in line.iter().enumerate() {
+                if CORNER_CHARS.contains(*c) {
+                    corners.push((column, row));
+                }
+            }
+        }
+        corners
+    }
+
+    /// Whether there is a wall between two grid points, either drawn with
+    /// line characters between them, or, if they are next to each other, by
+    /// joining them up directly
+    fn is_wall(&self, column1: usize, row1: usize, column2: usize, row2: usize) -> bool {
+        let (line_chars, between): (&str, Vec<char>) = if row1 == row2 {
+            let between = (column1 + 1..column2).map(|c| self.get(c, row1)).collect();
+            (HORIZONTAL_CHARS, between)
+        } else {
+            let between = (row1 + 1..row2).map(|r| self.get(column1, r)).collect();
+            (VERTICAL_CHARS, between)
+        };
+        if between.is_empty() {
+            let joins = |c: char| CORNER_CHARS.contains(c) || line_chars.contains(c);
+            joins(self.get(column1, row1)) && joins(self.get(column2, row2))
+        } else {
+            between.iter().all(|c| line_chars.contains(*c))
+        }
+    }
+}
+
+/// Whether a line is an entry in the key to the letters, e.g. "P  Pillar"
+fn is_key_line(line: &[char]) -> bool {
+    line.len() > 3 && line[0].is_alphabetic() && line[1] == ' ' && line[2] == ' '
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_rooms() {
+        let input = "\
++-----+
+|     |
+|      --+
+|        |
++-----+--+
+";
+        let map = ascii_to_map(input).expect("Bad import");
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert!(map.are_connected(Point::new(0, 0), Point::new(1, 0)));
+        assert!(map.are_connected(Point::new(2, 1), Point::new(3, 1)));
+        assert!(map.are_connected(Point::new(3, 1), Point::new(3, 2)));
+        assert!(!map.are_connected(Point::new(1, 1), Point::new(2, 1)));
+        assert!(map.are_connected(Point::new(2, 0), Point::new(2, 1)));
+        assert!(!map.are_connected(Point::new(2, 1), Point::new(2, 2)));
+    }
+
+    #[test]
+    fn test_import_wide_cells() {
+        let input = "\
++---+
+| P |
+|   |
++---+
+";
+        let map = ascii_to_map(input).expect("Bad import");
+        assert_eq!((map.width(), map.height()), (1, 1));
+        assert_eq!(map.entities().len(), 1);
+        assert_eq!(map.entities()[0].shape(), Shape::Pillar);
+    }
+
+    #[test]
+    fn test_import_source() {
+        let input = "\
++--+--+
+|p | c|
++--C--+
+|     |
++-----+
+
+P  Pillar
+";
+        let expected = "\
+grid 2, 2
+rect at 0, 0 width 2 height 2
+line along top from 0, 1 length 2
+line along left from 1, 0 length 1
+entity pillar within 0, 0
+entity circle within 1, 0
+entity circle at 1, 1 radius 1
+";
+        assert_eq!(import_ascii(input).expect("Bad import"), expected);
+    }
+
+    #[test]
+    fn test_import_without_walls() {
+        assert!(import_ascii("P\n").is_err());
+        assert!(import_ascii("+\n").is_err());
+        assert!(import_ascii("+  +\n\n+  +\n").is_err());
+    }
+
+    #[test]
+    fn test_import_circles_off_the_grid() {
+        let input = "\
+C--+
+|  |
++--C
+";
+        let map = ascii_to_map(input).expect("Bad import");
+        assert!(map.entities().is_empty());
+    }
+
+    #[test]
+    fn test_import_ignores_words() {
+        let input = "\
++----------+
+| Kitchen  |
+|          |
++----------+
+   Cellar
+";
+        let map = ascii_to_map(input).expect("Bad import");
+        assert!(map.entities().is_empty());
+    }
+
+    #[test]
+    fn test_spacing() {
+        assert_eq!(spacing(&[4, 10, 13, 4]), Some(3));
+        assert_eq!(spacing(&[2, 2]), None);
+    }
+
+    #[test]
+    fn test_cell_size() {
+        assert_eq!(cell_size(&[0, 12], 3), 3);
+        assert_eq!(cell_size(&[0, 4, 8], 3), 4);
+        assert_eq!(cell_size(&[5], 3), 3);
+    }
+}
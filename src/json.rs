@@ -0,0 +1,155 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::entities::{Entity, EntityPosition};
+use crate::json_value::JsonValue;
+use crate::map::Map;
+use crate::points::Point;
+use crate::shapes::Shape;
+use std::collections::HashSet;
+
+/// Describe the geometry of a map as JSON, in grid units. The walls are
+/// split up in the same way as they are drawn: closed loops of walls as
+/// polygons, and the rest as polylines. The same loop can be found more than
+/// once, and paths can be a single point, which draw nothing, so those are
+/// left out.
+pub fn map_to_json(map: &Map) -> String {
+    let all_rooms = map.rooms();
+    let open_walls: Vec<Vec<Point>> = map
+        .open_walls(&all_rooms)
+        .into_iter()
+        .filter(|path| path.len() > 1)
+        .collect();
+    let mut rooms: Vec<Vec<Point>> = Vec::new();
+    let mut seen: HashSet<Vec<Point>> = HashSet::new();
+    for room in all_rooms {
+        let mut key = room.clone();
+        key.sort_by_key(|p| (p.y(), p.x()));
+        if seen.insert(key) {
+            rooms.push(room);
+        }
+    }
+    let entities = map
+        .entities()
+        .iter()
+        .map(|entity| entity_json(map, entity))
+        .collect();
+
+    JsonValue::object(vec![
+        ("width", map.width().into()),
+        ("height", map.height().into()),
+        (
+            "walls",
+            JsonValue::object(vec![
+                ("polygons", paths_json(&rooms)),
+                ("polylines", paths_json(&open_walls)),
+            ]),
+        ),
+        ("entities", JsonValue::Array(entities)),
+    ])
+    .pretty()
+}
+
+fn paths_json(paths: &[Vec<Point>]) -> JsonValue {
+    JsonValue::Array(
+        paths
+            .iter()
+            .map(|path| {
+                JsonValue::Array(
+                    path.iter()
+                        .map(|p| JsonValue::Array(vec![p.x().into(), p.y().into()]))
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// An entity's shape and the cells it fills, or for a circle drawn at a
+/// grid point, its centre and radius
+fn entity_json(map: &Map, entity: &Entity) -> JsonValue {
+    let mut fields = match entity.shape() {
+        Shape::Glyph(index) => vec![
+            ("shape", JsonValue::string("glyph")),
+            ("name", JsonValue::string(map.glyph(index).unwrap().name())),
+        ],
+        Shape::Stamp(index) => vec![
+            ("shape", JsonValue::string("stamp")),
+            ("path", JsonValue::string(map.stamp(index).unwrap().path())),
+        ],
+        shape => vec![("shape", JsonValue::string(shape.keyword().unwrap()))],
+    };
+    let p = entity.point();
+    match (entity.position(), entity.shape()) {
+        (EntityPosition::At, Shape::Circle(radius)) => fields.extend([
+            ("position", JsonValue::string("at")),
+            ("x", p.x().into()),
+            ("y", p.y().into()),
+            ("radius", radius.into()),
+        ]),
+        _ => fields.extend([
+            ("position", JsonValue::string("within")),
+            ("x", p.x().into()),
+            ("y", p.y().into()),
+            ("width", entity.width().into()),
+            ("height", entity.height().into()),
+        ]),
+    }
+    JsonValue::object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walls() {
+        let mut map = Map::new(2, 2);
+        for (p1, p2) in [
+            ((0, 0), (1, 0)),
+            ((1, 0), (1, 1)),
+            ((1, 1), (0, 1)),
+            ((0, 1), (0, 0)),
+        ] {
+            map.connect(Point::new(p1.0, p1.1), Point::new(p2.0, p2.1));
+        }
+        map.connect(Point::new(2, 0), Point::new(2, 1));
+        map.connect(Point::new(2, 1), Point::new(2, 2));
+        let json = map_to_json(&map);
+        assert!(json.contains("\"polylines\": [\n      [[2, 0], [2, 1], [2, 2]]\n    ]"));
+        assert_eq!(json.matches("[0, 0]").count(), 1);
+    }
+
+    #[test]
+    fn test_entities() {
+        let mut map = Map::new(2, 2);
+        map.add_entity(Entity::spanning(Shape::Bed, Point::new(0, 0), 1, 2));
+        map.add_entity(Entity::new(
+            Shape::Circle(1),
+            Point::new(1, 1),
+            EntityPosition::At,
+        ));
+        let expected = "\
+{
+  \"width\": 2,
+  \"height\": 2,
+  \"walls\": {
+    \"polygons\": [],
+    \"polylines\": []
+  },
+  \"entities\": [
+    {\"shape\": \"bed\", \"position\": \"within\", \"x\": 0, \"y\": 0, \"width\": 1, \"height\": 2},
+    {\"shape\": \"circle\", \"position\": \"at\", \"x\": 1, \"y\": 1, \"radius\": 1}
+  ]
+}
+";
+        assert_eq!(map_to_json(&map), expected);
+    }
+}
@@ -0,0 +1,454 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use std::fmt;
+
+/// A JSON document, as exchanged with a language server client or written
+/// by `--format json`. Objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Build an object from its keys and values
+    pub fn object(entries: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn string(s: &str) -> JsonValue {
+        JsonValue::String(s.to_string())
+    }
+
+    /// The value of a key of an object, or `None` if this isn't an object or
+    /// doesn't have the key
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follow a path of keys through nested objects
+    pub fn at(&self, path: &[&str]) -> Option<&JsonValue> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            JsonValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<JsonValue, String> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            i: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.i < parser.chars.len() {
+            return Err(format!("Unexpected character at {}", parser.i));
+        }
+        Ok(value)
+    }
+
+    /// Write the value for people to read, with one item on each line.
+    /// Small arrays and objects, like points, paths and records of plain
+    /// values, are kept on one line.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, Layout::Indented(0));
+        out.push('\n');
+        out
+    }
+
+    /// Whether the value goes on one line: an object of plain values, or an
+    /// array that is no more than two levels deep and has no objects in it
+    fn is_small(&self) -> bool {
+        let is_plain =
+            |value: &JsonValue| !matches!(value, JsonValue::Array(_) | JsonValue::Object(_));
+        let is_flat = |value: &JsonValue| match value {
+            JsonValue::Array(items) => items.iter().all(is_plain),
+            JsonValue::Object(_) => false,
+            _ => true,
+        };
+        match self {
+            JsonValue::Array(items) => items.iter().all(is_flat),
+            JsonValue::Object(entries) => entries.iter().all(|(_, value)| is_plain(value)),
+            _ => true,
+        }
+    }
+
+    fn write(&self, out: &mut String, layout: Layout) {
+        let layout = match layout {
+            Layout::Indented(_) if self.is_small() => Layout::Line,
+            layout => layout,
+        };
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(&b.to_string()),
+            JsonValue::Number(n) => out.push_str(&n.to_string()),
+            JsonValue::String(s) => write_string(out, s),
+            JsonValue::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    layout.separate(out, i);
+                    item.write(out, layout.inner());
+                }
+                layout.close(out, items.len());
+                out.push(']');
+            }
+            JsonValue::Object(entries) => {
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    layout.separate(out, i);
+                    write_string(out, key);
+                    out.push_str(if layout == Layout::Compact { ":" } else { ": " });
+                    value.write(out, layout.inner());
+                }
+                layout.close(out, entries.len());
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl From<usize> for JsonValue {
+    fn from(n: usize) -> JsonValue {
+        JsonValue::Number(n as f64)
+    }
+}
+
+/// How the items of arrays and objects are laid out
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    /// With no spaces, for messages
+    Compact,
+    /// On one line, with spaces after separators
+    Line,
+    /// One item on each line, at a depth of indentation
+    Indented(usize),
+}
+
+impl Layout {
+    fn inner(self) -> Layout {
+        match self {
+            Layout::Indented(depth) => Layout::Indented(depth + 1),
+            layout => layout,
+        }
+    }
+
+    /// Start the item at `index`
+    fn separate(self, out: &mut String, index: usize) {
+        if index > 0 {
+            out.push(',');
+        }
+        match self {
+            Layout::Compact => {}
+            Layout::Line if index > 0 => out.push(' '),
+            Layout::Line => {}
+            Layout::Indented(depth) => {
+                out.push('\n');
+                out.push_str(&"  ".repeat(depth + 1));
+            }
+        }
+    }
+
+    /// End a list of `count` items
+    fn close(self, out: &mut String, count: usize) {
+        if let Layout::Indented(depth) = self {
+            if count > 0 {
+                out.push('\n');
+                out.push_str(&"  ".repeat(depth));
+            }
+        }
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, Layout::Compact);
+        f.write_str(&out)
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser {
+    chars: Vec<char>,
+    i: usize,
+}
+
+impl Parser {
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.literal("true", JsonValue::Bool(true)),
+            Some('f') => self.literal("false", JsonValue::Bool(false)),
+            Some('n') => self.literal("null", JsonValue::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("Unexpected character '{}' at {}", c, self.i)),
+            None => Err("Unexpected end of input".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.i += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(entries)),
+                _ => return Err(format!("Expected ',' or '}}' at {}", self.i)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.i += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(format!("Expected ',' or ']' at {}", self.i)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    Some(c) => s.push(c),
+                    None => return Err("Unterminated string".to_string()),
+                },
+                Some(c) => s.push(c),
+                None => return Err("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// The character for a `\u` escape, which for characters outside the
+    /// Basic Multilingual Plane is a pair of escaped surrogates
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err("Unpaired surrogate".to_string());
+            }
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| "Invalid unicode escape".to_string())
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| "Invalid unicode escape".to_string())
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let start = self.i;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || "+-.eE".contains(c) {
+                self.i += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.i].iter().collect();
+        text.parse::<f64>()
+            .map(JsonValue::Number)
+            .map_err(|_| format!("Invalid number at {}", start))
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, String> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(format!("Expected '{}' at {}", word, self.i));
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(format!("Expected '{}' at {}", expected, self.i)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.i += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.i += 1;
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let value = JsonValue::parse(
+            r#" {"id": 1, "params": {"text": "a\"b\né", "list": [true, null, -2.5e1]}} "#,
+        )
+        .unwrap();
+        assert_eq!(value.get("id").and_then(|v| v.as_usize()), Some(1));
+        assert_eq!(
+            value.at(&["params", "text"]).unwrap().as_str(),
+            Some("a\"b\né")
+        );
+        let list = value.at(&["params", "list"]).unwrap().as_array().unwrap();
+        assert_eq!(
+            list,
+            &[
+                JsonValue::Bool(true),
+                JsonValue::Null,
+                JsonValue::Number(-25.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        let value = JsonValue::parse(r#""🐉""#).unwrap();
+        assert_eq!(value.as_str(), Some("🐉"));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(JsonValue::parse("{\"a\": }").is_err());
+        assert!(JsonValue::parse("[1, 2").is_err());
+        assert!(JsonValue::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        let value = JsonValue::object(vec![
+            ("a", JsonValue::from(1)),
+            (
+                "b",
+                JsonValue::Array(vec![JsonValue::string("x\"y"), JsonValue::Null]),
+            ),
+        ]);
+        assert_eq!(value.to_string(), r#"{"a":1,"b":["x\"y",null]}"#);
+        assert_eq!(JsonValue::parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_pretty() {
+        let point = |x: usize, y: usize| JsonValue::Array(vec![x.into(), y.into()]);
+        let value = JsonValue::object(vec![
+            ("path", JsonValue::Array(vec![point(0, 0), point(1, 0)])),
+            (
+                "items",
+                JsonValue::Array(vec![JsonValue::object(vec![("a", JsonValue::Null)])]),
+            ),
+            ("empty", JsonValue::Array(Vec::new())),
+        ]);
+        let expected = "\
+{
+  \"path\": [[0, 0], [1, 0]],
+  \"items\": [
+    {\"a\": null}
+  ],
+  \"empty\": []
+}
+";
+        assert_eq!(value.pretty(), expected);
+    }
+}
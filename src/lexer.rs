@@ -9,30 +9,92 @@
  */
 
 use super::compile_error::{CompileError, CompileErrorType};
-use super::token::{Token, TokenType};
+use super::source_location::SourceLocation;
+use super::token::{Comment, Token, TokenType};
 
 const SINGLE_LINE_COMMENT_CHAR: char = '#';
+const STRING_DELIMITER: char = '"';
+
+/// The keywords of the language, and the tokens they are lexed as
+pub(crate) const KEYWORDS: [(&str, TokenType); 47] = [
+    ("at", TokenType::At),
+    ("circle", TokenType::Circle),
+    ("square", TokenType::Square),
+    ("entity", TokenType::Entity),
+    ("grid", TokenType::Grid),
+    ("height", TokenType::Height),
+    ("rect", TokenType::Rect),
+    ("width", TokenType::Width),
+    ("within", TokenType::Within),
+    ("xor", TokenType::Xor),
+    ("radius", TokenType::Radius),
+    ("line", TokenType::Line),
+    ("along", TokenType::Along),
+    ("left", TokenType::Left),
+    ("right", TokenType::Right),
+    ("top", TokenType::Top),
+    ("bottom", TokenType::Bottom),
+    ("from", TokenType::From),
+    ("length", TokenType::Length),
+    ("stair", TokenType::Stair),
+    ("ladder", TokenType::Ladder),
+    ("x", TokenType::X),
+    ("to", TokenType::To),
+    ("pillar", TokenType::Pillar),
+    ("statue", TokenType::Statue),
+    ("chest", TokenType::Chest),
+    ("table", TokenType::Table),
+    ("bed", TokenType::Bed),
+    ("altar", TokenType::Altar),
+    ("well", TokenType::Well),
+    ("fountain", TokenType::Fountain),
+    ("trapdoor", TokenType::Trapdoor),
+    ("brazier", TokenType::Brazier),
+    ("barrel", TokenType::Barrel),
+    ("door", TokenType::Door),
+    ("rubble", TokenType::Rubble),
+    ("trap", TokenType::Trap),
+    ("glyph", TokenType::Glyph),
+    ("path", TokenType::Path),
+    ("close", TokenType::Close),
+    ("stamp", TokenType::Stamp),
+    ("size", TokenType::Size),
+    ("title", TokenType::Title),
+    ("scale", TokenType::Scale),
+    ("compass", TokenType::Compass),
+    ("rotate", TokenType::Rotate),
+    ("outside", TokenType::Outside),
+];
+
+/// The keyword that a token was lexed from, if it was one
+pub(crate) fn keyword(token_type: &TokenType) -> Option<&'static str> {
+    KEYWORDS
+        .iter()
+        .find(|(_, t)| std::mem::discriminant(t) == std::mem::discriminant(token_type))
+        .map(|(keyword, _)| *keyword)
+}
 
 struct Lexer {
     i: usize,
     chars: Vec<char>,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     line: usize,
     col: usize,
 }
 
 impl Lexer {
-    fn analyze(mut self) -> Result<Vec<Token>, CompileError> {
+    fn analyze(mut self) -> Result<(Vec<Token>, Vec<Comment>), CompileError> {
         while self.i < self.chars.len() {
             let ch = self.chars[self.i];
-            if ch.is_alphabetic() {
-                let token = self.lex_identifier()?;
+            if ch.is_alphabetic() || ch == '_' {
+                let token = self.lex_identifier();
                 self.tokens.push(token);
             } else if ch.is_ascii_digit() {
                 let token = self.lex_number()?;
                 self.tokens.push(token);
-            } else if ch == ',' {
-                self.add_token(TokenType::Comma, self.line, self.col);
+            } else if let Some(token_type) = punctuation(ch) {
+                self.add_token(token_type, self.line, self.col);
                 self.i += 1;
                 self.col += 1;
             } else if ch == '\n' {
@@ -43,6 +105,9 @@ impl Lexer {
                 // Skip whitespace
                 self.i += 1;
                 self.col += 1;
+            } else if ch == STRING_DELIMITER {
+                let token = self.lex_string()?;
+                self.tokens.push(token);
             } else if ch == SINGLE_LINE_COMMENT_CHAR {
                 self.lex_single_line_comment();
             } else {
@@ -53,53 +118,38 @@ impl Lexer {
                 ));
             }
         }
-        Ok(self.tokens)
+        Ok((self.tokens, self.comments))
     }
 
-    fn lex_identifier(&mut self) -> Result<Token, CompileError> {
+    /// Lex a keyword, or otherwise a name. Names are only valid in some
+    /// places, which the parser checks.
+    fn lex_identifier(&mut self) -> Token {
         let col = self.col;
-        let identifier = self.lex_while(|ch| ch.is_alphabetic());
-        let keywords = [
-            ("at", TokenType::At),
-            ("circle", TokenType::Circle),
-            ("square", TokenType::Square),
-            ("entity", TokenType::Entity),
-            ("grid", TokenType::Grid),
-            ("height", TokenType::Height),
-            ("rect", TokenType::Rect),
-            ("width", TokenType::Width),
-            ("within", TokenType::Within),
-            ("xor", TokenType::Xor),
-            ("radius", TokenType::Radius),
-            ("line", TokenType::Line),
-            ("along", TokenType::Along),
-            ("left", TokenType::Left),
-            ("right", TokenType::Right),
-            ("top", TokenType::Top),
-            ("bottom", TokenType::Bottom),
-            ("from", TokenType::From),
-            ("length", TokenType::Length),
-            ("stair", TokenType::Stair),
-            ("ladder", TokenType::Ladder),
-            ("x", TokenType::X),
-        ];
-        if let Some(index) = keywords
-            .iter()
-            .position(|(keyword, _tok)| *keyword == identifier)
-        {
-            Ok(Token::new(keywords[index].1, self.line, col))
-        } else {
-            Err(CompileError::new(
-                CompileErrorType::UnrecognizedKeyword(identifier),
-                self.line,
-                col,
-            ))
-        }
+        let identifier = self.lex_while(|ch| ch.is_alphanumeric() || ch == '_');
+        let token_type = match KEYWORDS.iter().find(|(keyword, _)| *keyword == identifier) {
+            Some((_, token_type)) => token_type.clone(),
+            None => TokenType::Identifier(identifier),
+        };
+        Token::new(token_type, self.line, col)
     }
 
     fn lex_number(&mut self) -> Result<Token, CompileError> {
         let col = self.col;
-        let s = self.lex_while(|ch| ch.is_ascii_digit());
+        let mut s = self.lex_while(|ch| ch.is_ascii_digit());
+        if self.i < self.chars.len() && self.chars[self.i] == '.' {
+            s.push('.');
+            self.i += 1;
+            self.col += 1;
+            s.push_str(&self.lex_while(|ch| ch.is_ascii_digit()));
+            return match s.parse::<f64>() {
+                Ok(n) => Ok(Token::new(TokenType::Decimal(n), self.line, col)),
+                Err(_) => Err(CompileError::new(
+                    CompileErrorType::InvalidNumber,
+                    self.line,
+                    col,
+                )),
+            };
+        }
         match s.parse::<u32>() {
             Ok(n) => Ok(Token::new(TokenType::Number(n), self.line, col)),
             Err(_) => Err(CompileError::new(
@@ -110,11 +160,32 @@ impl Lexer {
         }
     }
 
-    fn lex_single_line_comment(&mut self) {
-        while self.i < self.chars.len() && self.chars[self.i] != '\n' {
-            self.i += 1;
-            self.col += 1;
+    fn lex_string(&mut self) -> Result<Token, CompileError> {
+        let col = self.col;
+        self.i += 1;
+        self.col += 1;
+        let s = self.lex_while(|ch| ch != STRING_DELIMITER && ch != '\n');
+        if self.i >= self.chars.len() || self.chars[self.i] != STRING_DELIMITER {
+            return Err(CompileError::new(
+                CompileErrorType::UnterminatedString,
+                self.line,
+                col,
+            ));
         }
+        self.i += 1;
+        self.col += 1;
+        Ok(Token::new(TokenType::StringLiteral(s), self.line, col))
+    }
+
+    fn lex_single_line_comment(&mut self) {
+        let location = SourceLocation {
+            line: self.line,
+            col: self.col,
+        };
+        self.i += 1;
+        self.col += 1;
+        let text = self.lex_while(|ch| ch != '\n');
+        self.comments.push(Comment { text, location });
     }
 
     fn lex_while<F>(&mut self, predicate: F) -> String
@@ -137,11 +208,23 @@ impl Lexer {
     }
 }
 
-pub fn lex(input: &str) -> Result<Vec<Token>, CompileError> {
+fn punctuation(ch: char) -> Option<TokenType> {
+    match ch {
+        ',' => Some(TokenType::Comma),
+        '{' => Some(TokenType::LeftBrace),
+        '}' => Some(TokenType::RightBrace),
+        ';' => Some(TokenType::Semicolon),
+        _ => None,
+    }
+}
+
+/// Lex the input, also returning its comments, in order
+pub fn lex_with_comments(input: &str) -> Result<(Vec<Token>, Vec<Comment>), CompileError> {
     let lexer = Lexer {
         chars: input.chars().collect(),
         i: 0,
         tokens: Vec::new(),
+        comments: Vec::new(),
         line: 1,
         col: 1,
     };
@@ -152,6 +235,11 @@ pub fn lex(input: &str) -> Result<Vec<Token>, CompileError> {
 mod tests {
     use super::*;
 
+    fn lex(input: &str) -> Result<Vec<Token>, CompileError> {
+        let (tokens, _) = lex_with_comments(input)?;
+        Ok(tokens)
+    }
+
     #[test]
     fn test_lex_keyword() {
         let input = "grid";
@@ -193,18 +281,23 @@ mod tests {
     }
 
     #[test]
-    fn test_bad_keyword_error() {
-        let input = "badkeyword";
-        if let Err(err) = lex(input) {
-            assert!(matches!(
-                err.error_type,
-                CompileErrorType::UnrecognizedKeyword(_)
-            ));
-            assert_eq!(err.location.line, 1);
-            assert_eq!(err.location.col, 1);
-        } else {
-            panic!("Should fail");
-        }
+    fn test_lex_names() {
+        test_lex(
+            "badkeyword secret_door2 _x table",
+            &[
+                TokenType::Identifier("badkeyword".to_string()),
+                TokenType::Identifier("secret_door2".to_string()),
+                TokenType::Identifier("_x".to_string()),
+                TokenType::Table,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_keyword() {
+        assert_eq!(keyword(&TokenType::Fountain), Some("fountain"));
+        assert_eq!(keyword(&TokenType::Comma), None);
+        assert_eq!(keyword(&TokenType::Identifier("table".to_string())), None);
     }
 
     #[test]
@@ -232,6 +325,18 @@ mod tests {
         assert_eq!(tokens.len(), 4);
     }
 
+    #[test]
+    fn test_comments_are_kept() {
+        let input = "# a map\ngrid 10, 10 # a ten-by-ten grid\n";
+        let (tokens, comments) = lex_with_comments(input).expect("bad lex");
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, " a map");
+        assert_eq!(comments[1].text, " a ten-by-ten grid");
+        assert_eq!(comments[1].location.line, 2);
+        assert_eq!(comments[1].location.col, 13);
+    }
+
     #[test]
     fn test_line_number() {
         let input = "grid 10, 10\nrect at 1, 1 width 2 height 2";
@@ -289,6 +394,91 @@ mod tests {
         test_lex(input, &correct_token_types);
     }
 
+    #[test]
+    fn test_lex_glyph_definition() {
+        let input = "glyph crate { path 0,0 0.5,1 close; }";
+        let correct_token_types = vec![
+            TokenType::Glyph,
+            TokenType::Identifier("crate".to_string()),
+            TokenType::LeftBrace,
+            TokenType::Path,
+            TokenType::Number(0),
+            TokenType::Comma,
+            TokenType::Number(0),
+            TokenType::Decimal(0.5),
+            TokenType::Comma,
+            TokenType::Number(1),
+            TokenType::Close,
+            TokenType::Semicolon,
+            TokenType::RightBrace,
+        ];
+        test_lex(input, &correct_token_types);
+    }
+
+    #[test]
+    fn test_lex_decimal() {
+        let tokens = lex("0.25").expect("bad lex");
+        assert_eq!(tokens.len(), 1);
+        assert!(matches!(tokens[0].token_type, TokenType::Decimal(n) if n == 0.25));
+    }
+
+    #[test]
+    fn test_lex_glyph_name_in_entity() {
+        let input = "entity crate within 1, 1";
+        let tokens = lex(input).expect("bad lex");
+        assert!(matches!(&tokens[1].token_type, TokenType::Identifier(name) if name == "crate"));
+    }
+
+    #[test]
+    fn test_lex_stamp() {
+        let input = "stamp \"art/throne.svg\" within 5, 5 size 2";
+        let tokens = lex(input).expect("bad lex");
+        assert!(matches!(tokens[0].token_type, TokenType::Stamp));
+        match &tokens[1].token_type {
+            TokenType::StringLiteral(s) => assert_eq!(s, "art/throne.svg"),
+            t => panic!("Wrong token type: {:?}", t),
+        }
+        assert_eq!(tokens[1].location.col, 7);
+        assert!(matches!(tokens[6].token_type, TokenType::Size));
+    }
+
+    #[test]
+    fn test_lex_title_and_scale() {
+        let input = "title \"Goblin Caves\"\nscale 5 ft";
+        let tokens = lex(input).expect("bad lex");
+        assert!(matches!(tokens[0].token_type, TokenType::Title));
+        assert!(
+            matches!(&tokens[1].token_type, TokenType::StringLiteral(s) if s == "Goblin Caves")
+        );
+        assert!(matches!(tokens[2].token_type, TokenType::Scale));
+        assert!(matches!(tokens[3].token_type, TokenType::Number(5)));
+        assert!(matches!(&tokens[4].token_type, TokenType::Identifier(unit) if unit == "ft"));
+    }
+
+    #[test]
+    fn test_lex_compass() {
+        test_lex(
+            "compass at top right outside rotate 45",
+            &[
+                TokenType::Compass,
+                TokenType::At,
+                TokenType::Top,
+                TokenType::Right,
+                TokenType::Outside,
+                TokenType::Rotate,
+                TokenType::Number(45),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        match lex("stamp \"art/throne.svg\nsize") {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::UnterminatedString)),
+        }
+    }
+
     fn test_lex(input: &str, expected: &[TokenType]) {
         let tokens = lex(input).expect("Bad lex");
         assert_eq!(tokens.len(), expected.len());
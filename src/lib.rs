@@ -8,18 +8,42 @@
  * Copyright (c) 2024 David Jackson
  */
 
-mod ast;
+mod ascii;
+pub mod ast;
+pub mod builder;
+pub mod compass;
 mod compile_error;
 pub mod compiler;
-mod entities;
+pub mod decompiler;
+mod dot;
+pub mod entities;
 pub mod files;
+pub mod formatter;
 mod generator;
+pub mod glyphs;
 mod graph;
+mod hatching;
+pub mod import;
+mod json;
+mod json_value;
 mod lexer;
+pub mod lsp;
 pub mod map;
 mod parser;
-mod points;
-mod shapes;
-mod source_location;
+mod pdf;
+pub mod points;
+pub mod print;
+mod random;
+pub mod render;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod shapes;
+mod sketch;
+pub mod source_location;
+mod stamps;
 mod svg;
+pub mod theme;
+mod tmx;
 mod token;
+mod walls;
+pub mod watch;
@@ -0,0 +1,757 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+//! A language server for map files, which speaks the Language Server
+//! Protocol over `stdin` and `stdout`
+
+use crate::compiler::error_message;
+use crate::generator::generate_map;
+use crate::json_value::JsonValue;
+use crate::lexer::{lex_with_comments, KEYWORDS};
+use crate::map::column_label;
+use crate::parser::parse;
+use crate::token::{Token, TokenType};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+// LSP constants
+const TEXT_DOCUMENT_SYNC_FULL: usize = 1;
+const SEVERITY_ERROR: usize = 1;
+const COMPLETION_KIND_KEYWORD: usize = 14;
+const COMPLETION_KIND_REFERENCE: usize = 18;
+
+/// The state of a language server: the documents the client has open, and
+/// whether it has been asked to shut down
+#[derive(Default)]
+pub struct LanguageServer {
+    documents: HashMap<String, String>,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl LanguageServer {
+    pub fn new() -> LanguageServer {
+        LanguageServer::default()
+    }
+
+    /// Whether the client has sent `exit`, after which the server should stop
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handle a JSON-RPC message from the client, returning the messages to
+    /// send back: the response to a request, and any notifications
+    pub fn handle(&mut self, message: &str) -> Vec<String> {
+        let message = match JsonValue::parse(message) {
+            Ok(message) => message,
+            Err(e) => return vec![error_response(JsonValue::Null, PARSE_ERROR, &e).to_string()],
+        };
+        let method = message.get("method").and_then(JsonValue::as_str);
+        let params = message.get("params").cloned().unwrap_or(JsonValue::Null);
+        let replies = match (message.get("id"), method) {
+            (Some(id), Some(method)) => {
+                let response = match self.request(method, &params) {
+                    Ok(result) => JsonValue::object(vec![
+                        ("jsonrpc", JsonValue::string("2.0")),
+                        ("id", id.clone()),
+                        ("result", result),
+                    ]),
+                    Err((code, e)) => error_response(id.clone(), code, &e),
+                };
+                vec![response]
+            }
+            (None, Some(method)) => self.notification(method, &params),
+            // Responses to requests from the server, which it never makes
+            (_, None) => Vec::new(),
+        };
+        replies.iter().map(JsonValue::to_string).collect()
+    }
+
+    fn request(&mut self, method: &str, params: &JsonValue) -> Result<JsonValue, (i32, String)> {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "Server is shutting down".to_string()));
+        }
+        match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(JsonValue::Null)
+            }
+            "textDocument/completion" => {
+                let text = self.document(params)?;
+                Ok(completions(text))
+            }
+            "textDocument/hover" => {
+                let (text, line, character) = self.position(params)?;
+                Ok(hover(text, line, character))
+            }
+            "textDocument/definition" => {
+                let uri = document_uri(params)?;
+                let (text, line, character) = self.position(params)?;
+                Ok(definition(uri, text, line, character))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &JsonValue) -> Vec<JsonValue> {
+        let uri = match params
+            .at(&["textDocument", "uri"])
+            .and_then(JsonValue::as_str)
+        {
+            Some(uri) => uri.to_string(),
+            None => {
+                if method == "exit" {
+                    self.exited = true;
+                }
+                return Vec::new();
+            }
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .at(&["textDocument", "text"])
+                    .and_then(JsonValue::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+            }
+            "textDocument/didChange" => {
+                // With full text sync, the last change is the whole document
+                let text = params
+                    .get("contentChanges")
+                    .and_then(JsonValue::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(JsonValue::as_str);
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                // Clear the diagnostics of documents that are no longer open
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        }
+        match self.documents.get(&uri) {
+            Some(text) => vec![publish_diagnostics(&uri, diagnostics(&uri, text))],
+            None => Vec::new(),
+        }
+    }
+
+    /// The text of the document a request is about
+    fn document(&self, params: &JsonValue) -> Result<&str, (i32, String)> {
+        let uri = document_uri(params)?;
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Unknown document: {}", uri)))
+    }
+
+    /// The text of the document a request is about, and the 0-based line and
+    /// character in it
+    fn position(&self, params: &JsonValue) -> Result<(&str, usize, usize), (i32, String)> {
+        let text = self.document(params)?;
+        let line = params
+            .at(&["position", "line"])
+            .and_then(JsonValue::as_usize);
+        let character = params
+            .at(&["position", "character"])
+            .and_then(JsonValue::as_usize);
+        match (line, character) {
+            (Some(line), Some(character)) => Ok((text, line, character)),
+            _ => Err((INVALID_PARAMS, "Missing position".to_string())),
+        }
+    }
+}
+
+/// Run a language server, reading messages from `input` and writing replies
+/// to `output` with LSP's `Content-Length` headers, until the client sends
+/// `exit` or closes `input`
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<()> {
+    let mut server = LanguageServer::new();
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write!(output, "Content-Length: {}\r\n\r\n{}", reply.len(), reply)?;
+        }
+        output.flush()?;
+        if server.has_exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read the content of a message, or `None` at the end of the input
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn error_response(id: JsonValue, code: i32, message: &str) -> JsonValue {
+    JsonValue::object(vec![
+        ("jsonrpc", JsonValue::string("2.0")),
+        ("id", id),
+        (
+            "error",
+            JsonValue::object(vec![
+                ("code", JsonValue::Number(code as f64)),
+                ("message", JsonValue::string(message)),
+            ]),
+        ),
+    ])
+}
+
+fn capabilities() -> JsonValue {
+    JsonValue::object(vec![
+        (
+            "capabilities",
+            JsonValue::object(vec![
+                ("textDocumentSync", JsonValue::from(TEXT_DOCUMENT_SYNC_FULL)),
+                ("completionProvider", JsonValue::object(Vec::new())),
+                ("hoverProvider", JsonValue::Bool(true)),
+                ("definitionProvider", JsonValue::Bool(true)),
+            ]),
+        ),
+        (
+            "serverInfo",
+            JsonValue::object(vec![
+                ("name", JsonValue::string("ttmap")),
+                ("version", JsonValue::string(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+fn document_uri(params: &JsonValue) -> Result<&str, (i32, String)> {
+    params
+        .at(&["textDocument", "uri"])
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, "Missing document".to_string()))
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<JsonValue>) -> JsonValue {
+    JsonValue::object(vec![
+        ("jsonrpc", JsonValue::string("2.0")),
+        (
+            "method",
+            JsonValue::string("textDocument/publishDiagnostics"),
+        ),
+        (
+            "params",
+            JsonValue::object(vec![
+                ("uri", JsonValue::string(uri)),
+                ("diagnostics", JsonValue::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+/// Compile a document as `ttmap` would, with stamps found relative to it,
+/// and report the first error over the word it was found at
+fn diagnostics(uri: &str, text: &str) -> Vec<JsonValue> {
+    let base_dir = uri_to_path(uri)
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let error = match parse(text) {
+        Ok(ast) => generate_map(&ast, &base_dir).err(),
+        Err(e) => Some(e),
+    };
+    let Some(error) = error else {
+        return Vec::new();
+    };
+    let line = error.location.line.max(1);
+    let col = error.location.col.max(1);
+    let line_text = text.lines().nth(line - 1).unwrap_or("");
+    let chars: Vec<char> = line_text.chars().collect();
+    let start = (col - 1).min(chars.len());
+    let end = start + word_len(&chars, start);
+    vec![JsonValue::object(vec![
+        ("range", range(line - 1, &chars, start, end)),
+        ("severity", JsonValue::from(SEVERITY_ERROR)),
+        ("source", JsonValue::string("ttmap")),
+        (
+            "message",
+            JsonValue::string(&error_message(&error.error_type)),
+        ),
+    ])]
+}
+
+/// The keywords of the language, and the glyphs defined in the document
+fn completions(text: &str) -> JsonValue {
+    let mut items: Vec<JsonValue> = KEYWORDS
+        .iter()
+        .map(|(keyword, _)| {
+            JsonValue::object(vec![
+                ("label", JsonValue::string(keyword)),
+                ("kind", JsonValue::from(COMPLETION_KIND_KEYWORD)),
+            ])
+        })
+        .collect();
+    if let Ok((tokens, _)) = lex_with_comments(text) {
+        for pair in tokens.windows(2) {
+            if let (TokenType::Glyph, TokenType::Identifier(name)) =
+                (&pair[0].token_type, &pair[1].token_type)
+            {
+                items.push(JsonValue::object(vec![
+                    ("label", JsonValue::string(name)),
+                    ("kind", JsonValue::from(COMPLETION_KIND_REFERENCE)),
+                    ("detail", JsonValue::string("glyph")),
+                ]));
+            }
+        }
+    }
+    JsonValue::Array(items)
+}
+
+/// Describe the cell that a pair of coordinates under the cursor refers to.
+/// Coordinates after `within` are cells, and the rest are grid points, at
+/// the top-left corners of cells.
+fn hover(text: &str, line: usize, character: usize) -> JsonValue {
+    let Some((tokens, i, token_range)) = token_at(text, line, character) else {
+        return JsonValue::Null;
+    };
+    if !matches!(tokens[i].token_type, TokenType::Number(_)) {
+        return JsonValue::Null;
+    }
+    let number = |i: usize| match tokens.get(i).map(|t| &t.token_type) {
+        Some(TokenType::Number(n)) => Some(*n as usize),
+        _ => None,
+    };
+    let comma = |i: usize| matches!(tokens.get(i).map(|t| &t.token_type), Some(TokenType::Comma));
+    let first = if comma(i + 1) && number(i + 2).is_some() {
+        i
+    } else if i >= 2 && comma(i - 1) && number(i - 2).is_some() {
+        i - 2
+    } else {
+        return JsonValue::Null;
+    };
+    // Glyph paths are in their own coordinates, and the grid's size isn't a
+    // place on it
+    let in_glyph = tokens[..first]
+        .iter()
+        .rev()
+        .find_map(|t| match t.token_type {
+            TokenType::LeftBrace => Some(true),
+            TokenType::RightBrace => Some(false),
+            _ => None,
+        });
+    let previous = first.checked_sub(1).map(|i| &tokens[i].token_type);
+    if in_glyph == Some(true) || matches!(previous, Some(TokenType::Grid)) {
+        return JsonValue::Null;
+    }
+    let (x, y) = (number(first).unwrap(), number(first + 2).unwrap());
+    let cell = format!("{}{}", column_label(x), y + 1);
+    // The far corner of a range, as in `within 0, 0 to 4, 4`, is a cell too
+    let range_end = matches!(previous, Some(TokenType::To))
+        && first >= 5
+        && matches!(tokens[first - 5].token_type, TokenType::Within);
+    let value = if matches!(previous, Some(TokenType::Within)) || range_end {
+        format!("Cell **{}** (x {}, y {})", cell, x, y)
+    } else {
+        format!(
+            "Grid point {}, {}, at the top-left corner of cell **{}**",
+            x, y, cell
+        )
+    };
+    JsonValue::object(vec![
+        (
+            "contents",
+            JsonValue::object(vec![
+                ("kind", JsonValue::string("markdown")),
+                ("value", JsonValue::string(&value)),
+            ]),
+        ),
+        ("range", token_range),
+    ])
+}
+
+/// Where the glyph named under the cursor is defined, or the file that the
+/// stamp under the cursor draws. Maps can't include other maps, so those
+/// are the only things defined elsewhere.
+fn definition(uri: &str, text: &str, line: usize, character: usize) -> JsonValue {
+    let Some((tokens, i, _)) = token_at(text, line, character) else {
+        return JsonValue::Null;
+    };
+    let previous = i.checked_sub(1).map(|i| &tokens[i].token_type);
+    match (previous, &tokens[i].token_type) {
+        (Some(TokenType::Entity | TokenType::Glyph), TokenType::Identifier(name)) => {
+            let defined = tokens.windows(2).find(|pair| {
+                matches!(pair[0].token_type, TokenType::Glyph)
+                    && matches!(&pair[1].token_type, TokenType::Identifier(n) if n == name)
+            });
+            match defined {
+                Some(pair) => location(uri, token_range(text, &pair[1])),
+                None => JsonValue::Null,
+            }
+        }
+        (Some(TokenType::Stamp), TokenType::StringLiteral(path)) => {
+            match uri_to_path(uri).as_deref().and_then(Path::parent) {
+                Some(dir) => location(&path_to_uri(&dir.join(path)), range(0, &[], 0, 0)),
+                None => JsonValue::Null,
+            }
+        }
+        _ => JsonValue::Null,
+    }
+}
+
+fn location(uri: &str, range: JsonValue) -> JsonValue {
+    JsonValue::object(vec![("uri", JsonValue::string(uri)), ("range", range)])
+}
+
+/// The tokens of a document, the index of the one at a 0-based line and
+/// character, and its range
+fn token_at(text: &str, line: usize, character: usize) -> Option<(Vec<Token>, usize, JsonValue)> {
+    let (tokens, _) = lex_with_comments(text).ok()?;
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let col = char_index(&chars, character);
+    let i = tokens.iter().position(|t| {
+        let start = t.location.col - 1;
+        t.location.line == line + 1 && start <= col && col < start + word_len(&chars, start)
+    })?;
+    let token_range = token_range(text, &tokens[i]);
+    Some((tokens, i, token_range))
+}
+
+fn token_range(text: &str, token: &Token) -> JsonValue {
+    let line = token.location.line - 1;
+    let chars: Vec<char> = text.lines().nth(line).unwrap_or("").chars().collect();
+    let start = token.location.col - 1;
+    range(line, &chars, start, start + word_len(&chars, start))
+}
+
+/// The length of the word starting at a character: a whole string literal,
+/// a run of letters and digits, or otherwise the single character
+fn word_len(chars: &[char], start: usize) -> usize {
+    let word = |ch: char| ch.is_alphanumeric() || ch == '.';
+    match chars.get(start) {
+        None => 0,
+        Some('"') => match chars[start + 1..].iter().position(|ch| *ch == '"') {
+            Some(n) => n + 2,
+            None => chars.len() - start,
+        },
+        Some(ch) if word(*ch) => chars[start..].iter().take_while(|ch| word(**ch)).count(),
+        Some(_) => 1,
+    }
+}
+
+/// An LSP range between two character indexes of a line. LSP counts
+/// characters in UTF-16 code units.
+fn range(line: usize, chars: &[char], start: usize, end: usize) -> JsonValue {
+    let position = |index: usize| {
+        let character: usize = chars[..index.min(chars.len())]
+            .iter()
+            .map(|ch| ch.len_utf16())
+            .sum();
+        JsonValue::object(vec![
+            ("line", JsonValue::from(line)),
+            ("character", JsonValue::from(character)),
+        ])
+    };
+    JsonValue::object(vec![("start", position(start)), ("end", position(end))])
+}
+
+/// The index of the character at an LSP character offset
+fn char_index(chars: &[char], character: usize) -> usize {
+    let mut units = 0;
+    for (i, ch) in chars.iter().enumerate() {
+        if units >= character {
+            return i;
+        }
+        units += ch.len_utf16();
+    }
+    chars.len()
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URI: &str = "file:///maps/dungeon%20one.map";
+
+    fn open(server: &mut LanguageServer, text: &str) -> JsonValue {
+        let text = JsonValue::string(text).to_string();
+        let message = format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{}","languageId":"ttmap","version":1,"text":{}}}}}}}"#,
+            URI, text
+        );
+        let replies = server.handle(&message);
+        assert_eq!(replies.len(), 1);
+        JsonValue::parse(&replies[0]).unwrap()
+    }
+
+    fn request(
+        server: &mut LanguageServer,
+        method: &str,
+        line: usize,
+        character: usize,
+    ) -> JsonValue {
+        let message = format!(
+            r#"{{"jsonrpc":"2.0","id":7,"method":"{}","params":{{"textDocument":{{"uri":"{}"}},"position":{{"line":{},"character":{}}}}}}}"#,
+            method, URI, line, character
+        );
+        let replies = server.handle(&message);
+        assert_eq!(replies.len(), 1);
+        let reply = JsonValue::parse(&replies[0]).unwrap();
+        assert_eq!(reply.get("id"), Some(&JsonValue::from(7)));
+        reply.get("result").unwrap().clone()
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = LanguageServer::new();
+        let published = open(&mut server, "grid 2, 2\nrect at 0, 0 width 3 height 1\n");
+        let diagnostics = published.at(&["params", "diagnostics"]).unwrap();
+        let diagnostics = diagnostics.as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(
+            diagnostic.get("message").unwrap().as_str(),
+            Some("Out-of-bounds point")
+        );
+        assert_eq!(
+            diagnostic.at(&["range", "start", "line"]),
+            Some(&JsonValue::from(1))
+        );
+
+        let change = format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didChange","params":{{"textDocument":{{"uri":"{}","version":2}},"contentChanges":[{{"text":"grid 2, 2\nrect at 0, 0 width 2 height 1\n"}}]}}}}"#,
+            URI
+        );
+        let replies = server.handle(&change);
+        let published = JsonValue::parse(&replies[0]).unwrap();
+        assert_eq!(
+            published.at(&["params", "diagnostics"]),
+            Some(&JsonValue::Array(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_range() {
+        let mut server = LanguageServer::new();
+        let published = open(&mut server, "grid 2, 2\nrect at 0, 0 wide 2\n");
+        let diagnostic = &published
+            .at(&["params", "diagnostics"])
+            .unwrap()
+            .as_array()
+            .unwrap()[0];
+        assert_eq!(
+            diagnostic.get("message").unwrap().as_str(),
+            Some("Unrecognized keyword: \"wide\"")
+        );
+        let range = diagnostic.get("range").unwrap();
+        assert_eq!(
+            range.at(&["start", "character"]),
+            Some(&JsonValue::from(13))
+        );
+        assert_eq!(range.at(&["end", "character"]), Some(&JsonValue::from(17)));
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut server = LanguageServer::new();
+        open(&mut server, "grid 2, 2\nglyph crate { path 0,0 1,1 }\n");
+        let result = request(&mut server, "textDocument/completion", 1, 0);
+        let labels: Vec<&str> = result
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|item| item.get("label").and_then(JsonValue::as_str))
+            .collect();
+        assert_eq!(labels.len(), KEYWORDS.len() + 1);
+        assert!(labels.contains(&"fountain"));
+        assert!(labels.contains(&"crate"));
+    }
+
+    #[test]
+    fn test_hover() {
+        let mut server = LanguageServer::new();
+        open(
+            &mut server,
+            "grid 30, 30\nglyph rock { path 0,0 1,1 }\nentity pillar within 27, 3\nrect at 1, 2 width 2 height 2\nentity table within 0, 0 to 4, 4\n",
+        );
+        let result = request(&mut server, "textDocument/hover", 2, 22);
+        assert_eq!(
+            result.at(&["contents", "value"]).unwrap().as_str(),
+            Some("Cell **AB4** (x 27, y 3)")
+        );
+        let result = request(&mut server, "textDocument/hover", 4, 31);
+        assert_eq!(
+            result.at(&["contents", "value"]).unwrap().as_str(),
+            Some("Cell **E5** (x 4, y 4)")
+        );
+        let result = request(&mut server, "textDocument/hover", 3, 11);
+        assert_eq!(
+            result.at(&["contents", "value"]).unwrap().as_str(),
+            Some("Grid point 1, 2, at the top-left corner of cell **B3**")
+        );
+        assert_eq!(
+            request(&mut server, "textDocument/hover", 0, 5),
+            JsonValue::Null
+        );
+        assert_eq!(
+            request(&mut server, "textDocument/hover", 1, 18),
+            JsonValue::Null
+        );
+        assert_eq!(
+            request(&mut server, "textDocument/hover", 3, 0),
+            JsonValue::Null
+        );
+    }
+
+    #[test]
+    fn test_definition() {
+        let mut server = LanguageServer::new();
+        open(
+            &mut server,
+            "grid 4, 4\nglyph crate { path 0,0 1,1 }\nentity crate within 1, 1\nstamp \"art/x y.svg\" within 2, 2\n",
+        );
+        let result = request(&mut server, "textDocument/definition", 2, 9);
+        assert_eq!(result.get("uri").unwrap().as_str(), Some(URI));
+        assert_eq!(
+            result.at(&["range", "start", "line"]),
+            Some(&JsonValue::from(1))
+        );
+        assert_eq!(
+            result.at(&["range", "start", "character"]),
+            Some(&JsonValue::from(6))
+        );
+        let result = request(&mut server, "textDocument/definition", 3, 8);
+        assert_eq!(
+            result.get("uri").unwrap().as_str(),
+            Some("file:///maps/art/x%20y.svg")
+        );
+        assert_eq!(
+            request(&mut server, "textDocument/definition", 2, 0),
+            JsonValue::Null
+        );
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let mut server = LanguageServer::new();
+        let replies =
+            server.handle(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#);
+        let reply = JsonValue::parse(&replies[0]).unwrap();
+        assert_eq!(
+            reply.at(&["result", "capabilities", "hoverProvider"]),
+            Some(&JsonValue::Bool(true))
+        );
+        assert!(server
+            .handle(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#)
+            .is_empty());
+        let replies = server.handle(r#"{"jsonrpc":"2.0","id":2,"method":"workspace/symbol"}"#);
+        let reply = JsonValue::parse(&replies[0]).unwrap();
+        assert_eq!(
+            reply.at(&["error", "code"]),
+            Some(&JsonValue::Number(METHOD_NOT_FOUND as f64))
+        );
+        let replies = server.handle(r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#);
+        assert_eq!(
+            JsonValue::parse(&replies[0]).unwrap().get("result"),
+            Some(&JsonValue::Null)
+        );
+        assert!(!server.has_exited());
+        server.handle(r#"{"jsonrpc":"2.0","method":"exit"}"#);
+        assert!(server.has_exited());
+    }
+
+    #[test]
+    fn test_invalid_message() {
+        let mut server = LanguageServer::new();
+        let replies = server.handle("{\"id\": 1,");
+        let reply = JsonValue::parse(&replies[0]).unwrap();
+        assert_eq!(
+            reply.at(&["error", "code"]),
+            Some(&JsonValue::Number(PARSE_ERROR as f64))
+        );
+    }
+
+    #[test]
+    fn test_uris() {
+        assert_eq!(
+            uri_to_path(URI),
+            Some(PathBuf::from("/maps/dungeon one.map"))
+        );
+        assert_eq!(uri_to_path("untitled:1"), None);
+        assert_eq!(
+            path_to_uri(Path::new("/maps/dungeon one.map")),
+            "file:///maps/dungeon%20one.map"
+        );
+    }
+}
@@ -9,56 +9,361 @@
  */
 
 extern crate getopts;
-use getopts::Options;
+use getopts::{Matches, Options};
 use std::env;
+use std::fs;
 use std::io;
-use std::io::Read;
-use ttmap::compiler::compile_svg;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+use ttmap::compiler::{
+    compile_ascii, compile_dot, compile_json, compile_pdf, compile_pdf_tiles, compile_rooms_dot,
+    compile_svg_with_options, compile_tmx, format_source,
+};
 use ttmap::files::read_file;
+use ttmap::import::import_ascii;
+use ttmap::lsp::serve;
+use ttmap::map::{RenderOptions, WallThickness};
+use ttmap::print::Paper;
+use ttmap::theme::Theme;
+use ttmap::watch::Watcher;
 
 const DEFAULT_DIMENSION: usize = 10;
+const DEFAULT_WATCH_INTERVAL_MS: u64 = 500;
 
 // Options
 const OPT_FILE: &str = "f";
 const OPT_DIMENSION: &str = "d";
+const OPT_THEME: &str = "t";
+const OPT_WALL_THICKNESS: &str = "w";
+const OPT_LABELS: &str = "l";
+const OPT_HATCH: &str = "hatch";
+const OPT_SKETCHY: &str = "sketchy";
+const OPT_SEED: &str = "seed";
+const OPT_FORMAT: &str = "format";
+const OPT_PAPER: &str = "paper";
+const OPT_CHECK: &str = "check";
+const OPT_OUTPUT: &str = "o";
+const OPT_INTERVAL: &str = "interval";
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("import") => return import(&args[2..]),
+        Some("fmt") => return fmt(&args[2..]),
+        Some("lsp") => return lsp(),
+        Some("watch") => return watch(&args[2..]),
+        _ => {}
+    }
 
     let mut opts = Options::new();
     opts.optopt(OPT_FILE, "file", "input map file", "MAP_FILE");
+    add_render_opts(&mut opts);
+    opts.optopt(
+        "",
+        OPT_FORMAT,
+        "output format: svg (default), pdf, ascii, json, tmx, dot or dot-rooms",
+        "FORMAT",
+    );
+    opts.optopt(
+        "",
+        OPT_PAPER,
+        "paper size for printing PDFs: letter or a4",
+        "PAPER",
+    );
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(e) => panic!("{}", e.to_string()),
+    };
+
+    let mut base_dir = PathBuf::new();
+    if let Some(file_name) = matches.opt_str(OPT_FILE) {
+        if let Some(parent) = Path::new(&file_name).parent() {
+            base_dir = parent.to_path_buf();
+        }
+    }
+    let input = read_input(matches.opt_str(OPT_FILE));
+
+    let dim = dimension(&matches);
+    let options = render_options(&matches);
+
+    let format = matches.opt_str(OPT_FORMAT);
+    if matches.opt_present(OPT_PAPER) && format.as_deref() != Some("pdf") {
+        eprintln!("--paper can only be used with --format pdf");
+        process::exit(1);
+    }
+    match format.as_deref() {
+        None | Some("svg") => {
+            let s = compile_svg_with_options(&input, dim, &base_dir, &options);
+            println!("{}", s);
+        }
+        Some("pdf") => {
+            // With a paper size, the map is printed to scale across as many
+            // pages as it takes, otherwise it is drawn on a single page
+            let result = match matches.opt_str(OPT_PAPER) {
+                Some(name) => {
+                    let Some(paper) = Paper::parse(&name) else {
+                        eprintln!("Unknown paper size: {}", name);
+                        process::exit(1);
+                    };
+                    compile_pdf_tiles(&input, &base_dir, &options, paper)
+                }
+                None => compile_pdf(&input, dim, &base_dir, &options),
+            };
+            match result {
+                Ok(pdf) => io::stdout().write_all(&pdf).expect("Could not write PDF"),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("ascii") => match compile_ascii(&input, &base_dir, options.labels) {
+            Ok(text) => print!("{}", text),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some("json") => match compile_json(&input, &base_dir) {
+            Ok(json) => print!("{}", json),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some("tmx") => match compile_tmx(&input, dim, &base_dir) {
+            Ok(tmx) => print!("{}", tmx),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some("dot") => match compile_dot(&input, &base_dir) {
+            Ok(dot) => print!("{}", dot),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some("dot-rooms") => match compile_rooms_dot(&input, &base_dir) {
+            Ok(dot) => print!("{}", dot),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        Some(format) => {
+            eprintln!("Unknown format: {}", format);
+            process::exit(1);
+        }
+    }
+}
+
+/// Add the options that control how a map is drawn
+fn add_render_opts(opts: &mut Options) {
     opts.optopt(
         OPT_DIMENSION,
         "dimension",
         "map cell dimension in pixels",
         "DIMENSION",
     );
-    let matches = match opts.parse(&args[1..]) {
+    opts.optopt(
+        OPT_THEME,
+        "theme",
+        "built-in theme name or theme file",
+        "THEME",
+    );
+    opts.optopt(
+        OPT_WALL_THICKNESS,
+        "wall-thickness",
+        "wall thickness in pixels (e.g. 4px) or cells (e.g. 0.2cells)",
+        "THICKNESS",
+    );
+    opts.optflag(OPT_LABELS, "labels", "label the grid columns and rows");
+    opts.optflag("", OPT_HATCH, "cross-hatch the rock around the walls");
+    opts.optflag("", OPT_SKETCHY, "draw walls and entities as rough sketches");
+    opts.optopt("", OPT_SEED, "seed for procedurally drawn details", "SEED");
+}
+
+/// The cell dimension in pixels, from the `--dimension` option
+fn dimension(matches: &Matches) -> usize {
+    match matches.opt_str(OPT_DIMENSION) {
+        Some(dim) => dim.parse::<usize>().expect("Invalid dimension"),
+        None => DEFAULT_DIMENSION,
+    }
+}
+
+/// The settings for drawing a map, from the options added by
+/// `add_render_opts`
+fn render_options(matches: &Matches) -> RenderOptions {
+    let mut options = RenderOptions::default();
+    if let Some(theme) = matches.opt_str(OPT_THEME) {
+        options.theme = load_theme(&theme);
+    }
+    if let Some(thickness) = matches.opt_str(OPT_WALL_THICKNESS) {
+        let thickness = WallThickness::parse(&thickness).expect("Invalid wall thickness");
+        options.wall_thickness = Some(thickness);
+    }
+    options.labels = matches.opt_present(OPT_LABELS);
+    options.hatching = matches.opt_present(OPT_HATCH);
+    options.sketchy = matches.opt_present(OPT_SKETCHY);
+    if let Some(seed) = matches.opt_str(OPT_SEED) {
+        options.seed = seed.parse::<u64>().expect("Invalid seed");
+    }
+    options
+}
+
+/// Convert a text drawing of a map into map source
+fn import(args: &[String]) {
+    let mut opts = Options::new();
+    opts.optopt(OPT_FILE, "file", "input text drawing", "TEXT_FILE");
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(e) => panic!("{}", e.to_string()),
+    };
+    let input = read_input(matches.opt_str(OPT_FILE));
+    match import_ascii(&input) {
+        Ok(source) => print!("{}", source),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Format map files in place, or `stdin` to `stdout`. With `--check`,
+/// nothing is written, but the exit status is 1 if anything isn't formatted.
+fn fmt(args: &[String]) {
+    let mut opts = Options::new();
+    opts.optflag("", OPT_CHECK, "check that the files are formatted");
+    let matches = match opts.parse(args) {
         Ok(m) => m,
         Err(e) => panic!("{}", e.to_string()),
     };
+    let check = matches.opt_present(OPT_CHECK);
 
-    let input = if matches.opt_present(OPT_FILE) {
-        let file_name = matches.opt_str(OPT_FILE).unwrap();
-        read_file(&file_name)
-    } else {
-        let mut s = String::new();
-        io::stdin()
-            .read_to_string(&mut s)
-            .expect("Could not read stdin");
-        s
+    let format = |input: &str| match format_source(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
     };
 
-    let dim = if matches.opt_present(OPT_DIMENSION) {
-        matches
-            .opt_str(OPT_DIMENSION)
-            .unwrap()
-            .parse::<usize>()
-            .expect("Invalid dimension")
-    } else {
-        DEFAULT_DIMENSION
+    if matches.free.is_empty() {
+        let input = read_input(None);
+        let formatted = format(&input);
+        if check {
+            if formatted != input {
+                process::exit(1);
+            }
+        } else {
+            print!("{}", formatted);
+        }
+        return;
+    }
+
+    let mut unformatted = false;
+    for file_name in matches.free.iter() {
+        let input = read_file(file_name);
+        let formatted = format(&input);
+        if formatted == input {
+            continue;
+        }
+        if check {
+            println!("{}", file_name);
+            unformatted = true;
+        } else {
+            fs::write(file_name, formatted).expect("Could not write file");
+        }
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+/// Run a language server for editors over `stdin` and `stdout`
+fn lsp() {
+    let stdin = io::stdin();
+    if let Err(e) = serve(stdin.lock(), io::stdout()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// Compile a map file to an SVG file, and again whenever it or the files it
+/// stamps change, until interrupted
+fn watch(args: &[String]) {
+    let mut opts = Options::new();
+    opts.reqopt(OPT_FILE, "file", "input map file", "MAP_FILE");
+    opts.reqopt(OPT_OUTPUT, "output", "output SVG file", "SVG_FILE");
+    opts.optopt(
+        "",
+        OPT_INTERVAL,
+        "how often to check for changes, in milliseconds",
+        "INTERVAL",
+    );
+    add_render_opts(&mut opts);
+    let matches = match opts.parse(args) {
+        Ok(m) => m,
+        Err(e) => panic!("{}", e.to_string()),
+    };
+    let input = matches.opt_str(OPT_FILE).unwrap();
+    let output = matches.opt_str(OPT_OUTPUT).unwrap();
+    let interval = match matches.opt_str(OPT_INTERVAL) {
+        Some(ms) => ms.parse::<u64>().expect("Invalid interval"),
+        None => DEFAULT_WATCH_INTERVAL_MS,
     };
 
-    let s = compile_svg(&input, dim);
-    println!("{}", s);
+    let mut watcher = Watcher::new(
+        Path::new(&input),
+        Path::new(&output),
+        dimension(&matches),
+        render_options(&matches),
+    );
+    loop {
+        match watcher.poll() {
+            Some(Ok(())) => println!("Wrote {}", output),
+            Some(Err(e)) => eprintln!("{}: {}", input, e),
+            None => {}
+        }
+        thread::sleep(Duration::from_millis(interval));
+    }
+}
+
+/// Read the named file, or `stdin` if there isn't one
+fn read_input(file_name: Option<String>) -> String {
+    match file_name {
+        Some(file_name) => read_file(&file_name),
+        None => {
+            let mut s = String::new();
+            io::stdin()
+                .read_to_string(&mut s)
+                .expect("Could not read stdin");
+            s
+        }
+    }
+}
+
+fn load_theme(theme: &str) -> Theme {
+    if let Some(t) = Theme::builtin(theme) {
+        return t;
+    }
+    let content = match fs::read_to_string(theme) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Couldn't read theme {}: {}", theme, e);
+            process::exit(1);
+        }
+    };
+    match Theme::parse(&content) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Invalid theme {}: {}", theme, e);
+            process::exit(1);
+        }
+    }
 }
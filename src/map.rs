@@ -8,14 +8,37 @@
  * Copyright (c) 2024 David Jackson
  */
 
+use crate::compass::{Compass, Corner};
 use crate::entities::{Entity, EntityPosition};
+use crate::glyphs::Glyph;
 use crate::graph::{Graph, NodeHandle};
+use crate::hatching::{hatch_strokes, hatched_cells};
+use crate::pdf::PdfBuilder;
 use crate::points::Point;
+use crate::print::{print_tiled, Paper};
+use crate::random::Random;
+use crate::render::Renderer;
 use crate::shapes::Shape;
-use crate::svg::{Colour, SvgBuilder};
+use crate::sketch::{sketch, sketch_circle};
+use crate::stamps::Stamp;
+use crate::svg::{Colour, Style, SvgBuilder, TextAnchor};
+use crate::theme::Theme;
+use crate::walls::outline_walls;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
-const LIGHT_GRAY: Colour = Colour::Rgb(200, 200, 200);
+/// The size of a cell when drawing maps to be printed, so that one pixel is
+/// about one point
+const PRINT_DIMENSION: usize = 72;
+
+/// The width of each entry in the legend, in cells
+const LEGEND_ENTRY_CELLS: usize = 5;
+
+/// The length of the scale bar, in cells
+const SCALE_BAR_CELLS: usize = 4;
+
+/// How wide the margin for a compass rose outside the map is, in cells
+const COMPASS_MARGIN_CELLS: usize = 3;
 
 #[derive(Debug)]
 pub struct Map {
@@ -24,6 +47,11 @@ pub struct Map {
     graph: Graph<Point>,
     point_nodes: HashMap<usize, NodeHandle>,
     entities: Vec<Entity>,
+    glyphs: Vec<Glyph>,
+    stamps: Vec<Stamp>,
+    title: Option<String>,
+    scale: Option<MapScale>,
+    compass: Option<Compass>,
 }
 
 impl Map {
@@ -40,6 +68,11 @@ impl Map {
             graph,
             point_nodes,
             entities: Vec::new(),
+            glyphs: Vec::new(),
+            stamps: Vec::new(),
+            title: None,
+            scale: None,
+            compass: None,
         }
     }
 
@@ -81,6 +114,95 @@ impl Map {
         self.graph.is_edge_between(h1, h2)
     }
 
+    /// The wall segments, each one cell long, between pairs of grid points
+    pub fn edges(&self) -> Vec<(Point, Point)> {
+        self.graph
+            .edges()
+            .iter()
+            .map(|(h1, h2)| {
+                let p1 = *self.graph.find_node(*h1).unwrap().data();
+                let p2 = *self.graph.find_node(*h2).unwrap().data();
+                (p1, p2)
+            })
+            .collect()
+    }
+
+    /// The closed loops of walls, as the polygons that are drawn for them
+    pub(crate) fn rooms(&self) -> Vec<Vec<Point>> {
+        self.graph
+            .find_cycles()
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|h| *self.graph.find_node(*h).unwrap().data())
+                    .filter(|p| self.contains_point(*p))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The walls that aren't part of any of the rooms, as the paths that are
+    /// drawn for them
+    pub(crate) fn open_walls(&self, rooms: &[Vec<Point>]) -> Vec<Vec<Point>> {
+        // Grid points that connect only into lines, rather than polygons
+        let polygon_points: HashSet<Point> = rooms.iter().flatten().copied().collect();
+        let mut paths = Vec::new();
+        let connected_components = self.graph.connected_components();
+        for cc in connected_components.iter().filter(|c| c.len() > 1) {
+            let handles: Vec<NodeHandle> = cc
+                .iter()
+                .filter(|h| !polygon_points.contains(self.graph.find_node(**h).unwrap().data()))
+                .copied()
+                .collect();
+            if handles.is_empty() {
+                continue;
+            }
+            let endpoints: Vec<NodeHandle> = handles
+                .iter()
+                .filter(|h| self.graph.find_node(**h).unwrap().edge_count() == 1)
+                .copied()
+                .collect();
+            for chunk in endpoints.chunks(2) {
+                let start = chunk[0];
+                let end = if chunk.len() == 1 {
+                    // There is an odd number of edges so arbitrarily pick an edge to draw to
+                    endpoints[0]
+                } else {
+                    chunk[1]
+                };
+                let path = self.graph.find_path(start, end).unwrap();
+                paths.push(
+                    path.iter()
+                        .map(|h| *self.graph.find_node(*h).unwrap().data())
+                        .collect(),
+                );
+            }
+        }
+        paths
+    }
+
+    /// The longest straight runs of walls, as their start and end points,
+    /// going across the map row by row and then down it column by column
+    pub(crate) fn straight_walls(&self) -> Vec<(Point, Point)> {
+        let mut walls = Vec::new();
+        for y in 0..=self.height {
+            for (x, length) in runs(self.width, |x| {
+                self.are_connected(Point::new(x, y), Point::new(x + 1, y))
+            }) {
+                walls.push((Point::new(x, y), Point::new(x + length, y)));
+            }
+        }
+        for x in 0..=self.width {
+            for (y, length) in runs(self.height, |y| {
+                self.are_connected(Point::new(x, y), Point::new(x, y + 1))
+            }) {
+                walls.push((Point::new(x, y), Point::new(x, y + length)));
+            }
+        }
+        walls
+    }
+
     fn contains_point(&self, p: Point) -> bool {
         p.x() <= self.width && p.y() <= self.height
     }
@@ -92,29 +214,213 @@ impl Map {
     pub fn entities(&self) -> &Vec<Entity> {
         &self.entities
     }
+
+    /// Register a glyph defined in the map file, returning the index by which
+    /// `Shape::Glyph` refers to it
+    pub fn add_glyph(&mut self, glyph: Glyph) -> usize {
+        self.glyphs.push(glyph);
+        self.glyphs.len() - 1
+    }
+
+    pub fn glyph(&self, index: usize) -> Option<&Glyph> {
+        self.glyphs.get(index)
+    }
+
+    /// Register a loaded stamp, returning the index by which `Shape::Stamp`
+    /// refers to it
+    pub fn add_stamp(&mut self, stamp: Stamp) -> usize {
+        self.stamps.push(stamp);
+        self.stamps.len() - 1
+    }
+
+    pub fn stamp(&self, index: usize) -> Option<&Stamp> {
+        self.stamps.get(index)
+    }
+
+    pub fn stamps(&self) -> &Vec<Stamp> {
+        &self.stamps
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = Some(title);
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn set_scale(&mut self, scale: MapScale) {
+        self.scale = Some(scale);
+    }
+
+    pub fn scale(&self) -> Option<&MapScale> {
+        self.scale.as_ref()
+    }
+
+    pub fn set_compass(&mut self, compass: Compass) {
+        self.compass = Some(compass);
+    }
+
+    pub fn compass(&self) -> Option<&Compass> {
+        self.compass.as_ref()
+    }
+}
+
+/// How much distance one grid cell represents, e.g. 5 ft
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapScale {
+    amount: f64,
+    unit: String,
+}
+
+impl MapScale {
+    pub fn new(amount: f64, unit: &str) -> MapScale {
+        MapScale {
+            amount,
+            unit: unit.to_string(),
+        }
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+}
+
+/// Settings that control how a map is drawn
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub theme: Theme,
+    /// When set, walls are drawn as solid outlines of this thickness rather
+    /// than as lines
+    pub wall_thickness: Option<WallThickness>,
+    /// Whether to cross-hatch the rock around the outside of the walls
+    pub hatching: bool,
+    /// Whether to draw walls and entities with wobbly, hand-drawn lines
+    pub sketchy: bool,
+    /// Whether to label the columns and rows of the grid along the edges of
+    /// the map
+    pub labels: bool,
+    /// The seed for anything drawn procedurally, such as hatching
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WallThickness {
+    Pixels(usize),
+    Cells(f64),
+}
+
+impl WallThickness {
+    /// Parse a thickness such as `4px` or `0.25cells`. A number without a
+    /// unit is in pixels.
+    pub fn parse(s: &str) -> Option<WallThickness> {
+        if let Some(cells) = s.strip_suffix("cells") {
+            let cells = cells.trim().parse::<f64>().ok()?;
+            if cells > 0.0 {
+                return Some(WallThickness::Cells(cells));
+            }
+            return None;
+        }
+        let pixels = s.strip_suffix("px").unwrap_or(s).trim();
+        match pixels.parse::<usize>() {
+            Ok(n) if n > 0 => Some(WallThickness::Pixels(n)),
+            _ => None,
+        }
+    }
+
+    fn to_pixels(self, dim: usize) -> usize {
+        match self {
+            WallThickness::Pixels(n) => n,
+            WallThickness::Cells(c) => ((c * dim as f64).round() as usize).max(1),
+        }
+    }
 }
 
 pub fn map_to_svg(map: &Map, dim: usize) -> String {
-    let drawing = SvgMapDrawing::new(dim, map);
-    drawing.draw(map)
+    map_to_svg_with_options(map, dim, &RenderOptions::default())
+}
+
+pub fn map_to_svg_with_options(map: &Map, dim: usize, options: &RenderOptions) -> String {
+    draw_map::<SvgBuilder>(map, dim, options).build()
+}
+
+/// Draw a map with any renderer, where a cell is `dim` pixels across
+pub fn draw_map<R: Renderer>(map: &Map, dim: usize, options: &RenderOptions) -> R {
+    MapDrawing::new(dim, map, options).draw(map)
 }
 
-struct SvgMapDrawing {
-    builder: SvgBuilder,
+/// Draw a map as a PDF to be printed at the paper's cell size, spread
+/// across as many pages as it needs
+pub fn map_to_pdf_tiles(map: &Map, options: &RenderOptions, paper: Paper) -> Vec<u8> {
+    let drawing = draw_map::<PdfBuilder>(map, PRINT_DIMENSION, options);
+    print_tiled(drawing, PRINT_DIMENSION, paper)
+}
+
+/// Draw a map as a PDF with a single page, where a cell is `dim` points
+/// across
+pub fn map_to_pdf(map: &Map, dim: usize, options: &RenderOptions) -> Vec<u8> {
+    draw_map::<PdfBuilder>(map, dim, options).build()
+}
+
+struct MapDrawing<R: Renderer> {
+    builder: R,
     dim: usize,
+    theme: Theme,
+    wall_thickness: Option<usize>,
+    hatching: bool,
+    sketchy: bool,
+    labels: bool,
+    random: Random,
+    /// The grid and thick walls, drawn bigger than the map so that the
+    /// walls around its edge aren't cut off. Whatever is drawn after the
+    /// walls goes on top of it.
+    walls: Option<R>,
 }
 
-impl SvgMapDrawing {
-    fn new(dim: usize, map: &Map) -> SvgMapDrawing {
-        let svg_width = dim * map.width();
-        let svg_height = dim * map.height();
-        SvgMapDrawing {
+impl<R: Renderer> MapDrawing<R> {
+    fn new(dim: usize, map: &Map, options: &RenderOptions) -> MapDrawing<R> {
+        let width = dim * map.width();
+        let height = dim * map.height();
+        MapDrawing {
             dim,
-            builder: SvgBuilder::new(svg_width, svg_height),
+            builder: R::new(width, height),
+            theme: options.theme.clone(),
+            wall_thickness: options.wall_thickness.map(|t| t.to_pixels(dim)),
+            hatching: options.hatching,
+            sketchy: options.sketchy,
+            labels: options.labels,
+            random: Random::new(options.seed),
+            walls: None,
         }
     }
 
-    fn draw(mut self, map: &Map) -> String {
+    fn draw(mut self, map: &Map) -> R {
+        // If there are margins, the background also has to cover them, so it
+        // is drawn when the map is laid out, and likewise if thick walls pad
+        // the map
+        if let Some(colour) = self
+            .theme
+            .background()
+            .filter(|_| !self.has_margins(map) && self.wall_thickness.is_none())
+        {
+            self.builder = self.builder.background(colour);
+        }
+
+        // Fill the rooms before drawing the grid, so that the grid shows
+        // through them
+        let rooms = map.rooms();
+        if let Some(colour) = self.theme.fill() {
+            for room in rooms.iter() {
+                let points = scale_points(room, self.dim);
+                self.builder = self.builder.polygon(points, Style::filled(colour));
+            }
+        }
+
         // Draw the grid
         for i in 0..map.width() {
             for j in 0..map.height() {
@@ -123,183 +429,881 @@ impl SvgMapDrawing {
             }
         }
 
-        // Draw grid points that connect into polygons
-        let cycles = map.graph.find_cycles();
-        for cycle in cycles.iter() {
-            let points: Vec<Point> = cycle
-                .iter()
-                .map(|h| *map.graph.find_node(*h).unwrap().data())
-                .filter(|p| map.contains_point(*p))
-                .map(|p| p.scale(self.dim))
-                .collect();
-            self = self.polygon(points);
+        if self.hatching {
+            self = self.hatching(map);
         }
 
-        // Draw grid points that connect only into lines, rather than polygons
-        let polygon_points: HashSet<Point> = cycles
-            .iter()
-            .flatten()
-            .map(|h| *map.graph.find_node(*h).unwrap().data())
-            .collect();
-        let connected_components = map.graph.connected_components();
-        for cc in connected_components.iter().filter(|c| c.len() > 1) {
-            let handles: Vec<NodeHandle> = cc
-                .iter()
-                .filter(|h| !polygon_points.contains(map.graph.find_node(**h).unwrap().data()))
-                .copied()
-                .collect();
-            if handles.is_empty() {
-                continue;
+        self = match self.wall_thickness {
+            Some(thickness) => self.thick_walls(map, thickness),
+            None => self.walls(map, &rooms),
+        };
+
+        // Draw entities
+        for entity in map.entities().iter() {
+            self = self.entity(map, entity);
+        }
+
+        if let Some(compass) = map.compass().filter(|c| !c.is_outside()) {
+            self = self.compass(map, compass);
+        }
+        self.layout(map)
+    }
+
+    fn entity(mut self, map: &Map, entity: &Entity) -> Self {
+        match entity.shape() {
+            Shape::Circle(radius) => {
+                self = self.circle_entity(entity, radius);
             }
-            let endpoints: Vec<NodeHandle> = handles
-                .iter()
-                .filter(|h| map.graph.find_node(**h).unwrap().edge_count() == 1)
-                .copied()
-                .collect();
-            for chunk in endpoints.chunks(2) {
-                let start = chunk[0];
-                let end = if chunk.len() == 1 {
-                    // There is an odd number of edges so arbitrarily pick an edge to draw to
-                    endpoints[0]
+            Shape::Square => {
+                self = self.square_entity(entity);
+            }
+            Shape::Stair => {
+                self = self.stair_entity(entity);
+            }
+            Shape::Ladder => {
+                self = self.ladder_entity(entity);
+            }
+            Shape::X => {
+                self = self.x_entity(entity);
+            }
+            Shape::Pillar => {
+                self = self.pillar_entity(entity);
+            }
+            Shape::Statue => {
+                self = self.statue_entity(entity);
+            }
+            Shape::Chest => {
+                self = self.chest_entity(entity);
+            }
+            Shape::Table => {
+                self = self.table_entity(entity);
+            }
+            Shape::Bed => {
+                self = self.bed_entity(entity);
+            }
+            Shape::Altar => {
+                self = self.altar_entity(entity);
+            }
+            Shape::Well => {
+                self = self.well_entity(entity);
+            }
+            Shape::Fountain => {
+                self = self.fountain_entity(entity);
+            }
+            Shape::Trapdoor => {
+                self = self.trapdoor_entity(entity);
+            }
+            Shape::Brazier => {
+                self = self.brazier_entity(entity);
+            }
+            Shape::Barrel => {
+                self = self.barrel_entity(entity);
+            }
+            Shape::Door => {
+                self = self.door_entity(entity);
+            }
+            Shape::Rubble => {
+                self = self.rubble_entity(entity);
+            }
+            Shape::Trap => {
+                self = self.trap_entity(entity);
+            }
+            Shape::Glyph(index) => {
+                let glyph = map.glyph(index).unwrap();
+                self = self.custom_glyph_entity(entity, glyph);
+            }
+            Shape::Stamp(index) => {
+                let stamp = map.stamp(index).unwrap();
+                self = self.stamp_entity(entity, stamp);
+            }
+        }
+        self
+    }
+
+    /// Draw a compass rose in a corner of the map, turned so that its
+    /// north point shows which way north is
+    fn compass(mut self, map: &Map, compass: &Compass) -> Self {
+        let dim = self.dim as f64;
+        let (width, height) = (
+            (self.dim * map.width()) as f64,
+            (self.dim * map.height()) as f64,
+        );
+        // Leave room around the rose for the "N"
+        let inset = dim * 1.5;
+        let (cx, cy) = match compass.corner() {
+            Corner::TopLeft => (inset, inset),
+            Corner::TopRight => (width - inset, inset),
+            Corner::BottomLeft => (inset, height - inset),
+            Corner::BottomRight => (width - inset, height - inset),
+        };
+        let colour = self.theme.entity();
+        self.builder = compass_rose(self.builder, compass, (cx, cy), self.dim, colour);
+        self
+    }
+
+    /// Place the drawing of the map on the page, along with the grid
+    /// labels, title, legend and scale bar around it
+    fn layout(self, map: &Map) -> R {
+        if !self.has_margins(map) {
+            return self.padded();
+        }
+        let pad = self.wall_padding();
+        let dim = self.dim;
+        let size = (dim / 2).max(1);
+        let colour = self.theme.wall();
+        let label_margin = if self.labels { dim } else { 0 };
+        let title_height = if map.title().is_some() { 2 * dim } else { 0 };
+        // A compass outside the map goes in a margin of its own, beyond the
+        // labels
+        let outside_compass = map.compass().filter(|c| c.is_outside());
+        let compass_margin = if outside_compass.is_some() {
+            COMPASS_MARGIN_CELLS * dim
+        } else {
+            0
+        };
+        let compass_left = outside_compass.is_some_and(|c| c.is_left());
+        let left_margin = label_margin + if compass_left { compass_margin } else { 0 };
+        let right_margin = if compass_left { 0 } else { compass_margin };
+        let origin = Point::new(left_margin + pad, title_height + label_margin + pad);
+        let map_width = dim * map.width();
+        let map_height = dim * map.height();
+        let map_top = origin.y() - pad;
+        let map_bottom = (origin.y() + map_height + pad).max(map_top + compass_margin);
+        let scale_label = map
+            .scale()
+            .map(|scale| format!("1 square = {} {}", scale.amount(), scale.unit()));
+        let scale_bar_width = map.width().clamp(1, SCALE_BAR_CELLS) * dim + dim / 4;
+
+        // Make the page wide enough for the title and scale, as well as the
+        // map itself
+        let mut width = origin.x() + map_width + pad + right_margin;
+        if let Some(title) = map.title() {
+            width = width.max(text_width(title, dim) + dim);
+        }
+        if let Some(label) = scale_label.as_ref() {
+            width = width.max(origin.x() + scale_bar_width + text_width(label, size));
+        }
+
+        // The legend and scale bar go in rows beneath the map
+        let legend = if self.has_title_block(map) {
+            legend_entries(map)
+        } else {
+            Vec::new()
+        };
+        let columns = (map_width / (LEGEND_ENTRY_CELLS * dim).max(1)).max(1);
+        let legend_rows = legend.len().div_ceil(columns);
+        let scale_rows = if map.scale().is_some() { 1 } else { 0 };
+        let footer_height = if legend_rows + scale_rows > 0 {
+            (legend_rows + scale_rows) * dim + dim
+        } else {
+            0
+        };
+        let height = map_bottom + footer_height;
+
+        let mut page = R::new(width, height);
+        if let Some(colour) = self.theme.background() {
+            page = page.background(colour);
+        }
+
+        if let Some(title) = map.title() {
+            let p = Point::new(width / 2, title_height / 2);
+            page = page.text(p, title, dim, TextAnchor::Middle, colour);
+        }
+
+        if self.labels {
+            for i in 0..map.width() {
+                let p = Point::new(origin.x() + i * dim + dim / 2, origin.y() - dim / 2);
+                page = page.text(p, &column_label(i), size, TextAnchor::Middle, colour);
+            }
+            for j in 0..map.height() {
+                // Row numbers are right-aligned, so that their digits line up
+                let p = Point::new(origin.x() - dim / 5, origin.y() + j * dim + dim / 2);
+                let label = (j + 1).to_string();
+                page = page.text(p, &label, size, TextAnchor::End, colour);
+            }
+        }
+
+        let footer = map_bottom + dim / 2;
+        for (k, (shape, label)) in legend.iter().enumerate() {
+            let x = origin.x() + (k % columns) * LEGEND_ENTRY_CELLS * dim;
+            let y = footer + (k / columns) * dim;
+            let key = self.legend_key(map, *shape);
+            page = page.group(Point::new(x, y), key);
+            let p = Point::new(x + dim + dim / 4, y + dim / 2);
+            page = page.text(p, label, size, TextAnchor::Start, colour);
+        }
+
+        if let Some(label) = scale_label {
+            let y = footer + legend_rows * dim;
+            let squares = map.width().clamp(1, SCALE_BAR_CELLS);
+            for i in 0..squares {
+                // Alternate filled and empty squares, like a ruler
+                let style = if i % 2 == 0 {
+                    Style::filled(colour)
                 } else {
-                    chunk[1]
+                    Style::stroke(colour)
                 };
-                let path = map.graph.find_path(start, end).unwrap();
-                let points: Vec<Point> = path
-                    .iter()
-                    .map(|h| *map.graph.find_node(*h).unwrap().data())
-                    .collect();
-                let points = scale_points(&points, self.dim);
-                self = self.path(points);
+                let p = Point::new(origin.x() + i * dim, y + dim / 3);
+                page = page.rect(p, dim, dim / 3, style);
             }
+            let p = Point::new(origin.x() + scale_bar_width, y + dim / 2);
+            page = page.text(p, &label, size, TextAnchor::Start, colour);
         }
 
-        // Draw entities
-        for entity in map.entities().iter() {
-            match entity.shape() {
-                Shape::Circle(radius) => {
-                    self = self.circle_entity(entity, radius);
-                }
-                Shape::Square => {
-                    self = self.square_entity(entity);
-                }
-                Shape::Stair => {
-                    self = self.stair_entity(entity);
-                }
-                Shape::Ladder => {
-                    self = self.ladder_entity(entity);
-                }
-                Shape::X => {
-                    self = self.x_entity(entity);
-                }
-            }
-        }
-        self.builder.build()
+        if let Some(compass) = outside_compass {
+            let half = compass_margin as f64 / 2.0;
+            let cx = if compass_left {
+                half
+            } else {
+                (origin.x() + map_width + pad) as f64 + half
+            };
+            let cy = match compass.corner() {
+                Corner::TopLeft | Corner::TopRight => map_top as f64 + half,
+                Corner::BottomLeft | Corner::BottomRight => map_bottom as f64 - half,
+            };
+            let colour = self.theme.entity();
+            page = compass_rose(page, compass, (cx, cy), dim, colour);
+        }
+
+        page.group(
+            Point::new(origin.x() - pad, origin.y() - pad),
+            self.padded(),
+        )
+    }
+
+    /// The drawing of the map, including any thick walls around its edge
+    fn padded(self) -> R {
+        let pad = self.wall_padding();
+        match self.walls {
+            Some(walls) => walls.group(Point::new(pad, pad), self.builder),
+            None => self.builder,
+        }
+    }
+
+    /// Whether anything is drawn around the map, so that the page has to be
+    /// bigger than the map
+    fn has_margins(&self, map: &Map) -> bool {
+        self.labels || self.has_title_block(map) || map.compass().is_some_and(|c| c.is_outside())
+    }
+
+    fn has_title_block(&self, map: &Map) -> bool {
+        map.title().is_some() || map.scale().is_some()
+    }
+
+    /// Draw an example of an entity for the legend, in a single cell
+    fn legend_key(&self, map: &Map, shape: Shape) -> R {
+        let key = MapDrawing {
+            builder: R::new(self.dim, self.dim),
+            dim: self.dim,
+            theme: self.theme.clone(),
+            wall_thickness: None,
+            hatching: false,
+            sketchy: self.sketchy,
+            labels: false,
+            random: self.random.clone(),
+            walls: None,
+        };
+        let entity = Entity::new(shape, Point::new(0, 0), EntityPosition::Within);
+        key.entity(map, &entity).builder
+    }
+
+    fn walls(mut self, map: &Map, rooms: &[Vec<Point>]) -> Self {
+        for room in rooms.iter() {
+            let points = scale_points(room, self.dim);
+            self = self.polygon(points);
+        }
+        for path in map.open_walls(rooms) {
+            let points = scale_points(&path, self.dim);
+            self = self.path(points);
+        }
+        self
+    }
+
+    /// Draw the walls as solid outlines, so that they join cleanly at
+    /// corners and junctions however thick they are
+    fn thick_walls(mut self, map: &Map, thickness: usize) -> Self {
+        let pad = self.wall_padding();
+        let width = self.dim * map.width();
+        let height = self.dim * map.height();
+        let mut walls = R::new(width + 2 * pad, height + 2 * pad);
+        if let Some(colour) = self.theme.background().filter(|_| !self.has_margins(map)) {
+            walls = walls.background(colour);
+        }
+        let beneath = std::mem::replace(&mut self.builder, walls);
+        self.builder = self.builder.group(Point::new(pad, pad), beneath);
+        let loops = outline_walls(&map.edges(), self.dim, thickness, pad);
+        if !loops.is_empty() {
+            let style = Style::filled(self.theme.wall());
+            self.builder = self.builder.outline(loops.clone(), style);
+        }
+        // Sketchy walls are filled, then gone over in pen
+        if self.sketchy {
+            let style = Style::stroke(self.theme.wall());
+            for points in loops.iter() {
+                self = self.draw_sketch(points, true, style);
+            }
+        }
+        self.walls = Some(std::mem::replace(&mut self.builder, R::new(width, height)));
+        self
+    }
+
+    /// How far thick walls stick out of the map on each side
+    fn wall_padding(&self) -> usize {
+        self.wall_thickness.map_or(0, |t| t - t / 2)
+    }
+
+    /// Cross-hatch the band of cells around the outside of the walls
+    fn hatching(mut self, map: &Map) -> Self {
+        let style = Style::stroke(self.theme.wall());
+        for cell in hatched_cells(&map.edges(), map.width(), map.height()) {
+            for (start, end) in hatch_strokes(cell, self.dim, &mut self.random) {
+                self.builder = self.builder.path(vec![start, end], style);
+            }
+        }
+        self
     }
 
     fn grid_cell(mut self, p: Point) -> Self {
+        let style = Style::stroke(self.theme.grid()).with_opacity(self.theme.grid_opacity());
         self.builder = self
             .builder
-            .rect(p.scale(self.dim), self.dim, self.dim, LIGHT_GRAY);
+            .rect(p.scale(self.dim), self.dim, self.dim, style);
+        self
+    }
+
+    fn polygon(self, points: Vec<Point>) -> Self {
+        let style = self.wall_style();
+        self.draw_polygon(points, style)
+    }
+
+    fn path(self, points: Vec<Point>) -> Self {
+        let style = self.wall_style();
+        self.draw_path(points, style)
+    }
+
+    fn draw_path(mut self, points: Vec<Point>, style: Style) -> Self {
+        if self.sketchy {
+            return self.draw_sketch(&points, false, style);
+        }
+        self.builder = self.builder.path(points, style);
         self
     }
 
-    fn polygon(mut self, points: Vec<Point>) -> Self {
-        self.builder = self.builder.polygon(points, Colour::Black);
+    fn draw_polygon(mut self, points: Vec<Point>, style: Style) -> Self {
+        if self.sketchy {
+            if let Some(colour) = style.fill() {
+                self.builder = self.builder.polygon(points.clone(), Style::filled(colour));
+            }
+            return self.draw_sketch(&points, true, style.with_fill(None));
+        }
+        self.builder = self.builder.polygon(points, style);
         self
     }
 
-    fn path(mut self, points: Vec<Point>) -> Self {
-        self.builder = self.builder.path(points, Colour::Black);
+    fn draw_rect(mut self, p: Point, width: usize, height: usize, style: Style) -> Self {
+        if self.sketchy {
+            let corners = vec![
+                p,
+                p + Point::new(width, 0),
+                p + Point::new(width, height),
+                p + Point::new(0, height),
+            ];
+            return self.draw_polygon(corners, style);
+        }
+        self.builder = self.builder.rect(p, width, height, style);
         self
     }
 
-    fn circle_entity(mut self, entity: &Entity, radius: usize) -> Self {
-        let (x, y, r) = match entity.position() {
-            EntityPosition::Within => {
-                let mid = self.dim / 2;
-                let p = entity.point().scale(self.dim) + Point::new(mid, mid);
-                let r = mid - 1;
-                (p.x(), p.y(), r)
+    /// Draw lines as overlapping, wobbly strokes, like a hand-drawn sketch
+    fn draw_sketch(mut self, points: &[Point], closed: bool, style: Style) -> Self {
+        let roughness = self.dim as f64 / 10.0;
+        for stroke in sketch(points, closed, roughness, &mut self.random) {
+            self.builder = self.builder.curve(stroke.start, stroke.curves, style);
+        }
+        self
+    }
+
+    fn draw_circle(mut self, center: Point, r: usize, style: Style) -> Self {
+        if self.sketchy {
+            if let Some(colour) = style.fill() {
+                let filled = Style::filled(colour);
+                self.builder = self.builder.circle(center.x(), center.y(), r, filled);
             }
-            EntityPosition::At => {
-                let p = entity.point().scale(self.dim);
-                (p.x(), p.y(), radius * self.dim)
+            let roughness = self.dim as f64 / 10.0;
+            let center = (center.x() as f64, center.y() as f64);
+            for stroke in sketch_circle(center, r as f64, roughness, &mut self.random) {
+                let style = style.with_fill(None);
+                self.builder = self.builder.curve(stroke.start, stroke.curves, style);
             }
+            return self;
+        }
+        self.builder = self.builder.circle(center.x(), center.y(), r, style);
+        self
+    }
+
+    fn wall_style(&self) -> Style {
+        Style::stroke(self.theme.wall()).with_width(self.theme.wall_width())
+    }
+
+    fn entity_style(&self) -> Style {
+        Style::stroke(self.theme.entity()).with_fill(self.theme.entity_fill())
+    }
+
+    fn circle_entity(self, entity: &Entity, radius: usize) -> Self {
+        let (p, r) = match entity.position() {
+            EntityPosition::Within => {
+                let (origin, width, height) = self.cell_box(entity);
+                let p = origin + Point::new(width / 2, height / 2);
+                let r = (width.min(height) / 2).saturating_sub(1);
+                (p, r)
+            }
+            EntityPosition::At => (entity.point().scale(self.dim), radius * self.dim),
         };
 
-        self.builder = self.builder.circle(x, y, r, Colour::Black);
-        self
+        let style = self.entity_style();
+        self.draw_circle(p, r, style)
     }
 
-    fn square_entity(mut self, entity: &Entity) -> Self {
-        let side = self.dim * 3 / 5; // 60% of dim
-        let offset = (self.dim - side) / 2;
-        let delta = Point::new(offset, offset);
-        let p = entity.point().scale(self.dim) + delta;
-        self.builder = self.builder.rect(p, side, side, Colour::Black);
-        self
+    fn square_entity(self, entity: &Entity) -> Self {
+        let (origin, width, height) = self.cell_box(entity);
+        let side_x = width * 3 / 5; // 60% of the covered width
+        let side_y = height * 3 / 5; // 60% of the covered height
+        let delta = Point::new((width - side_x) / 2, (height - side_y) / 2);
+        let p = origin + delta;
+        let style = self.entity_style();
+        self.draw_rect(p, side_x, side_y, style)
     }
 
-    fn stair_entity(mut self, entity: &Entity) -> Self {
-        let height = self.dim * 3 / 5; // 60% of dim
-        let offset = (self.dim - height) / 2;
-        let delta = Point::new(offset, offset);
-        let riser = self.dim / 5; // 20% of dim
-        let origin = entity.point().scale(self.dim) + delta;
+    fn stair_entity(self, entity: &Entity) -> Self {
+        let (origin, width, height) = self.cell_box(entity);
+        let height_x = width * 3 / 5; // 60% of the covered width
+        let height_y = height * 3 / 5; // 60% of the covered height
+        let delta = Point::new((width - height_x) / 2, (height - height_y) / 2);
+        let riser_x = width / 5; // 20% of the covered width
+        let riser_y = height / 5; // 20% of the covered height
+        let origin = origin + delta;
         let points = [
-            (0, 2 * riser),
-            (0, 3 * riser),
-            (height, height),
-            (height, 0),
-            (2 * riser, 0),
-            (2 * riser, riser),
-            (riser, riser),
-            (riser, 2 * riser),
+            (0, 2 * riser_y),
+            (0, 3 * riser_y),
+            (height_x, height_y),
+            (height_x, 0),
+            (2 * riser_x, 0),
+            (2 * riser_x, riser_y),
+            (riser_x, riser_y),
+            (riser_x, 2 * riser_y),
         ]
         .iter()
         .map(|(x, y)| Point::new(*x, *y) + origin)
         .collect::<Vec<Point>>();
-        self.builder = self.builder.polygon(points, Colour::Black);
-        self
+        let style = self.entity_style();
+        self.draw_polygon(points, style)
     }
 
     fn ladder_entity(mut self, entity: &Entity) -> Self {
-        let height = self.dim * 3 / 5; // 60% of dim
-        let offset = (self.dim - height) / 2;
-        let delta = Point::new(offset, offset);
-        let l = self.dim / 5; // 20% of dim is ladder dimension
-        let origin = entity.point().scale(self.dim) + delta;
-        let left_rail_points = vec![Point::new(l, 0) + origin, Point::new(l, 3 * l) + origin];
+        let (origin, width, height) = self.cell_box(entity);
+        let height_x = width * 3 / 5; // 60% of the covered width
+        let height_y = height * 3 / 5; // 60% of the covered height
+        let delta = Point::new((width - height_x) / 2, (height - height_y) / 2);
+        // 20% of the covered area is the ladder dimension
+        let lx = width / 5;
+        let ly = height / 5;
+        let origin = origin + delta;
+        let left_rail_points = vec![Point::new(lx, 0) + origin, Point::new(lx, 3 * ly) + origin];
         let right_rail_points = left_rail_points
             .iter()
-            .map(|p| *p + Point::new(l, 0))
+            .map(|p| *p + Point::new(lx, 0))
             .collect::<Vec<Point>>();
-        let top_rung = vec![Point::new(l, l) + origin, Point::new(2 * l, l) + origin];
+        let top_rung = vec![Point::new(lx, ly) + origin, Point::new(2 * lx, ly) + origin];
         let bottom_rung = vec![
-            Point::new(l, 2 * l) + origin,
-            Point::new(2 * l, 2 * l) + origin,
+            Point::new(lx, 2 * ly) + origin,
+            Point::new(2 * lx, 2 * ly) + origin,
         ];
         let paths = [left_rail_points, right_rail_points, top_rung, bottom_rung];
+        let style = self.entity_style();
         for points in paths.into_iter() {
-            self.builder = self.builder.path(points, Colour::Black);
+            self = self.draw_path(points, style);
         }
         self
     }
 
-    fn x_entity(mut self, entity: &Entity) -> Self {
-        let offset = self.dim / 5; // 20% of dim
-        let delta = Point::new(offset, offset);
-        let p = entity.point().scale(self.dim) + delta;
-        let side = self.dim * 3 / 5; // 60% of dim
-        let horiz = Point::new(side, 0);
-        let vert = Point::new(0, side);
+    fn x_entity(self, entity: &Entity) -> Self {
+        let (origin, width, height) = self.cell_box(entity);
+        let delta = Point::new(width / 5, height / 5); // 20% of the covered area
+        let p = origin + delta;
+        let horiz = Point::new(width * 3 / 5, 0); // 60% of the covered width
+        let vert = Point::new(0, height * 3 / 5); // 60% of the covered height
+        let style = self.entity_style();
         let points1 = vec![p, p + horiz + vert];
-        self.builder = self.builder.path(points1, Colour::Black);
         let points2 = vec![p + vert, p + horiz];
-        self.builder = self.builder.path(points2, Colour::Black);
+        self.draw_path(points1, style).draw_path(points2, style)
+    }
+
+    fn pillar_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 0), (12, 12))
+            .glyph_circle(&g, (6, 6), 6)
+    }
+
+    fn statue_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        let pedestal = [
+            (4, 0),
+            (8, 0),
+            (12, 4),
+            (12, 8),
+            (8, 12),
+            (4, 12),
+            (0, 8),
+            (0, 4),
+        ];
+        self.glyph_polygon(&g, &pedestal)
+            .glyph_circle(&g, (6, 6), 3)
+    }
+
+    fn chest_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 2), (12, 10))
+            .glyph_path(&g, &[(0, 5), (12, 5)])
+            .glyph_rect(&g, (5, 4), (7, 7))
+    }
+
+    fn table_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 3), (12, 9))
+            .glyph_rect(&g, (2, 0), (4, 2))
+            .glyph_rect(&g, (8, 0), (10, 2))
+            .glyph_rect(&g, (2, 10), (4, 12))
+            .glyph_rect(&g, (8, 10), (10, 12))
+    }
+
+    fn bed_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (2, 0), (10, 12))
+            .glyph_rect(&g, (3, 1), (9, 3))
+            .glyph_path(&g, &[(2, 5), (10, 5)])
+    }
+
+    fn altar_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 3), (12, 9))
+            .glyph_circle(&g, (2, 6), 1)
+            .glyph_circle(&g, (10, 6), 1)
+            .glyph_path(&g, &[(6, 4), (6, 8)])
+            .glyph_path(&g, &[(5, 5), (7, 5)])
+    }
+
+    fn well_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_circle(&g, (6, 6), 6).glyph_circle(&g, (6, 6), 4)
+    }
+
+    fn fountain_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_circle(&g, (6, 6), 6)
+            .glyph_circle(&g, (6, 6), 2)
+            .glyph_path(&g, &[(6, 1), (6, 3)])
+            .glyph_path(&g, &[(6, 9), (6, 11)])
+            .glyph_path(&g, &[(1, 6), (3, 6)])
+            .glyph_path(&g, &[(9, 6), (11, 6)])
+    }
+
+    fn trapdoor_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 0), (12, 12))
+            .glyph_path(&g, &[(0, 2), (12, 2)])
+            .glyph_circle(&g, (6, 9), 1)
+    }
+
+    fn brazier_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        let flame = [(3, 8), (4, 3), (6, 6), (8, 2), (9, 8)];
+        self.glyph_circle(&g, (6, 6), 6).glyph_path(&g, &flame)
+    }
+
+    fn barrel_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_circle(&g, (6, 6), 6)
+            .glyph_path(&g, &[(4, 1), (4, 11)])
+            .glyph_path(&g, &[(8, 1), (8, 11)])
+    }
+
+    fn door_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_rect(&g, (0, 5), (12, 7))
+    }
+
+    fn rubble_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_polygon(&g, &[(1, 2), (4, 1), (3, 4)])
+            .glyph_polygon(&g, &[(7, 2), (10, 3), (8, 5)])
+            .glyph_polygon(&g, &[(2, 8), (5, 7), (5, 10)])
+            .glyph_polygon(&g, &[(8, 8), (11, 9), (9, 11)])
+    }
+
+    fn trap_entity(self, entity: &Entity) -> Self {
+        let g = self.glyph_box(entity);
+        self.glyph_polygon(&g, &[(6, 0), (12, 12), (0, 12)])
+            .glyph_path(&g, &[(6, 4), (6, 8)])
+            .glyph_circle(&g, (6, 10), 1)
+    }
+
+    fn custom_glyph_entity(mut self, entity: &Entity, glyph: &Glyph) -> Self {
+        let g = self.glyph_box(entity);
+        let style = self.entity_style();
+        for path in glyph.paths() {
+            let points = path.points().iter().map(|(x, y)| g.scale(*x, *y)).collect();
+            self = if path.is_closed() {
+                self.draw_polygon(points, style)
+            } else {
+                self.draw_path(points, style)
+            };
+        }
+        self
+    }
+
+    fn stamp_entity(mut self, entity: &Entity, stamp: &Stamp) -> Self {
+        let (origin, width, height) = self.cell_box(entity);
+        self.builder = self.builder.embed(origin, width, height, stamp);
         self
     }
+
+    fn glyph_rect(
+        self,
+        g: &GlyphBox,
+        top_left: (usize, usize),
+        bottom_right: (usize, usize),
+    ) -> Self {
+        let p1 = g.point(top_left.0, top_left.1);
+        let p2 = g.point(bottom_right.0, bottom_right.1);
+        let style = self.entity_style();
+        self.draw_rect(p1, p2.x() - p1.x(), p2.y() - p1.y(), style)
+    }
+
+    fn glyph_circle(self, g: &GlyphBox, center: (usize, usize), radius: usize) -> Self {
+        let p = g.point(center.0, center.1);
+        let style = self.entity_style();
+        self.draw_circle(p, g.length(radius), style)
+    }
+
+    fn glyph_path(self, g: &GlyphBox, points: &[(usize, usize)]) -> Self {
+        let points = points.iter().map(|(x, y)| g.point(*x, *y)).collect();
+        let style = self.entity_style();
+        self.draw_path(points, style)
+    }
+
+    fn glyph_polygon(self, g: &GlyphBox, points: &[(usize, usize)]) -> Self {
+        let points = points.iter().map(|(x, y)| g.point(*x, *y)).collect();
+        let style = self.entity_style();
+        self.draw_polygon(points, style)
+    }
+
+    /// The box that glyphs are drawn into: the middle 60% of the cells
+    /// covered by an entity, as with the square, stair and ladder
+    fn glyph_box(&self, entity: &Entity) -> GlyphBox {
+        let (origin, width, height) = self.cell_box(entity);
+        let inner_width = width * 3 / 5;
+        let inner_height = height * 3 / 5;
+        let delta = Point::new((width - inner_width) / 2, (height - inner_height) / 2);
+        GlyphBox {
+            origin: origin + delta,
+            width: inner_width,
+            height: inner_height,
+        }
+    }
+
+    /// The top-left corner, width and height, in pixels, of the cells an
+    /// entity covers
+    fn cell_box(&self, entity: &Entity) -> (Point, usize, usize) {
+        let origin = entity.point().scale(self.dim);
+        (
+            origin,
+            entity.width() * self.dim,
+            entity.height() * self.dim,
+        )
+    }
+}
+
+/// Number of units along each side of a glyph box that glyph coordinates are
+/// given in
+const GLYPH_UNITS: usize = 12;
+
+struct GlyphBox {
+    origin: Point,
+    width: usize,
+    height: usize,
+}
+
+impl GlyphBox {
+    fn point(&self, x: usize, y: usize) -> Point {
+        self.origin + Point::new(self.width * x / GLYPH_UNITS, self.height * y / GLYPH_UNITS)
+    }
+
+    /// Scale a point given in unit-cell coordinates into the box
+    fn scale(&self, x: f64, y: f64) -> Point {
+        let dx = (self.width as f64 * x).round() as usize;
+        let dy = (self.height as f64 * y).round() as usize;
+        self.origin + Point::new(dx, dy)
+    }
+
+    fn length(&self, units: usize) -> usize {
+        self.width.min(self.height) * units / GLYPH_UNITS
+    }
+}
+
+/// The shapes of the entities on a map, each with a name for the legend, in
+/// the order in which they first appear
+pub(crate) fn legend_entries(map: &Map) -> Vec<(Shape, String)> {
+    let mut entries: Vec<(Shape, String)> = Vec::new();
+    for entity in map.entities() {
+        let shape = legend_shape(map, entity.shape());
+        if !entries.iter().any(|(s, _)| *s == shape) {
+            entries.push((shape, shape_label(map, shape)));
+        }
+    }
+    entries
+}
+
+/// The shape that stands for an entity's shape in the legend. Shapes are
+/// told apart by what they are, not by their labels, so a glyph can share a
+/// name with a built-in shape.
+pub(crate) fn legend_shape(map: &Map, shape: Shape) -> Shape {
+    match shape {
+        // Circles of any size are shown as one that fits in the key
+        Shape::Circle(_) => Shape::Circle(0),
+        // The same file stamped in several places is one entry
+        Shape::Stamp(index) => {
+            let path = map.stamp(index).unwrap().path();
+            let first = map.stamps().iter().position(|s| s.path() == path);
+            Shape::Stamp(first.unwrap_or(index))
+        }
+        shape => shape,
+    }
+}
+
+pub(crate) fn shape_label(map: &Map, shape: Shape) -> String {
+    let label = match shape {
+        Shape::Circle(_) => "Circle",
+        Shape::Square => "Square",
+        Shape::Stair => "Stairs",
+        Shape::Ladder => "Ladder",
+        Shape::X => "X",
+        Shape::Pillar => "Pillar",
+        Shape::Statue => "Statue",
+        Shape::Chest => "Chest",
+        Shape::Table => "Table",
+        Shape::Bed => "Bed",
+        Shape::Altar => "Altar",
+        Shape::Well => "Well",
+        Shape::Fountain => "Fountain",
+        Shape::Trapdoor => "Trapdoor",
+        Shape::Brazier => "Brazier",
+        Shape::Barrel => "Barrel",
+        Shape::Door => "Door",
+        Shape::Rubble => "Rubble",
+        Shape::Trap => "Trap",
+        Shape::Glyph(index) => return capitalise(map.glyph(index).unwrap().name()),
+        Shape::Stamp(index) => {
+            let path = Path::new(map.stamp(index).unwrap().path());
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            return capitalise(&name.replace(['_', '-'], " "));
+        }
+    };
+    label.to_string()
+}
+
+fn capitalise(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Draw a compass rose centred on a point, for a map whose cells are `dim`
+/// pixels across
+fn compass_rose<R: Renderer>(
+    builder: R,
+    compass: &Compass,
+    (cx, cy): (f64, f64),
+    dim: usize,
+    colour: Colour,
+) -> R {
+    let point = |(x, y): (f64, f64)| {
+        let (x, y) = compass.rotate((x, y));
+        Point::new(
+            (cx + x).round().max(0.0) as usize,
+            (cy + y).round().max(0.0) as usize,
+        )
+    };
+
+    // A four-pointed star, with the half of the north point filled in
+    let r = dim as f64 * 0.8;
+    let w = r / 4.0;
+    let star = [
+        (0.0, -r),
+        (w, -w),
+        (r, 0.0),
+        (w, w),
+        (0.0, r),
+        (-w, w),
+        (-r, 0.0),
+        (-w, -w),
+    ];
+    let north = [(0.0, -r), (w, -w), (0.0, 0.0)];
+    let centre = point((0.0, 0.0));
+    let radius = (r * 0.6).round() as usize;
+    let size = (dim / 2).max(1);
+    let label = point((0.0, -(r + size as f64 * 0.8)));
+    builder
+        .circle(centre.x(), centre.y(), radius, Style::stroke(colour))
+        .polygon(
+            star.iter().map(|p| point(*p)).collect(),
+            Style::stroke(colour),
+        )
+        .polygon(
+            north.iter().map(|p| point(*p)).collect(),
+            Style::filled(colour),
+        )
+        .text(label, "N", size, TextAnchor::Middle, colour)
+}
+
+/// Roughly how wide a line of text will be drawn, since the actual width
+/// depends on the font
+fn text_width(text: &str, size: usize) -> usize {
+    text.chars().count() * size * 3 / 5
+}
+
+/// The letters labelling a column of the grid: A to Z, then AA, AB and so on
+pub(crate) fn column_label(column: usize) -> String {
+    let mut label = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        let letter = (n - 1) % 26;
+        label.push((b'A' + letter as u8) as char);
+        n = (n - 1) / 26;
+    }
+    label.iter().rev().collect()
+}
+
+/// The starts and lengths of the unbroken runs of steps in `0..length` for
+/// which `connected` holds
+fn runs<F>(length: usize, connected: F) -> Vec<(usize, usize)>
+where
+    F: Fn(usize) -> bool,
+{
+    let mut runs = Vec::new();
+    let mut start = None;
+    for i in 0..=length {
+        match (start, i < length && connected(i)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                runs.push((s, i - s));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
 }
 
 fn grid_points(width: usize, height: usize) -> PointsIter {
@@ -343,6 +1347,7 @@ impl Iterator for PointsIter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::svg::Colour;
 
     #[test]
     fn test_empty_map() {
@@ -380,6 +1385,262 @@ mod tests {
         assert!(map.contains_point(valid_point));
     }
 
+    #[test]
+    fn test_parse_wall_thickness() {
+        assert_eq!(WallThickness::parse("4px"), Some(WallThickness::Pixels(4)));
+        assert_eq!(WallThickness::parse("3"), Some(WallThickness::Pixels(3)));
+        assert_eq!(
+            WallThickness::parse("0.25cells"),
+            Some(WallThickness::Cells(0.25))
+        );
+        assert_eq!(WallThickness::parse("0px"), None);
+        assert_eq!(WallThickness::parse("thick"), None);
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut map = Map::new(3, 2);
+        map.connect(point(1, 1), point(1, 2));
+        map.connect(point(1, 1), point(2, 1));
+        map.connect(point(1, 2), point(1, 1));
+        assert_eq!(
+            map.edges(),
+            vec![(point(1, 1), point(1, 2)), (point(1, 1), point(2, 1))]
+        );
+    }
+
+    #[test]
+    fn test_circle_entity_in_tiny_cells() {
+        let mut map = Map::new(2, 1);
+        map.add_entity(Entity::new(
+            Shape::Circle(1),
+            point(0, 0),
+            EntityPosition::Within,
+        ));
+        map.add_entity(Entity::spanning(Shape::Circle(1), point(1, 0), 1, 1));
+        assert!(map_to_svg(&map, 1).contains("<circle"));
+    }
+
+    #[test]
+    fn test_legend_entries_are_told_apart_by_shape() {
+        let mut map = Map::new(3, 1);
+        map.add_glyph(Glyph::new("Square".to_string(), Vec::new()));
+        for (x, shape) in [Shape::Square, Shape::Glyph(0), Shape::Square]
+            .into_iter()
+            .enumerate()
+        {
+            map.add_entity(Entity::new(shape, point(x, 0), EntityPosition::Within));
+        }
+        let shapes: Vec<Shape> = legend_entries(&map).into_iter().map(|(s, _)| s).collect();
+        assert_eq!(shapes, vec![Shape::Square, Shape::Glyph(0)]);
+    }
+
+    #[test]
+    fn test_legend_with_no_cell_size() {
+        let mut map = Map::new(2, 2);
+        map.set_title("Hall".to_string());
+        map.set_scale(MapScale::new(5.0, "ft"));
+        assert!(map_to_svg(&map, 0).starts_with("<svg"));
+    }
+
+    #[test]
+    fn test_thick_border_walls_are_not_cut_off() {
+        let mut map = Map::new(1, 1);
+        map.connect(point(0, 0), point(1, 0));
+        let options = RenderOptions {
+            wall_thickness: Some(WallThickness::Pixels(4)),
+            ..Default::default()
+        };
+        let svg = map_to_svg_with_options(&map, 10, &options);
+        assert!(svg.starts_with("<svg version=\"1.1\" width=\"14\" height=\"14\""));
+        assert!(svg.contains("<path d=\"M0 0 L14 0 L14 4 L0 4 Z\""));
+        assert!(svg.contains("<g transform=\"translate(2 2)\"><rect x=\"0\" y=\"0\""));
+    }
+
+    #[test]
+    fn test_sketchy_circles() {
+        let mut map = Map::new(4, 1);
+        for (i, shape) in [
+            Shape::Circle(0),
+            Shape::Pillar,
+            Shape::Well,
+            Shape::Fountain,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            map.add_entity(Entity::new(shape, point(i, 0), EntityPosition::Within));
+        }
+        let options = RenderOptions {
+            sketchy: true,
+            ..Default::default()
+        };
+        let svg = map_to_svg_with_options(&map, 20, &options);
+        assert!(!svg.contains("<circle"));
+        assert!(svg.contains(" C"));
+    }
+
+    #[test]
+    fn test_sketchy_thick_walls() {
+        let mut map = Map::new(1, 1);
+        map.connect(point(0, 0), point(1, 0));
+        let options = RenderOptions {
+            sketchy: true,
+            wall_thickness: Some(WallThickness::Pixels(4)),
+            ..Default::default()
+        };
+        let svg = map_to_svg_with_options(&map, 20, &options);
+        assert!(svg.contains("fill-rule=\"evenodd\""));
+        // Each outline is gone over with more than one stroke
+        assert!(svg.matches("<path d=\"M").count() > 2);
+    }
+
+    /// Records the points of everything drawn, one list per shape
+    struct PointsRenderer {
+        width: usize,
+        height: usize,
+        shapes: Vec<Vec<(f64, f64)>>,
+    }
+
+    impl PointsRenderer {
+        fn draw(mut self, points: Vec<(f64, f64)>) -> Self {
+            self.shapes.push(points);
+            self
+        }
+    }
+
+    fn to_f64(points: &[Point]) -> Vec<(f64, f64)> {
+        points
+            .iter()
+            .map(|p| (p.x() as f64, p.y() as f64))
+            .collect()
+    }
+
+    impl Renderer for PointsRenderer {
+        fn new(width: usize, height: usize) -> Self {
+            PointsRenderer {
+                width,
+                height,
+                shapes: Vec::new(),
+            }
+        }
+
+        fn width(&self) -> usize {
+            self.width
+        }
+
+        fn height(&self) -> usize {
+            self.height
+        }
+
+        fn rect(self, p: Point, width: usize, height: usize, _: impl Into<Style>) -> Self {
+            self.draw(to_f64(&[p, p + Point::new(width, height)]))
+        }
+
+        fn path(self, points: Vec<Point>, _: impl Into<Style>) -> Self {
+            self.draw(to_f64(&points))
+        }
+
+        fn circle(self, x: usize, y: usize, radius: usize, _: impl Into<Style>) -> Self {
+            let (x, y, r) = (x as f64, y as f64, radius as f64);
+            self.draw(vec![(x - r, y - r), (x + r, y + r)])
+        }
+
+        fn polygon(self, points: Vec<Point>, _: impl Into<Style>) -> Self {
+            self.draw(to_f64(&points))
+        }
+
+        fn outline(self, loops: Vec<Vec<Point>>, _: impl Into<Style>) -> Self {
+            self.draw(to_f64(&loops.concat()))
+        }
+
+        fn curve(
+            self,
+            start: (f64, f64),
+            curves: Vec<[(f64, f64); 3]>,
+            _: impl Into<Style>,
+        ) -> Self {
+            let mut points = vec![start];
+            points.extend(curves.into_iter().flatten());
+            self.draw(points)
+        }
+
+        fn text(self, p: Point, _: &str, _: usize, _: TextAnchor, _: Colour) -> Self {
+            self.draw(to_f64(&[p]))
+        }
+
+        fn group(mut self, p: Point, inner: Self) -> Self {
+            for shape in inner.shapes {
+                let (dx, dy) = (p.x() as f64, p.y() as f64);
+                self.shapes
+                    .push(shape.into_iter().map(|(x, y)| (x + dx, y + dy)).collect());
+            }
+            self
+        }
+    }
+
+    #[test]
+    fn test_entity_glyphs_draw_within_their_cell() {
+        let dim = 20;
+        let empty = Map::new(3, 3);
+        let background: Vec<String> = draw_map::<PointsRenderer>(&empty, dim, &Default::default())
+            .shapes
+            .iter()
+            .map(|shape| format!("{:?}", shape))
+            .collect();
+        for shape in [
+            Shape::Circle(0),
+            Shape::Square,
+            Shape::Stair,
+            Shape::Ladder,
+            Shape::X,
+            Shape::Pillar,
+            Shape::Statue,
+            Shape::Chest,
+            Shape::Table,
+            Shape::Bed,
+            Shape::Altar,
+            Shape::Well,
+            Shape::Fountain,
+            Shape::Trapdoor,
+            Shape::Brazier,
+            Shape::Barrel,
+            Shape::Door,
+            Shape::Rubble,
+            Shape::Trap,
+        ] {
+            let mut map = Map::new(3, 3);
+            map.add_entity(Entity::new(shape, point(1, 1), EntityPosition::Within));
+            let mut drawn = draw_map::<PointsRenderer>(&map, dim, &Default::default()).shapes;
+            // Leave out what is drawn without the entity
+            for key in background.iter() {
+                let i = drawn.iter().position(|s| format!("{:?}", s) == *key);
+                drawn.remove(i.unwrap());
+            }
+            assert!(!drawn.is_empty(), "{:?} draws nothing", shape);
+            for (x, y) in drawn.concat() {
+                assert!(
+                    (20.0..=40.0).contains(&x) && (20.0..=40.0).contains(&y),
+                    "{:?} draws outside its cell at {}, {}",
+                    shape,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_column_label() {
+        assert_eq!(column_label(0), "A");
+        assert_eq!(column_label(5), "F");
+        assert_eq!(column_label(25), "Z");
+        assert_eq!(column_label(26), "AA");
+        assert_eq!(column_label(27), "AB");
+        assert_eq!(column_label(701), "ZZ");
+        assert_eq!(column_label(702), "AAA");
+    }
+
     #[test]
     fn test_point_exists() {
         let map = Map::new(2, 2);
@@ -10,32 +10,71 @@
 
 use crate::ast::GridDimensionsNode;
 
-use crate::ast::{AbstractSyntaxTree, AstNode, AstNodeType, EntityNode, ShapeNode};
+use crate::ast::{
+    AbstractSyntaxTree, AstNode, AstNodeType, EntityNode, ScaleNode, ShapeNode, StampNode, Trivia,
+};
+use crate::compass::{Compass, Corner};
 use crate::compile_error::{CompileError, CompileErrorType, SyntaxError};
 use crate::entities::EntityPosition;
-use crate::lexer::lex;
+use crate::glyphs::{Glyph, GlyphPath};
+use crate::lexer::{keyword, lex_with_comments};
 use crate::points::Point;
 use crate::shapes::{Line, LineOrientation, Rect, Shape, ShapeBoolean};
 use crate::source_location::SourceLocation;
-use crate::token::{Token, TokenType};
+use crate::token::{Comment, Token, TokenType};
 
-const ENTITY_SHAPES: [TokenType; 5] = [
+const ENTITY_SHAPES: [TokenType; 19] = [
     TokenType::Circle,
     TokenType::Square,
     TokenType::Stair,
     TokenType::Ladder,
     TokenType::X,
+    TokenType::Pillar,
+    TokenType::Statue,
+    TokenType::Chest,
+    TokenType::Table,
+    TokenType::Bed,
+    TokenType::Altar,
+    TokenType::Well,
+    TokenType::Fountain,
+    TokenType::Trapdoor,
+    TokenType::Brazier,
+    TokenType::Barrel,
+    TokenType::Door,
+    TokenType::Rubble,
+    TokenType::Trap,
 ];
 
 pub fn parse(input: &str) -> Result<AbstractSyntaxTree, CompileError> {
-    let tokens = lex(input)?;
-    let parser = Parser { tokens, i: 0 };
+    let (tokens, comments) = lex_with_comments(input)?;
+    // A glyph named after a built-in shape replaces it throughout the file,
+    // so that moving the definition doesn't change what is drawn
+    let shadowed = tokens
+        .windows(2)
+        .filter(|pair| matches!(pair[0].token_type, TokenType::Glyph))
+        .filter_map(|pair| keyword(&pair[1].token_type))
+        .map(str::to_string)
+        .collect();
+    let parser = Parser {
+        tokens,
+        i: 0,
+        glyph_names: Vec::new(),
+        shadowed,
+        comments: comments.into_iter().peekable(),
+        last_line: 0,
+    };
     parser.parse()
 }
 
 struct Parser {
     tokens: Vec<Token>,
     i: usize,
+    glyph_names: Vec<String>,
+    /// Built-in shape keywords that the file defines glyphs for
+    shadowed: Vec<String>,
+    comments: std::iter::Peekable<std::vec::IntoIter<Comment>>,
+    /// The last line that a node or comment has been attached from
+    last_line: usize,
 }
 
 impl Parser {
@@ -43,32 +82,105 @@ impl Parser {
         let mut ast = AbstractSyntaxTree::new();
 
         let grid_dimensions_node = self.parse_grid_dimensions()?;
-        ast.add_node(grid_dimensions_node);
+        ast.add_node(self.attach_comments(grid_dimensions_node));
 
         while self.next_matches_any(&[
             TokenType::Rect,
             TokenType::Entity,
             TokenType::Xor,
             TokenType::Line,
+            TokenType::Glyph,
+            TokenType::Stamp,
+            TokenType::Title,
+            TokenType::Scale,
+            TokenType::Compass,
         ]) {
             let boolean_op = self.parse_boolean_op();
             if self.next_matches(TokenType::Rect) {
                 let node = self.parse_rect(boolean_op)?;
-                ast.add_node(node);
+                ast.add_node(self.attach_comments(node));
             } else if self.next_matches(TokenType::Entity) {
                 let node = self.parse_entity()?;
-                ast.add_node(node);
+                ast.add_node(self.attach_comments(node));
             } else if self.next_matches(TokenType::Line) {
                 let node = self.parse_line(boolean_op)?;
-                ast.add_node(node);
+                ast.add_node(self.attach_comments(node));
+            } else if self.next_matches(TokenType::Glyph) {
+                let node = self.parse_glyph()?;
+                ast.add_node(self.attach_comments(node));
+            } else if self.next_matches(TokenType::Stamp) {
+                let node = self.parse_stamp()?;
+                ast.add_node(self.attach_comments(node));
+            } else if self.next_matches(TokenType::Title) {
+                let node = self.parse_title()?;
+                ast.add_node(self.attach_comments(node));
+            } else if self.next_matches(TokenType::Scale) {
+                let node = self.parse_scale()?;
+                ast.add_node(self.attach_comments(node));
+            } else if self.next_matches(TokenType::Compass) {
+                let node = self.parse_compass()?;
+                ast.add_node(self.attach_comments(node));
             } else {
                 panic!("Unexpected token type");
             }
         }
+        if let Some(token) = self.peek() {
+            if let TokenType::Identifier(name) = &token.token_type {
+                return Err(CompileError::new(
+                    CompileErrorType::UnrecognizedKeyword(name.clone()),
+                    token.location.line,
+                    token.location.col,
+                ));
+            }
+        }
+
+        let trailing = self.leading_trivia(usize::MAX);
+        ast.set_trailing(trailing);
 
         Ok(ast)
     }
 
+    /// Attach the comments before a node that has just been parsed, and any
+    /// comment at the end of its last line. Comments inside a node that
+    /// spans several lines are moved before it.
+    fn attach_comments(&mut self, node: AstNode) -> AstNode {
+        let end_line = self.tokens[self.i - 1].location.line;
+        let mut leading = self.leading_trivia(end_line);
+        if self.is_blank_between(self.last_line, node.location().line) {
+            leading.push(Trivia::BlankLine);
+        }
+        let trailing = self
+            .comments
+            .next_if(|c| c.location.line == end_line)
+            .map(|c| c.text);
+        self.last_line = end_line;
+        node.with_comments(leading, trailing)
+    }
+
+    /// Take the comments before a line, with the blank lines between them
+    fn leading_trivia(&mut self, line: usize) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
+        while let Some(comment) = self.comments.next_if(|c| c.location.line < line) {
+            if self.is_blank_between(self.last_line, comment.location.line) {
+                trivia.push(Trivia::BlankLine);
+            }
+            self.last_line = comment.location.line;
+            trivia.push(Trivia::Comment(comment.text));
+        }
+        trivia
+    }
+
+    /// Whether there is a blank line between two lines, after the first
+    /// node or comment
+    fn is_blank_between(&self, first: usize, last: usize) -> bool {
+        if first == 0 || last <= first + 1 {
+            return false;
+        }
+        // The tokens are in order, so find the first one after `first`
+        let i = self.tokens.partition_point(|t| t.location.line <= first);
+        self.tokens.get(i).is_none_or(|t| t.location.line >= last)
+    }
+
     fn parse_grid_dimensions(&mut self) -> Result<AstNode, CompileError> {
         let location = self.accept(TokenType::Grid)?.location;
         let width = self.accept_number()?;
@@ -116,11 +228,7 @@ impl Parser {
             LineOrientation::Bottom
         } else {
             let loc = self.consume()?.location;
-            return Err(CompileError::new(
-                CompileErrorType::InvalidOrientation,
-                loc.line,
-                loc.col,
-            ));
+            return Err(invalid_orientation(loc));
         };
         self.consume()?; // Consume the orientation token
         self.accept(TokenType::From)?;
@@ -141,8 +249,161 @@ impl Parser {
         Ok(Point::new(x, y))
     }
 
+    fn parse_glyph(&mut self) -> Result<AstNode, CompileError> {
+        let location = self.accept(TokenType::Glyph)?.location;
+        let name_location = self.peek().map(|t| t.location).unwrap_or(location);
+        let name = self.accept_name()?;
+        if self.glyph_names.contains(&name) {
+            return Err(CompileError::new(
+                CompileErrorType::DuplicateGlyph(name),
+                name_location.line,
+                name_location.col,
+            ));
+        }
+        self.accept(TokenType::LeftBrace)?;
+        let mut paths = Vec::new();
+        while self.next_matches(TokenType::Path) {
+            paths.push(self.parse_glyph_path()?);
+            if !self.next_matches(TokenType::Semicolon) {
+                break;
+            }
+            self.accept(TokenType::Semicolon)?;
+        }
+        self.accept(TokenType::RightBrace)?;
+        self.glyph_names.push(name.clone());
+        let node_type = AstNodeType::Glyph(Glyph::new(name, paths));
+        Ok(AstNode::new(node_type, location))
+    }
+
+    /// Whether the next token is a keyword that a glyph has been named after
+    fn next_is_glyph_keyword(&self) -> bool {
+        self.peek()
+            .and_then(|t| keyword(&t.token_type))
+            .is_some_and(|k| self.shadowed.iter().any(|s| s == k))
+    }
+
+    /// The shape for a glyph, which must be defined before it is used
+    fn glyph_shape(&self, name: String, location: SourceLocation) -> Result<Shape, CompileError> {
+        match self.glyph_index(&name) {
+            Some(index) => Ok(Shape::Glyph(index)),
+            None => Err(CompileError::new(
+                CompileErrorType::UndefinedGlyph(name),
+                location.line,
+                location.col,
+            )),
+        }
+    }
+
+    /// The index of a glyph that has been defined, by its name
+    fn glyph_index(&self, name: &str) -> Option<usize> {
+        self.glyph_names.iter().position(|n| n == name)
+    }
+
+    fn parse_glyph_path(&mut self) -> Result<GlyphPath, CompileError> {
+        self.accept(TokenType::Path)?;
+        let mut points = Vec::new();
+        while self.next_matches_any(&[TokenType::Number(0), TokenType::Decimal(0.0)]) {
+            let location = self.peek().unwrap().location;
+            let x = self.accept_coordinate()?;
+            self.accept(TokenType::Comma)?;
+            let y = self.accept_coordinate()?;
+            if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+                // Glyph points must lie within the unit cell
+                return Err(CompileError::new(
+                    CompileErrorType::OutOfBounds,
+                    location.line,
+                    location.col,
+                ));
+            }
+            points.push((x, y));
+        }
+        if points.is_empty() {
+            let token = self.consume()?;
+            return Err(syntax_error(TokenType::Number(0), token));
+        }
+        let closed = self.next_matches(TokenType::Close);
+        if closed {
+            self.accept(TokenType::Close)?;
+        }
+        Ok(GlyphPath::new(points, closed))
+    }
+
+    fn parse_stamp(&mut self) -> Result<AstNode, CompileError> {
+        let location = self.accept(TokenType::Stamp)?.location;
+        let path = self.accept_string()?;
+        self.accept(TokenType::Within)?;
+        let point = self.parse_point()?;
+        let size = if self.next_matches(TokenType::Size) {
+            let location = self.accept(TokenType::Size)?.location;
+            match self.accept_number()? {
+                0 => {
+                    return Err(CompileError::new(
+                        CompileErrorType::InvalidSize,
+                        location.line,
+                        location.col,
+                    ))
+                }
+                size => size as usize,
+            }
+        } else {
+            1
+        };
+        let node_type = AstNodeType::Stamp(StampNode { path, point, size });
+        Ok(AstNode::new(node_type, location))
+    }
+
+    fn parse_title(&mut self) -> Result<AstNode, CompileError> {
+        let location = self.accept(TokenType::Title)?.location;
+        let title = self.accept_string()?;
+        Ok(AstNode::new(AstNodeType::Title(title), location))
+    }
+
+    fn parse_scale(&mut self) -> Result<AstNode, CompileError> {
+        let location = self.accept(TokenType::Scale)?.location;
+        let amount = self.accept_coordinate()?;
+        let unit = self.accept_identifier()?;
+        let scale = ScaleNode { amount, unit };
+        Ok(AstNode::new(AstNodeType::Scale(scale), location))
+    }
+
+    fn parse_compass(&mut self) -> Result<AstNode, CompileError> {
+        let location = self.accept(TokenType::Compass)?.location;
+        self.accept(TokenType::At)?;
+        let token = self.consume()?;
+        let top = match token.token_type {
+            TokenType::Top => true,
+            TokenType::Bottom => false,
+            _ => return Err(invalid_orientation(token.location)),
+        };
+        let token = self.consume()?;
+        let left = match token.token_type {
+            TokenType::Left => true,
+            TokenType::Right => false,
+            _ => return Err(invalid_orientation(token.location)),
+        };
+        let corner = match (top, left) {
+            (true, true) => Corner::TopLeft,
+            (true, false) => Corner::TopRight,
+            (false, true) => Corner::BottomLeft,
+            (false, false) => Corner::BottomRight,
+        };
+        let outside = self.next_matches(TokenType::Outside);
+        if outside {
+            self.accept(TokenType::Outside)?;
+        }
+        let rotation = if self.next_matches(TokenType::Rotate) {
+            self.accept(TokenType::Rotate)?;
+            self.accept_coordinate()?
+        } else {
+            0.0
+        };
+        let compass = Compass::new(corner, rotation).with_outside(outside);
+        Ok(AstNode::new(AstNodeType::Compass(compass), location))
+    }
+
     fn parse_entity(&mut self) -> Result<AstNode, CompileError> {
         let node_location = self.accept(TokenType::Entity)?.location;
+        let shape_location = self.peek().map(|t| t.location).unwrap_or(node_location);
         let shape_token_type = self.parse_shape()?;
         let position_location: SourceLocation;
         let position: EntityPosition;
@@ -167,10 +428,11 @@ impl Parser {
                 tok.location.col,
             ));
         }
-        let x = self.accept_number()? as usize;
-        self.accept(TokenType::Comma)?;
-        let y = self.accept_number()? as usize;
-        let point = Point::new(x, y);
+        let point = self.parse_point()?;
+        let (width, height) = match position {
+            EntityPosition::Within => self.parse_cell_range(point)?,
+            EntityPosition::At => (1, 1),
+        };
 
         let shape = match shape_token_type {
             TokenType::Circle => {
@@ -183,32 +445,25 @@ impl Parser {
                 } as usize;
                 Shape::Circle(radius)
             }
-            TokenType::Square => {
+            TokenType::Identifier(name) => {
                 if matches!(position, EntityPosition::At) {
                     return Err(invalid_position(position_location));
                 }
-                Shape::Square
+                self.glyph_shape(name, shape_location)?
             }
-            TokenType::Stair => {
+            ref token_type
+                if keyword(token_type).is_some_and(|k| self.shadowed.iter().any(|s| s == k)) =>
+            {
                 if matches!(position, EntityPosition::At) {
                     return Err(invalid_position(position_location));
                 }
-                Shape::Stair
+                self.glyph_shape(keyword(token_type).unwrap().to_string(), shape_location)?
             }
-            TokenType::Ladder => {
-                if matches!(position, EntityPosition::At) {
-                    return Err(invalid_position(position_location));
-                }
-                Shape::Ladder
-            }
-            TokenType::X => {
+            _ => {
                 if matches!(position, EntityPosition::At) {
                     return Err(invalid_position(position_location));
                 }
-                Shape::X
-            }
-            _ => {
-                panic!("Unexpected shape token type {:?}", shape_token_type);
+                cell_shape(shape_token_type, shape_location)?
             }
         };
 
@@ -216,11 +471,31 @@ impl Parser {
             shape,
             point,
             position,
+            width,
+            height,
         });
         let node = AstNode::new(node_type, node_location);
         Ok(node)
     }
 
+    /// Parse the optional `to X, Y` suffix of a `within` position, returning
+    /// the number of cells covered horizontally and vertically
+    fn parse_cell_range(&mut self, start: Point) -> Result<(usize, usize), CompileError> {
+        if !self.next_matches(TokenType::To) {
+            return Ok((1, 1));
+        }
+        let location = self.accept(TokenType::To)?.location;
+        let end = self.parse_point()?;
+        if end.x() < start.x() || end.y() < start.y() {
+            return Err(CompileError::new(
+                CompileErrorType::InvalidRange,
+                location.line,
+                location.col,
+            ));
+        }
+        Ok((end.x() - start.x() + 1, end.y() - start.y() + 1))
+    }
+
     fn parse_shape(&mut self) -> Result<TokenType, CompileError> {
         if self.is_at_end() {
             let tok = self.tokens.last().unwrap();
@@ -229,8 +504,11 @@ impl Parser {
                 tok.location.line,
                 tok.location.col,
             ))
-        } else if self.next_matches_any(&ENTITY_SHAPES) {
-            Ok(self.consume()?.token_type)
+        } else if self.next_matches_any(&ENTITY_SHAPES)
+            || self.next_matches(TokenType::Identifier(String::new()))
+            || self.next_is_glyph_keyword()
+        {
+            Ok(self.consume()?.token_type.clone())
         } else {
             let token = self.consume()?;
             Err(CompileError::new(
@@ -243,7 +521,7 @@ impl Parser {
 
     fn accept(&mut self, token_type: TokenType) -> Result<&Token, CompileError> {
         let token = self.consume()?;
-        if token_type_matches(token, token_type) {
+        if token_type_matches(token, &token_type) {
             Ok(token)
         } else {
             Err(syntax_error(token_type, token))
@@ -258,6 +536,44 @@ impl Parser {
         }
     }
 
+    fn accept_identifier(&mut self) -> Result<String, CompileError> {
+        let token = self.consume()?;
+        match &token.token_type {
+            TokenType::Identifier(name) => Ok(name.clone()),
+            _ => Err(syntax_error(TokenType::Identifier(String::new()), token)),
+        }
+    }
+
+    /// Accept a glyph name, which can also be a keyword
+    fn accept_name(&mut self) -> Result<String, CompileError> {
+        let token = self.consume()?;
+        match &token.token_type {
+            TokenType::Identifier(name) => Ok(name.clone()),
+            token_type => match keyword(token_type) {
+                Some(keyword) => Ok(keyword.to_string()),
+                None => Err(syntax_error(TokenType::Identifier(String::new()), token)),
+            },
+        }
+    }
+
+    fn accept_string(&mut self) -> Result<String, CompileError> {
+        let token = self.consume()?;
+        match &token.token_type {
+            TokenType::StringLiteral(s) => Ok(s.clone()),
+            _ => Err(syntax_error(TokenType::StringLiteral(String::new()), token)),
+        }
+    }
+
+    /// Accept either a whole or a decimal number
+    fn accept_coordinate(&mut self) -> Result<f64, CompileError> {
+        let token = self.consume()?;
+        match token.token_type {
+            TokenType::Number(n) => Ok(n as f64),
+            TokenType::Decimal(n) => Ok(n),
+            _ => Err(syntax_error(TokenType::Decimal(0.0), token)),
+        }
+    }
+
     fn consume(&mut self) -> Result<&Token, CompileError> {
         if self.i >= self.tokens.len() {
             return Err(CompileError::new(
@@ -272,12 +588,12 @@ impl Parser {
     }
 
     fn next_matches_any(&self, token_types: &[TokenType]) -> bool {
-        token_types.iter().any(|&tt| self.next_matches(tt))
+        token_types.iter().any(|tt| self.next_matches(tt.clone()))
     }
 
     fn next_matches(&self, token_type: TokenType) -> bool {
         match self.peek() {
-            Some(token) => token_type_matches(token, token_type),
+            Some(token) => token_type_matches(token, &token_type),
             None => false,
         }
     }
@@ -295,16 +611,62 @@ impl Parser {
     }
 }
 
-fn token_type_matches(token: &Token, token_type: TokenType) -> bool {
-    std::mem::discriminant(&token.token_type) == std::mem::discriminant(&token_type)
+fn token_type_matches(token: &Token, token_type: &TokenType) -> bool {
+    std::mem::discriminant(&token.token_type) == std::mem::discriminant(token_type)
 }
 
+/// The error for an unexpected token. A name where a keyword was expected is
+/// most likely a misspelt keyword.
 fn syntax_error(expected: TokenType, token: &Token) -> CompileError {
-    let actual = token.token_type;
-    let err_type = CompileErrorType::SyntaxError(SyntaxError::new(expected, actual));
+    let err_type = match &token.token_type {
+        TokenType::Identifier(name) if !matches!(expected, TokenType::Identifier(_)) => {
+            CompileErrorType::UnrecognizedKeyword(name.clone())
+        }
+        actual => CompileErrorType::SyntaxError(SyntaxError::new(expected, actual.clone())),
+    };
     CompileError::new(err_type, token.location.line, token.location.col)
 }
 
+/// The shape drawn by an entity that can only be placed within cells
+fn cell_shape(token_type: TokenType, location: SourceLocation) -> Result<Shape, CompileError> {
+    let shape = match token_type {
+        TokenType::Square => Shape::Square,
+        TokenType::Stair => Shape::Stair,
+        TokenType::Ladder => Shape::Ladder,
+        TokenType::X => Shape::X,
+        TokenType::Pillar => Shape::Pillar,
+        TokenType::Statue => Shape::Statue,
+        TokenType::Chest => Shape::Chest,
+        TokenType::Table => Shape::Table,
+        TokenType::Bed => Shape::Bed,
+        TokenType::Altar => Shape::Altar,
+        TokenType::Well => Shape::Well,
+        TokenType::Fountain => Shape::Fountain,
+        TokenType::Trapdoor => Shape::Trapdoor,
+        TokenType::Brazier => Shape::Brazier,
+        TokenType::Barrel => Shape::Barrel,
+        TokenType::Door => Shape::Door,
+        TokenType::Rubble => Shape::Rubble,
+        TokenType::Trap => Shape::Trap,
+        _ => {
+            return Err(CompileError::new(
+                CompileErrorType::InvalidShape,
+                location.line,
+                location.col,
+            ))
+        }
+    };
+    Ok(shape)
+}
+
+fn invalid_orientation(location: SourceLocation) -> CompileError {
+    CompileError::new(
+        CompileErrorType::InvalidOrientation,
+        location.line,
+        location.col,
+    )
+}
+
 fn invalid_position(position_position: SourceLocation) -> CompileError {
     CompileError::new(
         CompileErrorType::InvalidPosition,
@@ -425,6 +787,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_glyph_library_entities_within_cell() {
+        let input = "grid 10, 10\nentity pillar within 1,1\nentity trapdoor within 2,2";
+        let ast = parse(input).expect("Bad parse");
+        assert!(matches!(entity_at_index(&ast, 1).shape, Shape::Pillar));
+        assert!(matches!(entity_at_index(&ast, 2).shape, Shape::Trapdoor));
+    }
+
+    #[test]
+    fn test_parse_glyph_library_entity_at_cell_is_invalid() {
+        let input = "grid 10, 10\nentity barrel at 5,7";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::InvalidPosition)),
+        }
+    }
+
     #[test]
     fn test_parse_rect_with_xor() {
         let input = "grid 10, 10\nrect at 1, 2 width 3 height 2\nxor rect at 4,2 width 2 height 2";
@@ -458,6 +837,249 @@ mod tests {
         assert_eq!(line.length(), 4);
     }
 
+    #[test]
+    fn test_parse_entity_within_cell_range() {
+        let input = "grid 10, 10\nentity square within 2,2 to 4,3";
+        let ast = parse(input).expect("Bad parse");
+        let entity = entity_at_index(&ast, 1);
+        assert!(matches!(entity.shape, Shape::Square));
+        assert_eq!(entity.point.x(), 2);
+        assert_eq!(entity.point.y(), 2);
+        assert_eq!(entity.width, 3);
+        assert_eq!(entity.height, 2);
+    }
+
+    #[test]
+    fn test_parse_entity_within_backwards_cell_range_is_invalid() {
+        let input = "grid 10, 10\nentity square within 4,3 to 2,2";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::InvalidRange)),
+        }
+    }
+
+    #[test]
+    fn test_parse_glyph() {
+        let input = "grid 10, 10\nglyph crate { path 0,0 1,0 1,1 0,1 close; path 0,0 0.5,0.5 }";
+        let ast = parse(input).expect("Bad parse");
+        let node = ast.nodes().nth(1).unwrap();
+        match node.node_type() {
+            AstNodeType::Glyph(glyph) => {
+                assert_eq!(glyph.name(), "crate");
+                assert_eq!(glyph.paths().len(), 2);
+                assert!(glyph.paths()[0].is_closed());
+                assert!(!glyph.paths()[1].is_closed());
+                assert_eq!(glyph.paths()[1].points(), &[(0.0, 0.0), (0.5, 0.5)]);
+            }
+            _ => panic!("Not a glyph node: {:?}", node.node_type()),
+        }
+    }
+
+    #[test]
+    fn test_parse_glyph_entity() {
+        let input =
+            "grid 10, 10\nglyph a { path 0,0 1,1 }\nglyph b { path 0,1 1,0 }\nentity b within 3,3";
+        let ast = parse(input).expect("Bad parse");
+        let entity = entity_at_index(&ast, 3);
+        assert!(matches!(entity.shape, Shape::Glyph(1)));
+    }
+
+    #[test]
+    fn test_parse_glyph_names_with_underscores_and_digits() {
+        let input =
+            "grid 10, 10\nglyph secret_door2 { path 0,0 1,1 }\nentity secret_door2 within 3,3";
+        let ast = parse(input).expect("Bad parse");
+        assert!(matches!(entity_at_index(&ast, 2).shape, Shape::Glyph(0)));
+    }
+
+    #[test]
+    fn test_parse_glyphs_named_after_keywords() {
+        let input = "grid 10, 10\nglyph table { path 0,0 1,1 }\nglyph rect { path 0,1 1,0 }\nentity table within 2,2\nentity rect within 3,3\nentity pillar within 4,4";
+        let ast = parse(input).expect("Bad parse");
+        match ast.nodes().nth(1).unwrap().node_type() {
+            AstNodeType::Glyph(glyph) => assert_eq!(glyph.name(), "table"),
+            node_type => panic!("Not a glyph node: {:?}", node_type),
+        }
+        assert!(matches!(entity_at_index(&ast, 3).shape, Shape::Glyph(0)));
+        assert!(matches!(entity_at_index(&ast, 4).shape, Shape::Glyph(1)));
+        assert!(matches!(entity_at_index(&ast, 5).shape, Shape::Pillar));
+    }
+
+    #[test]
+    fn test_parse_keyword_glyph_used_before_definition() {
+        let input = "grid 10, 10\nentity table within 1,1\nglyph table { path 0,0 1,1 }";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::UndefinedGlyph(_)));
+                assert_eq!((e.location.line, e.location.col), (2, 8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_unrecognized_keyword() {
+        for (input, col) in [("grid 10, 10\nbadkeyword", 1), ("grid 10, 10\nscale ft", 7)] {
+            match parse(input) {
+                Ok(_) => panic!("Should fail"),
+                Err(e) => {
+                    assert!(matches!(
+                        e.error_type,
+                        CompileErrorType::UnrecognizedKeyword(_)
+                    ));
+                    assert_eq!((e.location.line, e.location.col), (2, col));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_undefined_glyph_entity() {
+        let input = "grid 10, 10\nentity crate within 3,3";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::UndefinedGlyph(_)));
+                assert_eq!(e.location.line, 2);
+                assert_eq!(e.location.col, 8);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_glyph_point_outside_cell() {
+        let input = "grid 10, 10\nglyph crate { path 0,0 2,1 }";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::OutOfBounds)),
+        }
+    }
+
+    #[test]
+    fn test_parse_stamp_of_size_zero() {
+        let input = "grid 10, 10\nstamp \"throne.svg\" within 5,5 size 0";
+        match parse(input) {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::InvalidSize));
+                assert_eq!((e.location.line, e.location.col), (2, 31));
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_stamp() {
+        let input = "grid 10, 10\nstamp \"art/throne.svg\" within 5,5 size 2";
+        let ast = parse(input).expect("Bad parse");
+        let node = ast.nodes().nth(1).unwrap();
+        match node.node_type() {
+            AstNodeType::Stamp(stamp) => {
+                assert_eq!(stamp.path, "art/throne.svg");
+                assert_eq!(stamp.point, Point::new(5, 5));
+                assert_eq!(stamp.size, 2);
+            }
+            _ => panic!("Not a stamp node: {:?}", node.node_type()),
+        }
+    }
+
+    #[test]
+    fn test_parse_title_and_scale() {
+        let input = "grid 10, 10\ntitle \"Goblin Caves\"\nscale 2.5 m";
+        let ast = parse(input).expect("Bad parse");
+        let mut nodes = ast.nodes().skip(1);
+        match nodes.next().unwrap().node_type() {
+            AstNodeType::Title(title) => assert_eq!(title, "Goblin Caves"),
+            t => panic!("Not a title node: {:?}", t),
+        }
+        match nodes.next().unwrap().node_type() {
+            AstNodeType::Scale(scale) => {
+                assert_eq!(scale.amount, 2.5);
+                assert_eq!(scale.unit, "m");
+            }
+            t => panic!("Not a scale node: {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_parse_scale_without_unit() {
+        match parse("grid 10, 10\nscale 5\nrect at 1, 1 width 2 height 2") {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::SyntaxError(_))),
+        }
+    }
+
+    #[test]
+    fn test_parse_compass() {
+        let input = "grid 10, 10\ncompass at bottom left rotate 90";
+        let ast = parse(input).expect("Bad parse");
+        match ast.nodes().nth(1).unwrap().node_type() {
+            AstNodeType::Compass(compass) => {
+                assert_eq!(*compass, Compass::new(Corner::BottomLeft, 90.0));
+            }
+            t => panic!("Not a compass node: {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_parse_compass_outside() {
+        let ast = parse("grid 10, 10\ncompass at top left outside rotate 45").expect("Bad parse");
+        match ast.nodes().nth(1).unwrap().node_type() {
+            AstNodeType::Compass(compass) => {
+                assert_eq!(
+                    *compass,
+                    Compass::new(Corner::TopLeft, 45.0).with_outside(true)
+                );
+            }
+            t => panic!("Not a compass node: {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_parse_compass_without_rotation() {
+        let ast = parse("grid 10, 10\ncompass at top right").expect("Bad parse");
+        match ast.nodes().nth(1).unwrap().node_type() {
+            AstNodeType::Compass(compass) => {
+                assert_eq!(*compass, Compass::new(Corner::TopRight, 0.0));
+            }
+            t => panic!("Not a compass node: {:?}", t),
+        }
+    }
+
+    #[test]
+    fn test_parse_compass_in_invalid_corner() {
+        match parse("grid 10, 10\ncompass at left top") {
+            Ok(_) => panic!("Should fail"),
+            Err(e) => assert!(matches!(e.error_type, CompileErrorType::InvalidOrientation)),
+        }
+    }
+
+    #[test]
+    fn test_cell_shape_of_other_token_is_invalid() {
+        let location = SourceLocation { line: 2, col: 8 };
+        match cell_shape(TokenType::Comma, location) {
+            Ok(shape) => panic!("Should fail, got {:?}", shape),
+            Err(e) => {
+                assert!(matches!(e.error_type, CompileErrorType::InvalidShape));
+                assert_eq!((e.location.line, e.location.col), (2, 8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_comments_are_attached() {
+        let input = "# header\ngrid 4, 4 # size\n\n# room\nrect at 0, 0 width 1 height 1\n# end";
+        let ast = parse(input).expect("Bad parse");
+        let nodes: Vec<&AstNode> = ast.nodes().collect();
+        assert_eq!(nodes[0].leading(), [Trivia::Comment(" header".to_string())]);
+        assert_eq!(nodes[0].trailing_comment(), Some(" size"));
+        assert_eq!(
+            nodes[1].leading(),
+            [Trivia::BlankLine, Trivia::Comment(" room".to_string())]
+        );
+        assert_eq!(nodes[1].trailing_comment(), None);
+        assert_eq!(ast.trailing(), [Trivia::Comment(" end".to_string())]);
+    }
+
     fn rect_at_index(ast: &AbstractSyntaxTree, index: usize) -> &Rect {
         let mut nodes = ast.nodes();
         for _ in 0..index {
@@ -465,10 +1087,7 @@ mod tests {
         }
         let node = nodes.next().unwrap();
         match node.node_type() {
-            AstNodeType::Shape(shape_node) => match shape_node {
-                ShapeNode::Rect(rect) => rect,
-                _ => panic!("Not a rect node: {:?}", node.node_type()),
-            },
+            AstNodeType::Shape(ShapeNode::Rect(rect)) => rect,
             _ => panic!("Not a rect node: {:?}", node.node_type()),
         }
     }
@@ -480,10 +1099,7 @@ mod tests {
         }
         let node = nodes.next().unwrap();
         match node.node_type() {
-            AstNodeType::Shape(shape_node) => match shape_node {
-                ShapeNode::Line(line) => line,
-                _ => panic!("Not a line node: {:?}", node.node_type()),
-            },
+            AstNodeType::Shape(ShapeNode::Line(line)) => line,
             _ => panic!("Not a line node: {:?}", node.node_type()),
         }
     }
@@ -495,7 +1111,7 @@ mod tests {
         }
         let node = nodes.next().unwrap();
         match node.node_type() {
-            AstNodeType::Entity(e) => &e,
+            AstNodeType::Entity(e) => e,
             _ => panic!("Not an entity node: {:?}", node.node_type()),
         }
     }
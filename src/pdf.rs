@@ -0,0 +1,542 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::points::Point;
+use crate::render::Renderer;
+use crate::svg::{Colour, Style, TextAnchor};
+use std::fmt::Write;
+
+/// The name under which the font for all text is added to each page
+const FONT_NAME: &str = "F1";
+
+/// How far along the tangent the control points of a Bézier curve
+/// approximating a quarter circle are, as a fraction of the radius
+const CIRCLE_KAPPA: f64 = 0.552_284_749_831;
+
+/// A sequence of PDF drawing operators, such as a page or a form's content
+#[derive(Debug, Default)]
+pub struct PdfContent {
+    ops: String,
+}
+
+impl PdfContent {
+    pub fn new() -> PdfContent {
+        PdfContent::default()
+    }
+
+    pub fn save(&mut self) {
+        self.ops.push_str("q\n");
+    }
+
+    pub fn restore(&mut self) {
+        self.ops.push_str("Q\n");
+    }
+
+    /// Apply the transformation matrix `[a b c d e f]`
+    pub fn transform(&mut self, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) {
+        self.op(&[a, b, c, d, e, f], "cm");
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        self.op(&[x, y], "m");
+    }
+
+    pub fn line_to(&mut self, x: f64, y: f64) {
+        self.op(&[x, y], "l");
+    }
+
+    pub fn curve_to(&mut self, c1: (f64, f64), c2: (f64, f64), end: (f64, f64)) {
+        self.op(&[c1.0, c1.1, c2.0, c2.1, end.0, end.1], "c");
+    }
+
+    pub fn close_path(&mut self) {
+        self.ops.push_str("h\n");
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.op(&[x, y, width, height], "re");
+    }
+
+    /// Add a circle to the current path, as four Bézier curves
+    pub fn circle(&mut self, cx: f64, cy: f64, r: f64) {
+        let k = r * CIRCLE_KAPPA;
+        self.move_to(cx + r, cy);
+        self.curve_to((cx + r, cy + k), (cx + k, cy + r), (cx, cy + r));
+        self.curve_to((cx - k, cy + r), (cx - r, cy + k), (cx - r, cy));
+        self.curve_to((cx - r, cy - k), (cx - k, cy - r), (cx, cy - r));
+        self.curve_to((cx + k, cy - r), (cx + r, cy - k), (cx + r, cy));
+        self.close_path();
+    }
+
+    /// Restrict drawing to the current path
+    pub fn clip(&mut self) {
+        self.ops.push_str("W n\n");
+    }
+
+    pub fn stroke_colour(&mut self, colour: Colour) {
+        let (r, g, b) = rgb(colour);
+        self.op(&[r, g, b], "RG");
+    }
+
+    pub fn fill_colour(&mut self, colour: Colour) {
+        let (r, g, b) = rgb(colour);
+        self.op(&[r, g, b], "rg");
+    }
+
+    pub fn line_width(&mut self, width: f64) {
+        self.op(&[width], "w");
+    }
+
+    /// Draw lines as dashes, alternating the given lengths of line and gap
+    pub fn dash(&mut self, pattern: &[f64]) {
+        let lengths: Vec<String> = pattern.iter().map(|n| number(*n)).collect();
+        let _ = writeln!(self.ops, "[{}] 0 d", lengths.join(" "));
+    }
+
+    /// Paint the current path: stroking it, filling it, both or neither
+    pub fn paint(&mut self, stroke: bool, fill: bool, even_odd: bool) {
+        let op = match (stroke, fill, even_odd) {
+            (true, true, false) => "B",
+            (true, true, true) => "B*",
+            (true, false, _) => "S",
+            (false, true, false) => "f",
+            (false, true, true) => "f*",
+            (false, false, _) => "n",
+        };
+        self.ops.push_str(op);
+        self.ops.push('\n');
+    }
+
+    /// Draw a line of text with its baseline starting at `(x, y)`. With
+    /// `flipped`, the text is drawn the right way up in a coordinate system
+    /// whose y axis points down, as in SVG.
+    pub fn text(&mut self, x: f64, y: f64, size: f64, text: &str, flipped: bool) {
+        let d = if flipped { -1.0 } else { 1.0 };
+        let _ = writeln!(
+            self.ops,
+            "BT /{} {} Tf 1 0 0 {} {} {} Tm ({}) Tj ET",
+            FONT_NAME,
+            number(size),
+            number(d),
+            number(x),
+            number(y),
+            escape(text)
+        );
+    }
+
+    /// Add the operators from other content
+    pub fn append(&mut self, other: PdfContent) {
+        self.ops.push_str(&other.ops);
+    }
+
+    /// Draw the form added to the document under `name`
+    pub fn form(&mut self, name: &str) {
+        let _ = writeln!(self.ops, "/{} Do", name);
+    }
+
+    pub fn into_string(self) -> String {
+        self.ops
+    }
+
+    fn op(&mut self, operands: &[f64], operator: &str) {
+        for operand in operands {
+            self.ops.push_str(&number(*operand));
+            self.ops.push(' ');
+        }
+        self.ops.push_str(operator);
+        self.ops.push('\n');
+    }
+}
+
+/// Renders a drawing as a single-page PDF, in the same coordinates as
+/// `SvgBuilder`. Embedded SVG documents would have to be converted to PDF, so
+/// they are left out, and `compile_pdf` refuses maps that have any.
+pub struct PdfBuilder {
+    width: usize,
+    height: usize,
+    content: PdfContent,
+}
+
+impl Renderer for PdfBuilder {
+    fn new(width: usize, height: usize) -> Self {
+        PdfBuilder {
+            width,
+            height,
+            content: PdfContent::new(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn rect(mut self, point: Point, width: usize, height: usize, style: impl Into<Style>) -> Self {
+        let (x, y) = (point.x() as f64, point.y() as f64);
+        self.content.rect(x, y, width as f64, height as f64);
+        style.into().paint(&mut self.content, false);
+        self
+    }
+
+    fn path(mut self, points: Vec<Point>, style: impl Into<Style>) -> Self {
+        self.lines(&points, false);
+        style.into().paint(&mut self.content, false);
+        self
+    }
+
+    fn circle(mut self, x: usize, y: usize, radius: usize, style: impl Into<Style>) -> Self {
+        self.content.circle(x as f64, y as f64, radius as f64);
+        style.into().paint(&mut self.content, false);
+        self
+    }
+
+    fn polygon(mut self, points: Vec<Point>, style: impl Into<Style>) -> Self {
+        self.lines(&points, true);
+        style.into().paint(&mut self.content, false);
+        self
+    }
+
+    fn outline(mut self, loops: Vec<Vec<Point>>, style: impl Into<Style>) -> Self {
+        for points in loops.iter() {
+            self.lines(points, true);
+        }
+        style.into().paint(&mut self.content, true);
+        self
+    }
+
+    fn curve(
+        mut self,
+        start: (f64, f64),
+        curves: Vec<[(f64, f64); 3]>,
+        style: impl Into<Style>,
+    ) -> Self {
+        self.content.move_to(start.0, start.1);
+        for [c1, c2, end] in curves.iter() {
+            self.content.curve_to(*c1, *c2, *end);
+        }
+        style.into().paint(&mut self.content, false);
+        self
+    }
+
+    fn text(
+        mut self,
+        point: Point,
+        text: &str,
+        size: usize,
+        anchor: TextAnchor,
+        colour: Colour,
+    ) -> Self {
+        let size = size as f64;
+        // The standard fonts can't be measured without their metrics, so
+        // use the average width of a character
+        let width = text.chars().count() as f64 * size * 0.55;
+        let x = match anchor {
+            TextAnchor::Start => 0.0,
+            TextAnchor::Middle => -width / 2.0,
+            TextAnchor::End => -width,
+        } + point.x() as f64;
+        // Move the baseline down so that the text is centred on its point
+        let y = point.y() as f64 + size * 0.35;
+        self.content.fill_colour(colour);
+        self.content.text(x, y, size, text, true);
+        self
+    }
+
+    fn group(mut self, point: Point, inner: Self) -> Self {
+        self.content.save();
+        let (x, y) = (point.x() as f64, point.y() as f64);
+        self.content.transform(1.0, 0.0, 0.0, 1.0, x, y);
+        self.content.append(inner.content);
+        self.content.restore();
+        self
+    }
+}
+
+impl PdfBuilder {
+    /// The drawing operators, in the builder's coordinates
+    pub fn into_content(self) -> PdfContent {
+        self.content
+    }
+
+    /// Write a PDF with a single page, one point for each unit of the
+    /// drawing
+    pub fn build(self) -> Vec<u8> {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let mut doc = PdfDocument::new(width, height);
+        let mut page = PdfContent::new();
+        page.transform(1.0, 0.0, 0.0, -1.0, 0.0, height);
+        page.append(self.content);
+        doc.add_page(page);
+        doc.build()
+    }
+
+    fn lines(&mut self, points: &[Point], closed: bool) {
+        for (i, p) in points.iter().enumerate() {
+            let (x, y) = (p.x() as f64, p.y() as f64);
+            if i == 0 {
+                self.content.move_to(x, y);
+            } else {
+                self.content.line_to(x, y);
+            }
+        }
+        if closed {
+            self.content.close_path();
+        }
+    }
+}
+
+/// A PDF document of same-sized pages, which can share forms: drawings that
+/// are stored once and placed on any number of pages
+pub struct PdfDocument {
+    width: f64,
+    height: f64,
+    forms: Vec<(f64, f64, String)>,
+    pages: Vec<String>,
+}
+
+impl PdfDocument {
+    /// Start a document whose pages are `width` by `height` points
+    pub fn new(width: f64, height: f64) -> PdfDocument {
+        PdfDocument {
+            width,
+            height,
+            forms: Vec::new(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Add a form of the given size, returning the name it is drawn by
+    pub fn add_form(&mut self, width: f64, height: f64, content: PdfContent) -> String {
+        self.forms.push((width, height, content.into_string()));
+        format!("Fm{}", self.forms.len() - 1)
+    }
+
+    pub fn add_page(&mut self, content: PdfContent) {
+        self.pages.push(content.into_string());
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        // Objects are numbered: the catalog, the page tree, the font, the
+        // forms, then each page followed by its content
+        let font_id = 3;
+        let first_form_id = 4;
+        let first_page_id = first_form_id + self.forms.len();
+        let page_ids: Vec<usize> = (0..self.pages.len())
+            .map(|i| first_page_id + 2 * i)
+            .collect();
+
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        );
+
+        let resources = {
+            let forms: Vec<String> = (0..self.forms.len())
+                .map(|i| format!("/Fm{} {} 0 R", i, first_form_id + i))
+                .collect();
+            format!(
+                "<< /Font << /{} {} 0 R >> /XObject << {} >> >>",
+                FONT_NAME,
+                font_id,
+                forms.join(" ")
+            )
+        };
+
+        for (width, height, content) in self.forms.iter() {
+            let dict = format!(
+                "/Type /XObject /Subtype /Form /BBox [0 0 {} {}] /Resources << /Font << /{} {} 0 R >> >>",
+                number(*width),
+                number(*height),
+                FONT_NAME,
+                font_id
+            );
+            objects.push(stream(&dict, content));
+        }
+        for (i, content) in self.pages.iter().enumerate() {
+            let page = format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources {} /Contents {} 0 R >>",
+                number(self.width),
+                number(self.height),
+                resources,
+                page_ids[i] + 1
+            );
+            objects.push(page.into_bytes());
+            objects.push(stream("", content));
+        }
+
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n", objects.len() + 1).as_bytes());
+        pdf.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+fn stream(dict: &str, content: &str) -> Vec<u8> {
+    let bytes = latin1(content);
+    let dict = if dict.is_empty() {
+        format!("<< /Length {} >>", bytes.len())
+    } else {
+        format!("<< {} /Length {} >>", dict, bytes.len())
+    };
+    let mut object = format!("{}\nstream\n", dict).into_bytes();
+    object.extend_from_slice(&bytes);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+/// Encode text for the standard fonts, which only cover Latin-1
+fn latin1(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|ch| if (ch as u32) < 256 { ch as u8 } else { b'?' })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+fn rgb(colour: Colour) -> (f64, f64, f64) {
+    match colour {
+        Colour::Black => (0.0, 0.0, 0.0),
+        Colour::Rgb(r, g, b) => (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0),
+    }
+}
+
+/// Format a number as briefly as possible, as PDF doesn't allow exponents
+fn number(n: f64) -> String {
+    let s = format!("{:.3}", n);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(1.0), "1");
+        assert_eq!(number(0.5), "0.5");
+        assert_eq!(number(2.0 / 3.0), "0.667");
+        assert_eq!(number(-0.0001), "0");
+        assert_eq!(number(72.0), "72");
+    }
+
+    #[test]
+    fn test_content() {
+        let mut content = PdfContent::new();
+        content.stroke_colour(Colour::Rgb(255, 0, 0));
+        content.move_to(0.0, 0.0);
+        content.line_to(10.0, 5.5);
+        content.paint(true, false, false);
+        content.text(1.0, 2.0, 12.0, "A (b)", true);
+        assert_eq!(
+            content.into_string(),
+            "1 0 0 RG\n0 0 m\n10 5.5 l\nS\nBT /F1 12 Tf 1 0 0 -1 1 2 Tm (A \\(b\\)) Tj ET\n"
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let pdf = PdfBuilder::new(100, 50)
+            .rect(Point::new(10, 20), 30, 5, Colour::Black)
+            .polygon(
+                vec![Point::new(0, 0), Point::new(5, 0), Point::new(5, 5)],
+                Style::filled(Colour::Black),
+            )
+            .build();
+        let pdf = String::from_utf8(pdf).unwrap();
+        assert!(pdf.contains("/MediaBox [0 0 100 50]"));
+        assert!(pdf.contains(
+            "1 0 0 -1 0 50 cm\n10 20 30 5 re\n0 0 0 RG\n1 w\nS\n0 0 m\n5 0 l\n5 5 l\nh\n0 0 0 rg\nf\n"
+        ));
+    }
+
+    #[test]
+    fn test_builder_group() {
+        let inner = PdfBuilder::new(0, 0).circle(1, 2, 3, Colour::Black);
+        let content = PdfBuilder::new(100, 50)
+            .group(Point::new(10, 20), inner)
+            .into_content()
+            .into_string();
+        assert!(content.starts_with("q\n1 0 0 1 10 20 cm\n4 2 m\n"));
+        assert!(content.ends_with("S\nQ\n"));
+    }
+
+    #[test]
+    fn test_document_structure() {
+        let mut doc = PdfDocument::new(612.0, 792.0);
+        let form = doc.add_form(100.0, 100.0, PdfContent::new());
+        assert_eq!(form, "Fm0");
+        for _ in 0..2 {
+            let mut content = PdfContent::new();
+            content.form(&form);
+            doc.add_page(content);
+        }
+        let pdf = String::from_utf8(doc.build()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("/Kids [5 0 R 7 0 R]"));
+        assert!(pdf.contains("/XObject << /Fm0 4 0 R >>"));
+
+        // Every object should be where the cross-reference table says
+        let xref_start = pdf.find("xref\n").unwrap();
+        let offsets: Vec<usize> = pdf[xref_start..]
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 8);
+        for (i, offset) in offsets.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::pdf::{PdfBuilder, PdfContent, PdfDocument};
+use crate::render::Renderer;
+use crate::svg::Colour;
+
+/// Points per inch, the unit of PDF coordinates
+const INCH: f64 = 72.0;
+
+/// The unprintable border around each sheet
+const MARGIN: f64 = INCH / 2.0;
+
+/// How much of the map is repeated on neighbouring sheets, so that they can
+/// be overlapped and taped together
+const OVERLAP: f64 = INCH / 4.0;
+
+const CUT_MARK_LENGTH: f64 = 12.0;
+const CUT_MARK_GAP: f64 = 3.0;
+const LABEL_SIZE: f64 = 9.0;
+const GUIDE_COLOUR: Colour = Colour::Rgb(160, 160, 160);
+
+/// The paper sizes that maps can be printed on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Paper {
+    A4,
+    Letter,
+}
+
+impl Paper {
+    pub fn parse(name: &str) -> Option<Paper> {
+        match name.to_lowercase().as_str() {
+            "a4" => Some(Paper::A4),
+            "letter" => Some(Paper::Letter),
+            _ => None,
+        }
+    }
+
+    /// The width and height of a portrait sheet, in points
+    fn size(self) -> (f64, f64) {
+        match self {
+            Paper::A4 => (595.276, 841.89),
+            Paper::Letter => (612.0, 792.0),
+        }
+    }
+
+    /// The printed size of a grid cell, in points: an inch, or 25mm on
+    /// metric paper
+    pub fn cell_size(self) -> f64 {
+        match self {
+            Paper::A4 => 25.0 / 25.4 * INCH,
+            Paper::Letter => INCH,
+        }
+    }
+}
+
+/// Print a drawing across as many sheets of paper as it takes, at the
+/// paper's cell size, where `dim` is the size of a cell in the drawing. The
+/// sheets are turned whichever way needs fewer of them.
+pub fn print_tiled(drawing: PdfBuilder, dim: usize, paper: Paper) -> Vec<u8> {
+    let scale = paper.cell_size() / dim as f64;
+    let width = drawing.width() as f64 * scale;
+    let height = drawing.height() as f64 * scale;
+
+    let (short, long) = paper.size();
+    let portrait = Tiling::new(width, height, short, long);
+    let landscape = Tiling::new(width, height, long, short);
+    let tiling = if landscape.pages() < portrait.pages() {
+        landscape
+    } else {
+        portrait
+    };
+
+    // The drawing is stored once, the right way up in PDF's coordinates,
+    // and placed on every page
+    let mut doc = PdfDocument::new(tiling.page_width, tiling.page_height);
+    let mut content = PdfContent::new();
+    content.transform(scale, 0.0, 0.0, -scale, 0.0, height);
+    content.append(drawing.into_content());
+    let form = doc.add_form(width, height, content);
+
+    for row in 0..tiling.rows {
+        for column in 0..tiling.columns {
+            let page = tiling.page(&form, width, height, row, column);
+            doc.add_page(page);
+        }
+    }
+    doc.build()
+}
+
+/// How a drawing is divided between pages
+struct Tiling {
+    page_width: f64,
+    page_height: f64,
+    columns: usize,
+    rows: usize,
+}
+
+impl Tiling {
+    fn new(width: f64, height: f64, page_width: f64, page_height: f64) -> Tiling {
+        Tiling {
+            page_width,
+            page_height,
+            columns: tiles(width, page_width - 2.0 * MARGIN),
+            rows: tiles(height, page_height - 2.0 * MARGIN),
+        }
+    }
+
+    fn pages(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// Lay out the part of a `width` by `height` drawing that goes on one
+    /// page
+    fn page(&self, form: &str, width: f64, height: f64, row: usize, column: usize) -> PdfContent {
+        let area_width = self.page_width - 2.0 * MARGIN;
+        let area_height = self.page_height - 2.0 * MARGIN;
+        // Where this page's part of the drawing starts, from its top left
+        let x0 = column as f64 * (area_width - OVERLAP);
+        let y0 = row as f64 * (area_height - OVERLAP);
+        let tile_width = area_width.min(width - x0);
+        let tile_height = area_height.min(height - y0);
+        let left = MARGIN;
+        let top = self.page_height - MARGIN;
+        let (right, bottom) = (left + tile_width, top - tile_height);
+
+        let mut content = PdfContent::new();
+        content.save();
+        content.rect(left, bottom, tile_width, tile_height);
+        content.clip();
+        content.transform(1.0, 0.0, 0.0, 1.0, left - x0, top - height + y0);
+        content.form(form);
+        content.restore();
+
+        // Mark where the previous pages overlap this one
+        if column > 0 || row > 0 {
+            content.save();
+            content.stroke_colour(GUIDE_COLOUR);
+            content.line_width(0.5);
+            content.dash(&[3.0, 3.0]);
+            if column > 0 {
+                content.move_to(left + OVERLAP, bottom);
+                content.line_to(left + OVERLAP, top);
+            }
+            if row > 0 {
+                content.move_to(left, top - OVERLAP);
+                content.line_to(right, top - OVERLAP);
+            }
+            content.paint(true, false, false);
+            content.restore();
+        }
+
+        // Cut marks, just outside each corner of the printed area
+        content.save();
+        content.stroke_colour(Colour::Black);
+        content.line_width(0.5);
+        for (x, y, dx, dy) in [
+            (left, top, -1.0, 1.0),
+            (right, top, 1.0, 1.0),
+            (left, bottom, -1.0, -1.0),
+            (right, bottom, 1.0, -1.0),
+        ] {
+            content.move_to(x + dx * CUT_MARK_GAP, y);
+            content.line_to(x + dx * (CUT_MARK_GAP + CUT_MARK_LENGTH), y);
+            content.move_to(x, y + dy * CUT_MARK_GAP);
+            content.line_to(x, y + dy * (CUT_MARK_GAP + CUT_MARK_LENGTH));
+        }
+        content.paint(true, false, false);
+        content.restore();
+
+        let label = format!(
+            "Page {} of {}: row {} of {}, column {} of {}",
+            row * self.columns + column + 1,
+            self.pages(),
+            row + 1,
+            self.rows,
+            column + 1,
+            self.columns
+        );
+        content.fill_colour(Colour::Black);
+        content.text(left, MARGIN / 2.0, LABEL_SIZE, &label, false);
+        content
+    }
+}
+
+/// The number of overlapping tiles of size `tile` needed to cover `length`
+fn tiles(length: f64, tile: f64) -> usize {
+    if length <= tile {
+        1
+    } else {
+        ((length - OVERLAP) / (tile - OVERLAP)).ceil() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paper() {
+        assert_eq!(Paper::parse("a4"), Some(Paper::A4));
+        assert_eq!(Paper::parse("Letter"), Some(Paper::Letter));
+        assert_eq!(Paper::parse("foolscap"), None);
+    }
+
+    #[test]
+    fn test_tiles() {
+        assert_eq!(tiles(100.0, 540.0), 1);
+        assert_eq!(tiles(540.0, 540.0), 1);
+        assert_eq!(tiles(541.0, 540.0), 2);
+        // Each extra page adds its width less the overlap
+        assert_eq!(tiles(540.0 + 522.0, 540.0), 2);
+        assert_eq!(tiles(540.0 + 523.0, 540.0), 3);
+    }
+
+    #[test]
+    fn test_print_at_one_inch_per_cell() {
+        // A 40 by 30 grid is 40 by 30 inches on letter paper, which takes 6
+        // by 4 pages in portrait, rather than 5 by 5 in landscape
+        let drawing = PdfBuilder::new(400, 300);
+        let pdf = String::from_utf8(print_tiled(drawing, 10, Paper::Letter)).unwrap();
+        assert!(pdf.contains("/Count 24"));
+        assert!(pdf.contains("/MediaBox [0 0 612 792]"));
+        assert!(pdf.contains("/BBox [0 0 2880 2160]"));
+        assert!(pdf.contains("(Page 24 of 24: row 4 of 4, column 6 of 6)"));
+    }
+
+    #[test]
+    fn test_small_map_fits_on_one_page() {
+        let drawing = PdfBuilder::new(50, 50);
+        let pdf = String::from_utf8(print_tiled(drawing, 10, Paper::A4)).unwrap();
+        assert!(pdf.contains("/Count 1"));
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+pub use crate::points::Point;
+pub use crate::stamps::Stamp;
+pub use crate::svg::{Colour, Style, TextAnchor};
+
+/// A backend that maps can be drawn with. Drawings are measured in pixels,
+/// with the origin at the top-left and the y axis pointing down.
+///
+/// Renderers are built up in the same way as `SvgBuilder`, by consuming the
+/// renderer and returning it with the new shape drawn on top.
+pub trait Renderer: Sized {
+    /// Start an empty drawing of the given size
+    fn new(width: usize, height: usize) -> Self;
+
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    fn rect(self, point: Point, width: usize, height: usize, style: impl Into<Style>) -> Self;
+
+    /// Draw straight lines through the points
+    fn path(self, points: Vec<Point>, style: impl Into<Style>) -> Self;
+
+    fn circle(self, x: usize, y: usize, radius: usize, style: impl Into<Style>) -> Self;
+
+    /// Draw a closed shape through the points
+    fn polygon(self, points: Vec<Point>, style: impl Into<Style>) -> Self;
+
+    /// Draw a shape bounded by several loops, filled with the even-odd rule
+    /// so that inner loops make holes
+    fn outline(self, loops: Vec<Vec<Point>>, style: impl Into<Style>) -> Self;
+
+    /// Draw cubic Bézier curves, each given by its two control points and
+    /// its end point, starting from `start`
+    fn curve(
+        self,
+        start: (f64, f64),
+        curves: Vec<[(f64, f64); 3]>,
+        style: impl Into<Style>,
+    ) -> Self;
+
+    /// Draw text, centred vertically on the point
+    fn text(
+        self,
+        point: Point,
+        text: &str,
+        size: usize,
+        anchor: TextAnchor,
+        colour: Colour,
+    ) -> Self;
+
+    /// Draw another drawing with its origin moved to the point
+    fn group(self, point: Point, inner: Self) -> Self;
+
+    /// Draw a stamp, scaled to fill a box. Renderers that can't draw SVGs
+    /// leave them out.
+    fn embed(self, _point: Point, _width: usize, _height: usize, _stamp: &Stamp) -> Self {
+        self
+    }
+
+    /// Fill the whole drawing with a colour
+    fn background(self, colour: Colour) -> Self {
+        let (width, height) = (self.width(), self.height());
+        self.rect(Point::new(0, 0), width, height, Style::filled(colour))
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::compass::Compass;
+use crate::entities::{Entity, EntityPosition};
+use crate::glyphs::Glyph;
+use crate::map::{Map, MapScale};
+use crate::points::Point;
+use crate::shapes::Shape;
+use crate::stamps::Stamp;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The serialized form of a map. Walls are listed as pairs of neighbouring
+/// grid points, the top or left point first, sorted from the top-left, so
+/// that the same map always serializes in the same way.
+#[derive(Serialize, Deserialize)]
+struct MapData {
+    width: usize,
+    height: usize,
+    walls: Vec<[Point; 2]>,
+    entities: Vec<Entity>,
+    #[serde(default)]
+    glyphs: Vec<Glyph>,
+    #[serde(default)]
+    stamps: Vec<Stamp>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    scale: Option<MapScale>,
+    #[serde(default)]
+    compass: Option<Compass>,
+}
+
+impl Serialize for Map {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut walls: Vec<[Point; 2]> = self
+            .edges()
+            .into_iter()
+            .map(|(p1, p2)| {
+                if (p1.y(), p1.x()) <= (p2.y(), p2.x()) {
+                    [p1, p2]
+                } else {
+                    [p2, p1]
+                }
+            })
+            .collect();
+        walls.sort_by_key(|[p1, p2]| (p1.y(), p1.x(), p2.y(), p2.x()));
+
+        let data = MapData {
+            width: self.width(),
+            height: self.height(),
+            walls,
+            entities: self.entities().clone(),
+            glyphs: (0..).map_while(|i| self.glyph(i).cloned()).collect(),
+            stamps: (0..).map_while(|i| self.stamp(i).cloned()).collect(),
+            title: self.title().map(|title| title.to_string()),
+            scale: self.scale().cloned(),
+            compass: self.compass().copied(),
+        };
+        data.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Map {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Map, D::Error> {
+        let data = MapData::deserialize(deserializer)?;
+        data.into_map().map_err(D::Error::custom)
+    }
+}
+
+impl MapData {
+    /// Rebuild the map, checking that everything in it is on the grid
+    fn into_map(self) -> Result<Map, String> {
+        let mut map = Map::new(self.width, self.height);
+        for [p1, p2] in self.walls {
+            if !map.point_exists(p1) || !map.point_exists(p2) || p1.taxicab_distance(&p2) != 1 {
+                return Err(format!(
+                    "Invalid wall from {}, {} to {}, {}",
+                    p1.x(),
+                    p1.y(),
+                    p2.x(),
+                    p2.y()
+                ));
+            }
+            map.connect(p1, p2);
+        }
+
+        for entity in &self.entities {
+            let p = entity.point();
+            let on_grid = |x: Option<usize>, y: Option<usize>| match (x, y) {
+                (Some(x), Some(y)) => map.point_exists(Point::new(x, y)),
+                _ => false,
+            };
+            let fits = match (entity.position(), entity.shape()) {
+                (EntityPosition::Within, _) => {
+                    entity.width() > 0
+                        && entity.height() > 0
+                        && on_grid(
+                            p.x().checked_add(entity.width()),
+                            p.y().checked_add(entity.height()),
+                        )
+                }
+                // The whole circle has to be on the grid, not just its centre
+                (EntityPosition::At, Shape::Circle(r)) => {
+                    on_grid(p.x().checked_sub(r), p.y().checked_sub(r))
+                        && on_grid(p.x().checked_add(r), p.y().checked_add(r))
+                }
+                // Only circles can be drawn at a grid point
+                (EntityPosition::At, _) => false,
+            };
+            let defined = match entity.shape() {
+                Shape::Glyph(i) => i < self.glyphs.len(),
+                Shape::Stamp(i) => i < self.stamps.len(),
+                _ => true,
+            };
+            if !fits || !defined {
+                return Err(format!("Invalid entity at {}, {}", p.x(), p.y()));
+            }
+        }
+
+        for glyph in self.glyphs {
+            map.add_glyph(glyph);
+        }
+        for stamp in self.stamps {
+            map.add_stamp(stamp);
+        }
+        for entity in self.entities {
+            map.add_entity(entity);
+        }
+        if let Some(title) = self.title {
+            map.set_title(title);
+        }
+        if let Some(scale) = self.scale {
+            map.set_scale(scale);
+        }
+        if let Some(compass) = self.compass {
+            map.set_compass(compass);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compass::Corner;
+
+    fn sample_map() -> Map {
+        let mut map = Map::new(2, 1);
+        map.connect(Point::new(1, 0), Point::new(0, 0));
+        map.connect(Point::new(0, 0), Point::new(0, 1));
+        map.add_entity(Entity::spanning(Shape::Table, Point::new(0, 0), 2, 1));
+        map.set_title("Hall".to_string());
+        map.set_compass(Compass::new(Corner::TopRight, 0.0));
+        map
+    }
+
+    #[test]
+    fn test_schema() {
+        let json = serde_json::to_value(sample_map()).unwrap();
+        assert_eq!(
+            json["walls"],
+            serde_json::json!([
+                [{ "x": 0, "y": 0 }, { "x": 1, "y": 0 }],
+                [{ "x": 0, "y": 0 }, { "x": 0, "y": 1 }],
+            ])
+        );
+        assert_eq!(json["entities"][0]["shape"], "table");
+        assert_eq!(json["entities"][0]["position"], "within");
+        assert_eq!(json["compass"]["corner"], "top_right");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = serde_json::to_string(&sample_map()).unwrap();
+        let map: Map = serde_json::from_str(&json).unwrap();
+        assert!(map.are_connected(Point::new(0, 0), Point::new(1, 0)));
+        assert_eq!(map.edges().len(), 2);
+        assert_eq!(map.entities()[0].width(), 2);
+        assert_eq!(map.title(), Some("Hall"));
+        assert_eq!(serde_json::to_string(&map).unwrap(), json);
+    }
+
+    #[test]
+    fn test_invalid_walls() {
+        let json = r#"{"width": 1, "height": 1, "walls": [[{"x": 0, "y": 0}, {"x": 1, "y": 1}]], "entities": []}"#;
+        assert!(serde_json::from_str::<Map>(json).is_err());
+        let json = r#"{"width": 1, "height": 1, "walls": [[{"x": 0, "y": 0}, {"x": 2, "y": 0}]], "entities": []}"#;
+        assert!(serde_json::from_str::<Map>(json).is_err());
+    }
+
+    fn entity_json(entity: &str) -> String {
+        format!(
+            r#"{{"width": 2, "height": 2, "walls": [], "entities": [{}]}}"#,
+            entity
+        )
+    }
+
+    #[test]
+    fn test_invalid_entities() {
+        let too_wide = r#"{"shape": "table", "point": {"x": 1, "y": 0}, "position": "within", "width": 18446744073709551615, "height": 1}"#;
+        assert!(serde_json::from_str::<Map>(&entity_json(too_wide)).is_err());
+        let empty = r#"{"shape": "table", "point": {"x": 0, "y": 0}, "position": "within", "width": 0, "height": 1}"#;
+        assert!(serde_json::from_str::<Map>(&entity_json(empty)).is_err());
+        let big_circle = r#"{"shape": {"circle": 2}, "point": {"x": 1, "y": 1}, "position": "at", "width": 1, "height": 1}"#;
+        assert!(serde_json::from_str::<Map>(&entity_json(big_circle)).is_err());
+        let circle = r#"{"shape": {"circle": 1}, "point": {"x": 1, "y": 1}, "position": "at", "width": 1, "height": 1}"#;
+        assert!(serde_json::from_str::<Map>(&entity_json(circle)).is_ok());
+        let square = r#"{"shape": "square", "point": {"x": 1, "y": 1}, "position": "at", "width": 1, "height": 1}"#;
+        assert!(serde_json::from_str::<Map>(&entity_json(square)).is_err());
+    }
+}
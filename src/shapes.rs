@@ -11,6 +11,7 @@
 use super::points::Point;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     point: Point,
     width: usize,
@@ -46,12 +47,15 @@ impl Rect {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ShapeBoolean {
     Or,
     Xor,
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
     orientation: LineOrientation,
     start: Point,
@@ -92,6 +96,8 @@ impl Line {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum LineOrientation {
     Left,
     Right,
@@ -100,10 +106,117 @@ pub enum LineOrientation {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Shape {
     Circle(usize),
     Square,
     Stair,
     Ladder,
     X,
+    Pillar,
+    Statue,
+    Chest,
+    Table,
+    Bed,
+    Altar,
+    Well,
+    Fountain,
+    Trapdoor,
+    Brazier,
+    Barrel,
+    Door,
+    Rubble,
+    Trap,
+    /// A glyph defined in the map file, identified by the order in which it
+    /// was defined
+    Glyph(usize),
+    /// An external SVG drawing, identified by the order in which it was
+    /// loaded
+    Stamp(usize),
+}
+
+impl Shape {
+    /// The letter that stands for a built-in shape in text drawings of a
+    /// map. Glyphs and stamps are lettered by their names instead.
+    pub fn letter(&self) -> Option<char> {
+        let letter = match self {
+            Shape::Circle(_) => 'C',
+            Shape::Square => 'Q',
+            Shape::Stair => 'S',
+            Shape::Ladder => 'L',
+            Shape::X => 'X',
+            Shape::Pillar => 'P',
+            Shape::Statue => 'U',
+            Shape::Chest => 'H',
+            Shape::Table => 'T',
+            Shape::Bed => 'B',
+            Shape::Altar => 'A',
+            Shape::Well => 'W',
+            Shape::Fountain => 'F',
+            Shape::Trapdoor => 'O',
+            Shape::Brazier => 'Z',
+            Shape::Barrel => 'R',
+            Shape::Door => 'D',
+            Shape::Rubble => 'K',
+            Shape::Trap => 'V',
+            Shape::Glyph(_) | Shape::Stamp(_) => return None,
+        };
+        Some(letter)
+    }
+
+    /// The built-in shape a letter in a text drawing stands for, in either
+    /// case
+    pub fn from_letter(letter: char) -> Option<Shape> {
+        let shape = match letter.to_ascii_uppercase() {
+            'C' => Shape::Circle(0),
+            'Q' => Shape::Square,
+            'S' => Shape::Stair,
+            'L' => Shape::Ladder,
+            'X' => Shape::X,
+            'P' => Shape::Pillar,
+            'U' => Shape::Statue,
+            'H' => Shape::Chest,
+            'T' => Shape::Table,
+            'B' => Shape::Bed,
+            'A' => Shape::Altar,
+            'W' => Shape::Well,
+            'F' => Shape::Fountain,
+            'O' => Shape::Trapdoor,
+            'Z' => Shape::Brazier,
+            'R' => Shape::Barrel,
+            'D' => Shape::Door,
+            'K' => Shape::Rubble,
+            'V' => Shape::Trap,
+            _ => return None,
+        };
+        Some(shape)
+    }
+
+    /// The keyword for a built-in shape in a map file
+    pub fn keyword(&self) -> Option<&'static str> {
+        let keyword = match self {
+            Shape::Circle(_) => "circle",
+            Shape::Square => "square",
+            Shape::Stair => "stair",
+            Shape::Ladder => "ladder",
+            Shape::X => "x",
+            Shape::Pillar => "pillar",
+            Shape::Statue => "statue",
+            Shape::Chest => "chest",
+            Shape::Table => "table",
+            Shape::Bed => "bed",
+            Shape::Altar => "altar",
+            Shape::Well => "well",
+            Shape::Fountain => "fountain",
+            Shape::Trapdoor => "trapdoor",
+            Shape::Brazier => "brazier",
+            Shape::Barrel => "barrel",
+            Shape::Door => "door",
+            Shape::Rubble => "rubble",
+            Shape::Trap => "trap",
+            Shape::Glyph(_) | Shape::Stamp(_) => return None,
+        };
+        Some(keyword)
+    }
 }
@@ -0,0 +1,184 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::points::Point;
+use crate::random::Random;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// How many overlapping strokes each line is drawn with
+const STROKES: usize = 2;
+
+/// How far along the tangent the control points of a Bézier curve that
+/// approximates a quarter circle are, as a fraction of the radius
+const KAPPA: f64 = 0.5523;
+
+/// A continuous pen stroke made of cubic Bézier curves. Each curve is given
+/// as its two control points followed by its end point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub start: (f64, f64),
+    pub curves: Vec<[(f64, f64); 3]>,
+}
+
+/// Turn the straight lines joining `points` into wobbly, hand-drawn strokes.
+/// Every line is drawn more than once, with each corner and each curve
+/// displaced by up to `roughness` pixels.
+pub fn sketch(points: &[Point], closed: bool, roughness: f64, random: &mut Random) -> Vec<Stroke> {
+    let mut points: Vec<(f64, f64)> = points
+        .iter()
+        .map(|p| (p.x() as f64, p.y() as f64))
+        .collect();
+    if closed && !points.is_empty() {
+        points.push(points[0]);
+    }
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut strokes = Vec::new();
+    for _ in 0..STROKES {
+        // The ends of a closed stroke are displaced separately, so that it
+        // doesn't quite meet itself, as when drawn by hand
+        let corners: Vec<(f64, f64)> = points
+            .iter()
+            .map(|(x, y)| (x + random.jitter(roughness), y + random.jitter(roughness)))
+            .collect();
+        let curves = corners
+            .windows(2)
+            .map(|pair| {
+                let (start, end) = (pair[0], pair[1]);
+                let length = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+                // Short lines wobble less, so that they stay recognisable
+                let amount = roughness.min(length / 8.0);
+                let c1 = along(start, end, 0.25 + random.jitter(0.1));
+                let c2 = along(start, end, 0.75 + random.jitter(0.1));
+                [
+                    (c1.0 + random.jitter(amount), c1.1 + random.jitter(amount)),
+                    (c2.0 + random.jitter(amount), c2.1 + random.jitter(amount)),
+                    end,
+                ]
+            })
+            .collect();
+        strokes.push(Stroke {
+            start: corners[0],
+            curves,
+        });
+    }
+    strokes
+}
+
+/// Turn a circle into wobbly, hand-drawn strokes. Each stroke starts at a
+/// different place and is a little out of round, with its radius displaced
+/// by up to `roughness` pixels at each quarter turn.
+pub fn sketch_circle(
+    center: (f64, f64),
+    radius: f64,
+    roughness: f64,
+    random: &mut Random,
+) -> Vec<Stroke> {
+    // Small circles wobble less, so that they stay round
+    let amount = roughness.min(radius / 4.0);
+    (0..STROKES)
+        .map(|_| {
+            let start = random.jitter(PI);
+            // The ends are displaced separately, so the stroke doesn't quite
+            // meet itself
+            let quarters: Vec<(f64, f64)> = (0..=4)
+                .map(|k| (start + k as f64 * FRAC_PI_2, radius + random.jitter(amount)))
+                .collect();
+            let point =
+                |(angle, r): (f64, f64)| (center.0 + r * angle.cos(), center.1 + r * angle.sin());
+            let curves = quarters
+                .windows(2)
+                .map(|pair| {
+                    let ((a0, r0), (a1, r1)) = (pair[0], pair[1]);
+                    let (p0, p1) = (point(pair[0]), point(pair[1]));
+                    [
+                        (p0.0 - KAPPA * r0 * a0.sin(), p0.1 + KAPPA * r0 * a0.cos()),
+                        (p1.0 + KAPPA * r1 * a1.sin(), p1.1 - KAPPA * r1 * a1.cos()),
+                        p1,
+                    ]
+                })
+                .collect();
+            Stroke {
+                start: point(quarters[0]),
+                curves,
+            }
+        })
+        .collect()
+}
+
+fn along(start: (f64, f64), end: (f64, f64), t: f64) -> (f64, f64) {
+    (
+        start.0 + (end.0 - start.0) * t,
+        start.1 + (end.1 - start.1) * t,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_to(p: (f64, f64), x: usize, y: usize, roughness: f64) -> bool {
+        (p.0 - x as f64).abs() <= roughness && (p.1 - y as f64).abs() <= roughness
+    }
+
+    #[test]
+    fn test_sketch_line() {
+        let points = [Point::new(0, 0), Point::new(10, 0), Point::new(10, 10)];
+        let strokes = sketch(&points, false, 1.0, &mut Random::new(3));
+        assert_eq!(strokes.len(), STROKES);
+        for stroke in strokes.iter() {
+            assert!(close_to(stroke.start, 0, 0, 1.0));
+            assert_eq!(stroke.curves.len(), 2);
+            assert!(close_to(stroke.curves[0][2], 10, 0, 1.0));
+            assert!(close_to(stroke.curves[1][2], 10, 10, 1.0));
+        }
+        assert_ne!(strokes[0], strokes[1]);
+    }
+
+    #[test]
+    fn test_sketch_polygon_returns_to_start() {
+        let points = [Point::new(0, 0), Point::new(10, 0), Point::new(10, 10)];
+        let strokes = sketch(&points, true, 1.0, &mut Random::new(3));
+        for stroke in strokes.iter() {
+            assert_eq!(stroke.curves.len(), 3);
+            assert!(close_to(stroke.curves[2][2], 0, 0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_sketch_is_deterministic() {
+        let points = [Point::new(0, 0), Point::new(10, 0)];
+        let strokes1 = sketch(&points, false, 2.0, &mut Random::new(9));
+        let strokes2 = sketch(&points, false, 2.0, &mut Random::new(9));
+        assert_eq!(strokes1, strokes2);
+    }
+
+    #[test]
+    fn test_sketch_circle() {
+        let strokes = sketch_circle((20.0, 20.0), 8.0, 1.0, &mut Random::new(5));
+        assert_eq!(strokes.len(), STROKES);
+        for stroke in strokes.iter() {
+            assert_eq!(stroke.curves.len(), 4);
+            let ends = std::iter::once(stroke.start).chain(stroke.curves.iter().map(|c| c[2]));
+            for (x, y) in ends {
+                let distance = ((x - 20.0).powi(2) + (y - 20.0).powi(2)).sqrt();
+                assert!((7.0..=9.0).contains(&distance));
+            }
+        }
+        assert_ne!(strokes[0], strokes[1]);
+    }
+
+    #[test]
+    fn test_sketch_single_point() {
+        assert!(sketch(&[Point::new(1, 1)], false, 1.0, &mut Random::new(0)).is_empty());
+    }
+}
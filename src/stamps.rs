@@ -0,0 +1,263 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+/// An external SVG drawing that is inlined into the map as an entity
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stamp {
+    path: String,
+    view_box: String,
+    /// The attributes of the `svg` element, other than its position and size
+    attributes: Vec<(String, String)>,
+    content: String,
+}
+
+impl Stamp {
+    /// Extract the contents and coordinate system of an SVG document, or
+    /// `None` if it isn't one
+    pub fn parse(path: &str, svg: &str) -> Option<Stamp> {
+        let start = find_svg_start(svg)?;
+        let tag_end = find_tag_end(svg, start)?;
+        let self_closing = svg[..tag_end].ends_with('/');
+        let attributes_end = if self_closing { tag_end - 1 } else { tag_end };
+        let attributes = parse_attributes(&svg[start + "<svg".len()..attributes_end])?;
+
+        let content = if self_closing {
+            String::new()
+        } else {
+            let close = svg.rfind("</svg>")?;
+            if close < tag_end {
+                return None;
+            }
+            svg[tag_end + 1..close].trim().to_string()
+        };
+
+        let view_box = match attribute(&attributes, "viewBox") {
+            Some(v) => v.to_string(),
+            None => {
+                let width = parse_length(attribute(&attributes, "width")?)?;
+                let height = parse_length(attribute(&attributes, "height")?)?;
+                format!("0 0 {} {}", width, height)
+            }
+        };
+
+        let attributes = attributes
+            .into_iter()
+            .filter(|(name, _)| !["x", "y", "width", "height", "viewBox"].contains(&name.as_str()))
+            .collect();
+
+        Some(Stamp {
+            path: path.to_string(),
+            view_box,
+            attributes,
+            content,
+        })
+    }
+
+    /// The file name of the stamp, as written in the map file
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn view_box(&self) -> &str {
+        &self.view_box
+    }
+
+    /// The attributes of the stamp's `svg` element, such as the namespaces
+    /// it declares, to be kept where it is drawn
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// The contents, with the ids they define, and the references to them,
+    /// prefixed so that they don't collide with those of other stamps
+    pub fn content_with_id_prefix(&self, prefix: &str) -> String {
+        let mut content = String::new();
+        let mut ids = Vec::new();
+        let mut rest = self.content.as_str();
+        while let Some(i) = rest.find("id=") {
+            let (before, after) = rest.split_at(i + "id=".len());
+            content.push_str(before);
+            rest = after;
+            let is_id = before[..i].ends_with(char::is_whitespace);
+            let quote = match rest.chars().next() {
+                Some(q @ ('"' | '\'')) if is_id => q,
+                _ => continue,
+            };
+            let Some(end) = rest[1..].find(quote) else {
+                continue;
+            };
+            let id = &rest[1..end + 1];
+            content.push_str(&format!("{}{}{}", quote, prefix, id));
+            ids.push(id);
+            rest = &rest[end + 1..];
+        }
+        content.push_str(rest);
+
+        for id in ids {
+            for (open, close) in [("\"#", "\""), ("'#", "'"), ("(#", ")")] {
+                content = content.replace(
+                    &format!("{}{}{}", open, id, close),
+                    &format!("{}{}{}{}", open, prefix, id, close),
+                );
+            }
+        }
+        content
+    }
+}
+
+fn find_svg_start(svg: &str) -> Option<usize> {
+    let mut i = 0;
+    while let Some(offset) = svg[i..].find("<svg") {
+        let start = i + offset;
+        let next = svg[start + "<svg".len()..].chars().next()?;
+        if next.is_whitespace() || next == '>' || next == '/' {
+            return Some(start);
+        }
+        i = start + 1;
+    }
+    None
+}
+
+/// Find the `>` that ends the tag beginning at `start`, skipping over any
+/// quoted attribute values
+fn find_tag_end(svg: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, ch) in svg[start..].char_indices() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => (),
+            None if ch == '"' || ch == '\'' => quote = Some(ch),
+            None if ch == '>' => return Some(start + i),
+            None => (),
+        }
+    }
+    None
+}
+
+fn parse_attributes(s: &str) -> Option<Vec<(String, String)>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut attributes = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i >= chars.len() {
+            return Some(attributes);
+        }
+        let mut name = String::new();
+        while i < chars.len() && chars[i] != '=' && !chars[i].is_whitespace() {
+            name.push(chars[i]);
+            i += 1;
+        }
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i >= chars.len() || chars[i] != '=' {
+            return None;
+        }
+        i += 1;
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        let quote = *chars.get(i)?;
+        if quote != '"' && quote != '\'' {
+            return None;
+        }
+        i += 1;
+        let mut value = String::new();
+        while i < chars.len() && chars[i] != quote {
+            value.push(chars[i]);
+            i += 1;
+        }
+        if i >= chars.len() {
+            return None;
+        }
+        i += 1;
+        attributes.push((name, value));
+    }
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_length(s: &str) -> Option<f64> {
+    s.trim().trim_end_matches("px").parse::<f64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stamp_with_view_box() {
+        let svg = "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 64 64\"><circle cx=\"32\" cy=\"32\" r=\"30\"/></svg>\n";
+        let stamp = Stamp::parse("throne.svg", svg).expect("Bad stamp");
+        assert_eq!(stamp.view_box(), "0 0 64 64");
+        assert_eq!(stamp.content(), "<circle cx=\"32\" cy=\"32\" r=\"30\"/>");
+    }
+
+    #[test]
+    fn test_parse_stamp_keeps_attributes() {
+        let svg = "<svg xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"10\" height=\"10\" preserveAspectRatio=\"none\"/>";
+        let stamp = Stamp::parse("door.svg", svg).expect("Bad stamp");
+        assert_eq!(
+            stamp.attributes(),
+            [
+                (
+                    "xmlns:xlink".to_string(),
+                    "http://www.w3.org/1999/xlink".to_string()
+                ),
+                ("preserveAspectRatio".to_string(), "none".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_content_with_id_prefix() {
+        let svg = "<svg viewBox=\"0 0 1 1\"><defs><linearGradient id=\"a\"/><path id='ab' data-id=\"a\"/></defs><rect fill=\"url(#a)\"/><use xlink:href=\"#ab\"/><use href='#a'/></svg>";
+        let stamp = Stamp::parse("fancy.svg", svg).expect("Bad stamp");
+        assert_eq!(
+            stamp.content_with_id_prefix("stamp1-"),
+            "<defs><linearGradient id=\"stamp1-a\"/><path id='stamp1-ab' data-id=\"a\"/></defs><rect fill=\"url(#stamp1-a)\"/><use xlink:href=\"#stamp1-ab\"/><use href='#stamp1-a'/>"
+        );
+    }
+
+    #[test]
+    fn test_parse_stamp_with_dimensions() {
+        let svg = "<svg width=\"20px\" height='10'><rect width=\"5\" height=\"5\"/></svg>";
+        let stamp = Stamp::parse("table.svg", svg).expect("Bad stamp");
+        assert_eq!(stamp.view_box(), "0 0 20 10");
+    }
+
+    #[test]
+    fn test_parse_stamp_without_svg_element() {
+        assert!(Stamp::parse("bad.svg", "<html></html>").is_none());
+    }
+
+    #[test]
+    fn test_parse_unterminated_stamp() {
+        assert!(Stamp::parse("bad.svg", "<svg viewBox=\"0 0 1 1\"><g>").is_none());
+    }
+
+    #[test]
+    fn test_parse_stamp_without_size() {
+        assert!(Stamp::parse("bad.svg", "<svg><g/></svg>").is_none());
+    }
+}
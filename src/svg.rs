@@ -8,7 +8,10 @@
  * Copyright (c) 2024 David Jackson
  */
 
+use crate::pdf::PdfContent;
 use crate::points::Point;
+use crate::render::Renderer;
+use crate::stamps::Stamp;
 
 const SVG_XMLNS: &str = "http://www.w3.org/2000/svg";
 
@@ -16,10 +19,16 @@ pub struct SvgBuilder {
     height: usize,
     width: usize,
     elements: Vec<Box<dyn ToSvg>>,
+    /// How many stamps have been embedded
+    stamps: usize,
 }
 
 trait ToSvg {
     fn to_svg(&self) -> String;
+
+    /// Renumber the stamps embedded in an element, when it is moved into a
+    /// drawing that already has `offset` of them
+    fn renumber_stamps(&mut self, _offset: usize) {}
 }
 
 #[derive(Debug)]
@@ -27,18 +36,18 @@ struct SvgRect {
     point: Point,
     width: usize,
     height: usize,
-    stroke: Colour,
+    style: Style,
 }
 
 impl ToSvg for SvgRect {
     fn to_svg(&self) -> String {
         format!(
-            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" stroke=\"{}\" fill=\"none\"/>",
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
             self.point.x(),
             self.point.y(),
             self.width,
             self.height,
-            self.stroke.to_svg()
+            self.style.to_svg()
         )
     }
 }
@@ -46,7 +55,7 @@ impl ToSvg for SvgRect {
 #[derive(Debug)]
 struct SvgPath {
     points: Vec<Point>,
-    stroke: Colour,
+    style: Style,
 }
 
 impl ToSvg for SvgPath {
@@ -64,10 +73,10 @@ impl ToSvg for SvgPath {
             .collect();
         let lines_str = lines_strs.join(" ");
         format!(
-            "<path d=\"{} {}\" stroke=\"{}\" fill=\"none\"/>",
+            "<path d=\"{} {}\" {}/>",
             start,
             lines_str,
-            self.stroke.to_svg()
+            self.style.to_svg()
         )
     }
 }
@@ -77,17 +86,17 @@ struct SvgCircle {
     x: usize,
     y: usize,
     radius: usize,
-    stroke: Colour,
+    style: Style,
 }
 
 impl ToSvg for SvgCircle {
     fn to_svg(&self) -> String {
         format!(
-            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" stroke=\"{}\" fill=\"none\"/>",
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
             self.x,
             self.y,
             self.radius,
-            self.stroke.to_svg(),
+            self.style.to_svg(),
         )
     }
 }
@@ -95,7 +104,7 @@ impl ToSvg for SvgCircle {
 #[derive(Debug)]
 struct SvgPolygon {
     points: Vec<Point>,
-    stroke: Colour,
+    style: Style,
 }
 
 impl ToSvg for SvgPolygon {
@@ -107,56 +116,317 @@ impl ToSvg for SvgPolygon {
             .collect();
         let points_str = points_strings.join(" ");
         format!(
-            "<polygon points=\"{}\" stroke=\"{}\" fill=\"none\"/>",
+            "<polygon points=\"{}\" {}/>",
             points_str,
-            self.stroke.to_svg()
+            self.style.to_svg()
         )
     }
 }
 
-impl SvgBuilder {
-    pub fn new(width: usize, height: usize) -> SvgBuilder {
+/// A filled shape made up of several closed loops, where loops inside other
+/// loops cut holes in them
+#[derive(Debug)]
+struct SvgOutline {
+    loops: Vec<Vec<Point>>,
+    style: Style,
+}
+
+impl ToSvg for SvgOutline {
+    fn to_svg(&self) -> String {
+        let loops_strs: Vec<String> = self
+            .loops
+            .iter()
+            .map(|points| {
+                let points_strs: Vec<String> = points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| {
+                        let command = if i == 0 { "M" } else { "L" };
+                        format!("{}{} {}", command, p.x(), p.y())
+                    })
+                    .collect();
+                format!("{} Z", points_strs.join(" "))
+            })
+            .collect();
+        format!(
+            "<path d=\"{}\" fill-rule=\"evenodd\" {}/>",
+            loops_strs.join(" "),
+            self.style.to_svg()
+        )
+    }
+}
+
+/// A path made of cubic Bézier curves
+#[derive(Debug)]
+struct SvgCurve {
+    start: (f64, f64),
+    curves: Vec<[(f64, f64); 3]>,
+    style: Style,
+}
+
+impl ToSvg for SvgCurve {
+    fn to_svg(&self) -> String {
+        let coordinate = |(x, y): (f64, f64)| format!("{:.1} {:.1}", x, y);
+        let curves_strs: Vec<String> = self
+            .curves
+            .iter()
+            .map(|[c1, c2, end]| {
+                format!(
+                    "C{}, {}, {}",
+                    coordinate(*c1),
+                    coordinate(*c2),
+                    coordinate(*end)
+                )
+            })
+            .collect();
+        format!(
+            "<path d=\"M{} {}\" {}/>",
+            coordinate(self.start),
+            curves_strs.join(" "),
+            self.style.to_svg()
+        )
+    }
+}
+
+/// A group of elements, moved as one
+struct SvgGroup {
+    x: usize,
+    y: usize,
+    elements: Vec<Box<dyn ToSvg>>,
+}
+
+impl ToSvg for SvgGroup {
+    fn to_svg(&self) -> String {
+        let elements_str: String = self.elements.iter().map(|e| e.to_svg()).collect();
+        format!(
+            "<g transform=\"translate({} {})\">{}</g>",
+            self.x, self.y, elements_str
+        )
+    }
+
+    fn renumber_stamps(&mut self, offset: usize) {
+        for element in self.elements.iter_mut() {
+            element.renumber_stamps(offset);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+impl ToSvg for TextAnchor {
+    fn to_svg(&self) -> String {
+        match self {
+            TextAnchor::Start => "start",
+            TextAnchor::Middle => "middle",
+            TextAnchor::End => "end",
+        }
+        .to_string()
+    }
+}
+
+/// A line of text, vertically centred on its point
+#[derive(Debug)]
+struct SvgText {
+    point: Point,
+    text: String,
+    size: usize,
+    anchor: TextAnchor,
+    colour: Colour,
+}
+
+impl ToSvg for SvgText {
+    fn to_svg(&self) -> String {
+        format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"{}\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            self.point.x(),
+            self.point.y(),
+            self.size,
+            self.anchor.to_svg(),
+            self.colour.to_svg(),
+            escape(&self.text)
+        )
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A nested SVG document, scaled into a box
+#[derive(Debug)]
+struct SvgEmbedded {
+    point: Point,
+    width: usize,
+    height: usize,
+    stamp: Stamp,
+    /// Which stamp this is in the drawing, counting from one, by which its
+    /// ids are prefixed
+    number: usize,
+}
+
+impl ToSvg for SvgEmbedded {
+    fn to_svg(&self) -> String {
+        let attributes: String = self
+            .stamp
+            .attributes()
+            .iter()
+            .map(|(name, value)| format!(" {}=\"{}\"", name, value.replace('"', "&quot;")))
+            .collect();
+        format!(
+            "<svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" viewBox=\"{}\"{}>{}</svg>",
+            self.point.x(),
+            self.point.y(),
+            self.width,
+            self.height,
+            self.stamp.view_box(),
+            attributes,
+            self.stamp
+                .content_with_id_prefix(&format!("stamp{}-", self.number))
+        )
+    }
+
+    fn renumber_stamps(&mut self, offset: usize) {
+        self.number += offset;
+    }
+}
+
+impl Renderer for SvgBuilder {
+    fn new(width: usize, height: usize) -> Self {
         SvgBuilder {
             height,
             width,
             elements: Vec::new(),
+            stamps: 0,
         }
     }
 
-    pub fn rect(mut self, point: Point, width: usize, height: usize, stroke: Colour) -> SvgBuilder {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn rect(mut self, point: Point, width: usize, height: usize, style: impl Into<Style>) -> Self {
         let rect = SvgRect {
             point,
             width,
             height,
-            stroke,
+            style: style.into(),
         };
         self.elements.push(Box::new(rect));
         self
     }
 
-    pub fn path(mut self, points: Vec<Point>, stroke: Colour) -> SvgBuilder {
-        let path = SvgPath { points, stroke };
+    fn path(mut self, points: Vec<Point>, style: impl Into<Style>) -> Self {
+        let path = SvgPath {
+            points,
+            style: style.into(),
+        };
         self.elements.push(Box::new(path));
         self
     }
 
-    pub fn circle(mut self, x: usize, y: usize, radius: usize, stroke: Colour) -> SvgBuilder {
+    fn circle(mut self, x: usize, y: usize, radius: usize, style: impl Into<Style>) -> Self {
         let circle = SvgCircle {
             x,
             y,
             radius,
-            stroke,
+            style: style.into(),
         };
         self.elements.push(Box::new(circle));
         self
     }
 
-    pub fn polygon(mut self, points: Vec<Point>, stroke: Colour) -> SvgBuilder {
-        let polygon = SvgPolygon { points, stroke };
+    fn polygon(mut self, points: Vec<Point>, style: impl Into<Style>) -> Self {
+        let polygon = SvgPolygon {
+            points,
+            style: style.into(),
+        };
         self.elements.push(Box::new(polygon));
         self
     }
 
+    fn outline(mut self, loops: Vec<Vec<Point>>, style: impl Into<Style>) -> Self {
+        let outline = SvgOutline {
+            loops,
+            style: style.into(),
+        };
+        self.elements.push(Box::new(outline));
+        self
+    }
+
+    fn curve(
+        mut self,
+        start: (f64, f64),
+        curves: Vec<[(f64, f64); 3]>,
+        style: impl Into<Style>,
+    ) -> Self {
+        let curve = SvgCurve {
+            start,
+            curves,
+            style: style.into(),
+        };
+        self.elements.push(Box::new(curve));
+        self
+    }
+
+    fn text(
+        mut self,
+        point: Point,
+        text: &str,
+        size: usize,
+        anchor: TextAnchor,
+        colour: Colour,
+    ) -> Self {
+        let text = SvgText {
+            point,
+            text: text.to_string(),
+            size,
+            anchor,
+            colour,
+        };
+        self.elements.push(Box::new(text));
+        self
+    }
+
+    fn group(mut self, point: Point, inner: Self) -> Self {
+        let mut group = SvgGroup {
+            x: point.x(),
+            y: point.y(),
+            elements: inner.elements,
+        };
+        group.renumber_stamps(self.stamps);
+        self.stamps += inner.stamps;
+        self.elements.push(Box::new(group));
+        self
+    }
+
+    fn embed(mut self, point: Point, width: usize, height: usize, stamp: &Stamp) -> Self {
+        // Ids are shared by the whole document, so each stamp gets its own
+        self.stamps += 1;
+        let embedded = SvgEmbedded {
+            point,
+            width,
+            height,
+            stamp: stamp.clone(),
+            number: self.stamps,
+        };
+        self.elements.push(Box::new(embedded));
+        self
+    }
+}
+
+impl SvgBuilder {
     pub fn build(&self) -> String {
         let mut svg = String::new();
         svg.push_str(&format!(
@@ -172,12 +442,130 @@ impl SvgBuilder {
     }
 }
 
+/// How an element is stroked and filled
 #[derive(Debug, Clone, Copy)]
+pub struct Style {
+    stroke: Option<Colour>,
+    stroke_width: Option<f64>,
+    stroke_opacity: Option<f64>,
+    fill: Option<Colour>,
+}
+
+impl Style {
+    pub fn stroke(colour: Colour) -> Style {
+        Style {
+            stroke: Some(colour),
+            stroke_width: None,
+            stroke_opacity: None,
+            fill: None,
+        }
+    }
+
+    /// A style that fills an element without outlining it
+    pub fn filled(colour: Colour) -> Style {
+        Style {
+            stroke: None,
+            stroke_width: None,
+            stroke_opacity: None,
+            fill: Some(colour),
+        }
+    }
+
+    pub fn with_width(mut self, width: Option<f64>) -> Style {
+        self.stroke_width = width;
+        self
+    }
+
+    pub fn with_opacity(mut self, opacity: Option<f64>) -> Style {
+        self.stroke_opacity = opacity;
+        self
+    }
+
+    pub fn with_fill(mut self, fill: Option<Colour>) -> Style {
+        self.fill = fill;
+        self
+    }
+
+    pub fn fill(&self) -> Option<Colour> {
+        self.fill
+    }
+
+    pub fn stroke_colour(&self) -> Option<Colour> {
+        self.stroke
+    }
+
+    pub fn stroke_width(&self) -> Option<f64> {
+        self.stroke_width
+    }
+
+    pub fn stroke_opacity(&self) -> Option<f64> {
+        self.stroke_opacity
+    }
+}
+
+impl Style {
+    /// Paint the current PDF path with this style
+    pub(crate) fn paint(&self, content: &mut PdfContent, even_odd: bool) {
+        if let Some(colour) = self.stroke {
+            // Without transparency, fade the colour towards white instead
+            let colour = match self.stroke_opacity {
+                Some(opacity) => colour.faded(opacity),
+                None => colour,
+            };
+            content.stroke_colour(colour);
+            content.line_width(self.stroke_width.unwrap_or(1.0));
+        }
+        if let Some(colour) = self.fill {
+            content.fill_colour(colour);
+        }
+        content.paint(self.stroke.is_some(), self.fill.is_some(), even_odd);
+    }
+}
+
+impl From<Colour> for Style {
+    fn from(colour: Colour) -> Style {
+        Style::stroke(colour)
+    }
+}
+
+impl ToSvg for Style {
+    fn to_svg(&self) -> String {
+        let mut s = match self.stroke {
+            Some(colour) => format!("stroke=\"{}\"", colour.to_svg()),
+            None => "stroke=\"none\"".to_string(),
+        };
+        if let Some(width) = self.stroke_width {
+            s.push_str(&format!(" stroke-width=\"{}\"", width));
+        }
+        if let Some(opacity) = self.stroke_opacity {
+            s.push_str(&format!(" stroke-opacity=\"{}\"", opacity));
+        }
+        match self.fill {
+            Some(colour) => s.push_str(&format!(" fill=\"{}\"", colour.to_svg())),
+            None => s.push_str(" fill=\"none\""),
+        }
+        s
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Colour {
     Black,
     Rgb(u8, u8, u8),
 }
 
+impl Colour {
+    /// Mix the colour with white, as if it were drawn at the given opacity
+    fn faded(self, opacity: f64) -> Colour {
+        let (r, g, b) = match self {
+            Colour::Black => (0, 0, 0),
+            Colour::Rgb(r, g, b) => (r, g, b),
+        };
+        let fade = |c: u8| (255.0 - (255.0 - c as f64) * opacity.clamp(0.0, 1.0)).round() as u8;
+        Colour::Rgb(fade(r), fade(g), fade(b))
+    }
+}
+
 impl ToSvg for Colour {
     fn to_svg(&self) -> String {
         match self {
@@ -234,6 +622,98 @@ mod tests {
             "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><circle cx=\"100\" cy=\"100\" r=\"20\" stroke=\"black\" fill=\"none\"/></svg>");
     }
 
+    #[test]
+    fn test_embed() {
+        let stamp = Stamp::parse(
+            "throne.svg",
+            "<svg xmlns:xlink='http://www.w3.org/1999/xlink' viewBox=\"0 0 64 64\"><circle id=\"c\" cx=\"32\" cy=\"32\" r=\"30\"/></svg>",
+        )
+        .unwrap();
+        let builder = SvgBuilder::new(WIDTH, HEIGHT)
+            .embed(Point::new(10, 10), 20, 20, &stamp)
+            .embed(Point::new(30, 10), 20, 20, &stamp);
+        let svg = builder.build();
+        assert_eq!(svg, "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><svg x=\"10\" y=\"10\" width=\"20\" height=\"20\" viewBox=\"0 0 64 64\" xmlns:xlink=\"http://www.w3.org/1999/xlink\"><circle id=\"stamp1-c\" cx=\"32\" cy=\"32\" r=\"30\"/></svg><svg x=\"30\" y=\"10\" width=\"20\" height=\"20\" viewBox=\"0 0 64 64\" xmlns:xlink=\"http://www.w3.org/1999/xlink\"><circle id=\"stamp2-c\" cx=\"32\" cy=\"32\" r=\"30\"/></svg></svg>");
+    }
+
+    #[test]
+    fn test_grouped_stamps_keep_unique_ids() {
+        let stamp =
+            Stamp::parse("throne.svg", "<svg viewBox=\"0 0 1 1\"><g id=\"c\"/></svg>").unwrap();
+        let inner = SvgBuilder::new(10, 10).embed(Point::new(0, 0), 10, 10, &stamp);
+        let svg = SvgBuilder::new(WIDTH, HEIGHT)
+            .embed(Point::new(0, 0), 10, 10, &stamp)
+            .group(Point::new(10, 0), inner)
+            .embed(Point::new(20, 0), 10, 10, &stamp)
+            .build();
+        for id in ["stamp1-c", "stamp2-c", "stamp3-c"] {
+            assert_eq!(svg.matches(id).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_styled_rect() {
+        let style = Style::stroke(Colour::Black)
+            .with_width(Some(2.5))
+            .with_opacity(Some(0.5))
+            .with_fill(Some(Colour::Rgb(1, 2, 3)));
+        let builder = SvgBuilder::new(WIDTH, HEIGHT).rect(Point::new(0, 0), 10, 10, style);
+        let svg = builder.build();
+        assert_eq!(svg, "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><rect x=\"0\" y=\"0\" width=\"10\" height=\"10\" stroke=\"black\" stroke-width=\"2.5\" stroke-opacity=\"0.5\" fill=\"rgb(1, 2, 3)\"/></svg>");
+    }
+
+    #[test]
+    fn test_curve() {
+        let curves = vec![[(1.0, 2.0), (3.25, 4.0), (5.0, 6.0)]];
+        let svg = SvgBuilder::new(WIDTH, HEIGHT)
+            .curve((0.0, 0.5), curves, Colour::Black)
+            .build();
+        assert!(svg.contains(
+            "<path d=\"M0.0 0.5 C1.0 2.0, 3.2 4.0, 5.0 6.0\" stroke=\"black\" fill=\"none\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_text() {
+        let svg = SvgBuilder::new(WIDTH, HEIGHT)
+            .text(
+                Point::new(5, 6),
+                "A & B",
+                8,
+                TextAnchor::Middle,
+                Colour::Black,
+            )
+            .build();
+        assert!(svg.contains("<text x=\"5\" y=\"6\" font-family=\"sans-serif\" font-size=\"8\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"black\">A &amp; B</text>"));
+    }
+
+    #[test]
+    fn test_group() {
+        let inner = SvgBuilder::new(10, 10).circle(1, 2, 3, Colour::Black);
+        let svg = SvgBuilder::new(WIDTH, HEIGHT)
+            .group(Point::new(10, 20), inner)
+            .build();
+        assert_eq!(svg, "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><g transform=\"translate(10 20)\"><circle cx=\"1\" cy=\"2\" r=\"3\" stroke=\"black\" fill=\"none\"/></g></svg>");
+    }
+
+    #[test]
+    fn test_outline() {
+        let loops = vec![
+            vec![Point::new(0, 0), Point::new(30, 0), Point::new(30, 30)],
+            vec![Point::new(10, 5), Point::new(20, 5), Point::new(20, 15)],
+        ];
+        let builder = SvgBuilder::new(WIDTH, HEIGHT).outline(loops, Style::filled(Colour::Black));
+        let svg = builder.build();
+        assert_eq!(svg, "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><path d=\"M0 0 L30 0 L30 30 Z M10 5 L20 5 L20 15 Z\" fill-rule=\"evenodd\" stroke=\"none\" fill=\"black\"/></svg>");
+    }
+
+    #[test]
+    fn test_background() {
+        let builder = SvgBuilder::new(WIDTH, HEIGHT).background(Colour::Black);
+        let svg = builder.build();
+        assert_eq!(svg, "<svg version=\"1.1\" width=\"300\" height=\"200\" xmlns=\"http://www.w3.org/2000/svg\"><rect x=\"0\" y=\"0\" width=\"300\" height=\"200\" stroke=\"none\" fill=\"black\"/></svg>");
+    }
+
     #[test]
     fn test_polygon() {
         let points = vec![
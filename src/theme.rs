@@ -0,0 +1,301 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::svg::Colour;
+use std::fmt;
+
+const WHITE: Colour = Colour::Rgb(255, 255, 255);
+
+/// The colours and line weights used to draw a map
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    grid: Colour,
+    grid_opacity: Option<f64>,
+    wall: Colour,
+    wall_width: Option<f64>,
+    background: Option<Colour>,
+    fill: Option<Colour>,
+    entity: Colour,
+    entity_fill: Option<Colour>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            grid: Colour::Rgb(200, 200, 200),
+            grid_opacity: None,
+            wall: Colour::Black,
+            wall_width: None,
+            background: None,
+            fill: None,
+            entity: Colour::Black,
+            entity_fill: None,
+        }
+    }
+}
+
+impl Theme {
+    /// The names of the themes built into ttmap
+    pub const BUILTIN_NAMES: [&'static str; 5] =
+        ["default", "classic-blue", "parchment", "dark", "print"];
+
+    /// Look up one of the built-in themes by name
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            "default" => Theme::default(),
+            "classic-blue" => Theme {
+                grid: Colour::Rgb(120, 170, 220),
+                grid_opacity: None,
+                wall: Colour::Rgb(20, 60, 140),
+                wall_width: Some(2.0),
+                background: Some(Colour::Rgb(90, 150, 210)),
+                fill: Some(WHITE),
+                entity: Colour::Rgb(20, 60, 140),
+                entity_fill: None,
+            },
+            "parchment" => Theme {
+                grid: Colour::Rgb(180, 160, 120),
+                grid_opacity: Some(0.5),
+                wall: Colour::Rgb(70, 45, 20),
+                wall_width: Some(2.0),
+                background: Some(Colour::Rgb(240, 225, 190)),
+                fill: Some(Colour::Rgb(250, 240, 215)),
+                entity: Colour::Rgb(70, 45, 20),
+                entity_fill: None,
+            },
+            "dark" => Theme {
+                grid: Colour::Rgb(80, 80, 90),
+                grid_opacity: None,
+                wall: Colour::Rgb(230, 230, 230),
+                wall_width: Some(2.0),
+                background: Some(Colour::Rgb(30, 30, 35)),
+                fill: Some(Colour::Rgb(50, 50, 60)),
+                entity: Colour::Rgb(220, 200, 120),
+                entity_fill: None,
+            },
+            "print" => Theme {
+                grid: Colour::Rgb(220, 220, 220),
+                grid_opacity: None,
+                wall: Colour::Black,
+                wall_width: Some(3.0),
+                background: None,
+                fill: None,
+                entity: Colour::Black,
+                entity_fill: None,
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// Parse a theme file. Each line sets one property, as `key = value`,
+    /// and properties that aren't set are taken from the default theme.
+    /// Colours are written as `#rrggbb`, `black`, `white` or `none`.
+    pub fn parse(input: &str) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::default();
+        for (i, line) in input.lines().enumerate() {
+            let line_number = i + 1;
+            let line = strip_comment(line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), unquote(value.trim())),
+                None => return Err(ThemeError::new(line_number, "expected key = value")),
+            };
+            let error = |message: &str| ThemeError::new(line_number, message);
+            match key {
+                "grid" => {
+                    theme.grid = parse_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                "grid-opacity" => {
+                    theme.grid_opacity =
+                        Some(parse_opacity(value).ok_or_else(|| error("invalid opacity"))?)
+                }
+                "wall" => {
+                    theme.wall = parse_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                "wall-width" => {
+                    theme.wall_width =
+                        Some(parse_number(value).ok_or_else(|| error("invalid number"))?)
+                }
+                "background" => {
+                    theme.background =
+                        parse_optional_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                "fill" => {
+                    theme.fill =
+                        parse_optional_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                "entity" => {
+                    theme.entity = parse_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                "entity-fill" => {
+                    theme.entity_fill =
+                        parse_optional_colour(value).ok_or_else(|| error("invalid colour"))?
+                }
+                _ => return Err(error(&format!("unknown key \"{}\"", key))),
+            }
+        }
+        Ok(theme)
+    }
+
+    pub(crate) fn grid(&self) -> Colour {
+        self.grid
+    }
+
+    pub(crate) fn grid_opacity(&self) -> Option<f64> {
+        self.grid_opacity
+    }
+
+    pub(crate) fn wall(&self) -> Colour {
+        self.wall
+    }
+
+    pub(crate) fn wall_width(&self) -> Option<f64> {
+        self.wall_width
+    }
+
+    pub(crate) fn background(&self) -> Option<Colour> {
+        self.background
+    }
+
+    pub(crate) fn fill(&self) -> Option<Colour> {
+        self.fill
+    }
+
+    pub(crate) fn entity(&self) -> Colour {
+        self.entity
+    }
+
+    pub(crate) fn entity_fill(&self) -> Option<Colour> {
+        self.entity_fill
+    }
+}
+
+#[derive(Debug)]
+pub struct ThemeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ThemeError {
+    fn new(line: usize, message: &str) -> ThemeError {
+        ThemeError {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    // A '#' at the start of a value begins a colour rather than a comment
+    let mut start = 0;
+    if let Some(index) = line.find('=') {
+        let value = line[index + 1..].trim_start();
+        if value.trim_start_matches('"').starts_with('#') {
+            start = line.len() - value.len() + value.find('#').unwrap() + 1;
+        }
+    }
+    match line[start..].find('#') {
+        Some(index) => &line[..start + index],
+        None => line,
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.parse::<f64>().ok().filter(|n| n.is_finite() && *n >= 0.0)
+}
+
+fn parse_opacity(s: &str) -> Option<f64> {
+    parse_number(s).filter(|n| *n <= 1.0)
+}
+
+fn parse_optional_colour(s: &str) -> Option<Option<Colour>> {
+    if s == "none" {
+        Some(None)
+    } else {
+        parse_colour(s).map(Some)
+    }
+}
+
+fn parse_colour(s: &str) -> Option<Colour> {
+    match s {
+        "black" => Some(Colour::Black),
+        "white" => Some(WHITE),
+        _ => {
+            let hex = s.strip_prefix('#')?;
+            if hex.len() != 6 || !hex.is_ascii() {
+                return None;
+            }
+            let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            Some(Colour::Rgb(component(0)?, component(2)?, component(4)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes_exist() {
+        for name in Theme::BUILTIN_NAMES.iter() {
+            assert!(Theme::builtin(name).is_some(), "No theme {}", name);
+        }
+        assert!(Theme::builtin("plaid").is_none());
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        let input = "# A theme\ngrid = \"#102030\" # blue-black\nwall-width = 2.5\nbackground = white\nfill = none # no fill\n";
+        let theme = Theme::parse(input).expect("Bad theme");
+        assert_eq!(theme.grid(), Colour::Rgb(16, 32, 48));
+        assert_eq!(theme.wall_width(), Some(2.5));
+        assert_eq!(theme.background(), Some(WHITE));
+        assert_eq!(theme.fill(), None);
+        assert_eq!(theme.wall(), Colour::Black);
+    }
+
+    #[test]
+    fn test_parse_theme_with_unknown_key() {
+        let err = Theme::parse("grid = black\nstripes = 3").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn test_parse_theme_with_bad_colour() {
+        let err = Theme::parse("wall = #12345").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert_eq!(err.message, "invalid colour");
+    }
+
+    #[test]
+    fn test_parse_theme_with_bad_opacity() {
+        for opacity in ["1.5", "-0.5", "inf", "NaN"] {
+            let err = Theme::parse(&format!("grid-opacity = {}", opacity)).unwrap_err();
+            assert_eq!(err.message, "invalid opacity");
+        }
+        let theme = Theme::parse("grid-opacity = 1").unwrap();
+        assert_eq!(theme.grid_opacity(), Some(1.0));
+    }
+}
@@ -0,0 +1,140 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::entities::EntityPosition;
+use crate::map::{shape_label, Map};
+use crate::shapes::Shape;
+use crate::svg::escape;
+
+/// Write a map as a Tiled (TMX) map, with each tile `dim` pixels square. The
+/// walls are an object layer of straight polylines, and the entities are an
+/// object layer of boxes named after their shapes.
+pub fn map_to_tmx(map: &Map, dim: usize) -> String {
+    let mut next_id = 1;
+    let mut object_id = || {
+        let id = next_id;
+        next_id += 1;
+        id
+    };
+
+    let mut walls = String::new();
+    for (start, end) in map.straight_walls() {
+        walls.push_str(&format!(
+            "  <object id=\"{}\" type=\"wall\" x=\"{}\" y=\"{}\">\n   <polyline points=\"0,0 {},{}\"/>\n  </object>\n",
+            object_id(),
+            start.x() * dim,
+            start.y() * dim,
+            (end.x() - start.x()) * dim,
+            (end.y() - start.y()) * dim
+        ));
+    }
+
+    let mut entities = String::new();
+    for entity in map.entities() {
+        let shape = entity.shape();
+        let kind = match shape {
+            Shape::Glyph(_) => "glyph",
+            Shape::Stamp(_) => "stamp",
+            shape => shape.keyword().unwrap(),
+        };
+        let name = escape(&shape_label(map, shape));
+        let p = entity.point();
+        let id = object_id();
+        match (entity.position(), shape) {
+            // Circles drawn at a grid point are ellipses around it
+            (EntityPosition::At, Shape::Circle(radius)) => entities.push_str(&format!(
+                "  <object id=\"{}\" name=\"{}\" type=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\n   <ellipse/>\n  </object>\n",
+                id,
+                name,
+                kind,
+                (p.x() - radius) * dim,
+                (p.y() - radius) * dim,
+                2 * radius * dim,
+                2 * radius * dim
+            )),
+            _ => entities.push_str(&format!(
+                "  <object id=\"{}\" name=\"{}\" type=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                id,
+                name,
+                kind,
+                p.x() * dim,
+                p.y() * dim,
+                entity.width() * dim,
+                entity.height() * dim
+            )),
+        }
+    }
+
+    let mut tmx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tmx.push_str(&format!(
+        "<map version=\"1.10\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"3\" nextobjectid=\"{}\">\n",
+        map.width(),
+        map.height(),
+        dim,
+        dim,
+        object_id()
+    ));
+    if let Some(title) = map.title() {
+        tmx.push_str(&format!(
+            " <properties>\n  <property name=\"title\" value=\"{}\"/>\n </properties>\n",
+            escape(title)
+        ));
+    }
+    tmx.push_str(" <objectgroup id=\"1\" name=\"Walls\">\n");
+    tmx.push_str(&walls);
+    tmx.push_str(" </objectgroup>\n");
+    tmx.push_str(" <objectgroup id=\"2\" name=\"Entities\">\n");
+    tmx.push_str(&entities);
+    tmx.push_str(" </objectgroup>\n");
+    tmx.push_str("</map>\n");
+    tmx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::points::Point;
+
+    #[test]
+    fn test_walls() {
+        let mut map = Map::new(2, 2);
+        map.connect(Point::new(0, 0), Point::new(1, 0));
+        map.connect(Point::new(1, 0), Point::new(2, 0));
+        map.connect(Point::new(2, 0), Point::new(2, 1));
+        let tmx = map_to_tmx(&map, 10);
+        assert!(tmx.contains("width=\"2\" height=\"2\" tilewidth=\"10\" tileheight=\"10\""));
+        assert!(tmx.contains(
+            "<object id=\"1\" type=\"wall\" x=\"0\" y=\"0\">\n   <polyline points=\"0,0 20,0\"/>"
+        ));
+        assert!(tmx.contains(
+            "<object id=\"2\" type=\"wall\" x=\"20\" y=\"0\">\n   <polyline points=\"0,0 0,10\"/>"
+        ));
+        assert!(tmx.contains("nextobjectid=\"3\""));
+    }
+
+    #[test]
+    fn test_entities() {
+        let mut map = Map::new(3, 3);
+        map.add_entity(Entity::spanning(Shape::Table, Point::new(0, 1), 2, 1));
+        map.add_entity(Entity::new(
+            Shape::Circle(1),
+            Point::new(2, 2),
+            EntityPosition::At,
+        ));
+        let tmx = map_to_tmx(&map, 20);
+        assert!(tmx.contains(
+            "<object id=\"1\" name=\"Table\" type=\"table\" x=\"0\" y=\"20\" width=\"40\" height=\"20\"/>"
+        ));
+        assert!(tmx.contains(
+            "<object id=\"2\" name=\"Circle\" type=\"circle\" x=\"20\" y=\"20\" width=\"40\" height=\"40\">\n   <ellipse/>"
+        ));
+    }
+}
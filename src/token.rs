@@ -25,7 +25,15 @@ impl Token {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A comment, which isn't a token but is kept so that source can be
+/// formatted without losing it. The text is everything after the `#`.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub location: SourceLocation,
+}
+
+#[derive(Debug, Clone)]
 pub enum TokenType {
     Grid,
     At,
@@ -51,4 +59,35 @@ pub enum TokenType {
     Stair,
     Ladder,
     X,
+    To,
+    Pillar,
+    Statue,
+    Chest,
+    Table,
+    Bed,
+    Altar,
+    Well,
+    Fountain,
+    Trapdoor,
+    Brazier,
+    Barrel,
+    Door,
+    Rubble,
+    Trap,
+    Glyph,
+    Path,
+    Close,
+    LeftBrace,
+    RightBrace,
+    Semicolon,
+    Identifier(String),
+    Decimal(f64),
+    Stamp,
+    Size,
+    StringLiteral(String),
+    Title,
+    Scale,
+    Compass,
+    Rotate,
+    Outside,
 }
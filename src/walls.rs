@@ -0,0 +1,225 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::points::Point;
+use std::collections::HashMap;
+
+/// Trace the outline of a set of walls drawn `thickness` pixels thick.
+///
+/// Each wall edge, given in grid coordinates, is widened into a rectangle
+/// that also covers the corners at either end of it, so that walls meeting at
+/// corners and junctions overlap and join squarely. The outline of the union
+/// of those rectangles is returned as closed loops of points, in pixels,
+/// moved right and down by `offset`, which should be at least half the
+/// thickness so that walls on the edge of the grid aren't cut off. The loops
+/// around the holes in the walls (the rooms) run in the opposite direction
+/// to the loops around the walls themselves, so they can be filled with the
+/// even-odd rule.
+pub fn outline_walls(
+    edges: &[(Point, Point)],
+    dim: usize,
+    thickness: usize,
+    offset: usize,
+) -> Vec<Vec<Point>> {
+    let before = thickness / 2;
+    let after = thickness - before;
+    let rects: Vec<(usize, usize, usize, usize)> = edges
+        .iter()
+        .map(|(p1, p2)| {
+            let x1 = p1.x().min(p2.x()) * dim + offset;
+            let x2 = p1.x().max(p2.x()) * dim + offset;
+            let y1 = p1.y().min(p2.y()) * dim + offset;
+            let y2 = p1.y().max(p2.y()) * dim + offset;
+            (
+                x1.saturating_sub(before),
+                y1.saturating_sub(before),
+                x2 + after,
+                y2 + after,
+            )
+        })
+        .collect();
+    outline_rects(&rects)
+}
+
+/// Trace the outline of the union of rectangles given as `(x1, y1, x2, y2)`
+fn outline_rects(rects: &[(usize, usize, usize, usize)]) -> Vec<Vec<Point>> {
+    // Divide the plane into cells along every rectangle side, so that each
+    // cell is either entirely covered or entirely uncovered
+    let xs = sorted_unique(rects.iter().flat_map(|r| [r.0, r.2]));
+    let ys = sorted_unique(rects.iter().flat_map(|r| [r.1, r.3]));
+    if xs.len() < 2 || ys.len() < 2 {
+        return Vec::new();
+    }
+    let columns = xs.len() - 1;
+    let rows = ys.len() - 1;
+    let mut covered = vec![vec![false; rows]; columns];
+    for (x1, y1, x2, y2) in rects.iter() {
+        let (i1, i2) = (index_of(&xs, *x1), index_of(&xs, *x2));
+        let (j1, j2) = (index_of(&ys, *y1), index_of(&ys, *y2));
+        for column in covered.iter_mut().take(i2).skip(i1) {
+            for cell in column.iter_mut().take(j2).skip(j1) {
+                *cell = true;
+            }
+        }
+    }
+    let is_covered = |i: i64, j: i64| {
+        i >= 0 && j >= 0 && (i as usize) < columns && (j as usize) < rows && {
+            covered[i as usize][j as usize]
+        }
+    };
+
+    // Find the sides of covered cells that face uncovered ones, running
+    // clockwise around the covered area, as edges between cell corners
+    let mut outgoing: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for (i, column) in covered.iter().enumerate() {
+        for (j, _) in column.iter().enumerate().filter(|(_, c)| **c) {
+            let (ii, jj) = (i as i64, j as i64);
+            let mut sides = Vec::new();
+            if !is_covered(ii, jj - 1) {
+                sides.push(((i, j), (i + 1, j)));
+            }
+            if !is_covered(ii + 1, jj) {
+                sides.push(((i + 1, j), (i + 1, j + 1)));
+            }
+            if !is_covered(ii, jj + 1) {
+                sides.push(((i + 1, j + 1), (i, j + 1)));
+            }
+            if !is_covered(ii - 1, jj) {
+                sides.push(((i, j + 1), (i, j)));
+            }
+            for (start, end) in sides {
+                outgoing.entry(start).or_default().push(end);
+            }
+        }
+    }
+
+    // Chain the edges into loops. Where two loops touch at a corner, either
+    // choice of next edge gives the same area under the even-odd rule.
+    let mut starts: Vec<(usize, usize)> = outgoing.keys().copied().collect();
+    starts.sort_by_key(|(i, j)| (*j, *i));
+    let mut loops = Vec::new();
+    for start in starts {
+        while let Some(mut next) = take_edge(&mut outgoing, start) {
+            let mut corners = vec![start];
+            while next != start {
+                corners.push(next);
+                next = take_edge(&mut outgoing, next).unwrap();
+            }
+            let points = remove_collinear(&corners)
+                .iter()
+                .map(|(i, j)| Point::new(xs[*i], ys[*j]))
+                .collect();
+            loops.push(points);
+        }
+    }
+    loops
+}
+
+fn take_edge(
+    outgoing: &mut HashMap<(usize, usize), Vec<(usize, usize)>>,
+    from: (usize, usize),
+) -> Option<(usize, usize)> {
+    outgoing.get_mut(&from).and_then(|ends| ends.pop())
+}
+
+fn remove_collinear(corners: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let n = corners.len();
+    (0..n)
+        .filter(|&k| {
+            let prev = corners[(k + n - 1) % n];
+            let here = corners[k];
+            let next = corners[(k + 1) % n];
+            let horizontal = prev.1 == here.1 && here.1 == next.1;
+            let vertical = prev.0 == here.0 && here.0 == next.0;
+            !(horizontal || vertical)
+        })
+        .map(|k| corners[k])
+        .collect()
+}
+
+fn sorted_unique(values: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut values: Vec<usize> = values.collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn index_of(values: &[usize], value: usize) -> usize {
+    values.binary_search(&value).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_wall() {
+        let edges = [(Point::new(1, 1), Point::new(2, 1))];
+        let loops = outline_walls(&edges, 10, 4, 0);
+        assert_eq!(
+            loops,
+            vec![vec![
+                Point::new(8, 8),
+                Point::new(22, 8),
+                Point::new(22, 12),
+                Point::new(8, 12),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_corner_is_mitred() {
+        let edges = [
+            (Point::new(1, 1), Point::new(2, 1)),
+            (Point::new(1, 1), Point::new(1, 2)),
+        ];
+        let loops = outline_walls(&edges, 10, 4, 0);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(
+            loops[0],
+            vec![
+                Point::new(8, 8),
+                Point::new(22, 8),
+                Point::new(22, 12),
+                Point::new(12, 12),
+                Point::new(12, 22),
+                Point::new(8, 22),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_room_has_inner_and_outer_outline() {
+        let edges = [
+            (Point::new(1, 1), Point::new(2, 1)),
+            (Point::new(2, 1), Point::new(2, 2)),
+            (Point::new(1, 2), Point::new(2, 2)),
+            (Point::new(1, 1), Point::new(1, 2)),
+        ];
+        let loops = outline_walls(&edges, 10, 2, 0);
+        assert_eq!(loops.len(), 2);
+        assert!(loops.iter().all(|l| l.len() == 4));
+    }
+
+    #[test]
+    fn test_border_walls_are_whole() {
+        let edges = [(Point::new(0, 0), Point::new(1, 0))];
+        let loops = outline_walls(&edges, 10, 4, 2);
+        assert_eq!(
+            loops,
+            vec![vec![
+                Point::new(0, 0),
+                Point::new(14, 0),
+                Point::new(14, 4),
+                Point::new(0, 4),
+            ]]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * Copyright (c) 2024 David Jackson
+ */
+
+use crate::ast::AstNodeType;
+use crate::compiler::compile_map;
+use crate::map::{map_to_svg_with_options, RenderOptions};
+use crate::parser::parse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// When a file was last changed and how long it is, or `None` if it can't be
+/// read
+type Fingerprint = Option<(SystemTime, u64)>;
+
+/// Recompiles a map file to an SVG file whenever the map, or any file it
+/// stamps, changes. If the map doesn't compile, the last SVG that did is
+/// left in place.
+pub struct Watcher {
+    input: PathBuf,
+    output: PathBuf,
+    dim: usize,
+    options: RenderOptions,
+    stamps: Vec<PathBuf>,
+    fingerprints: Option<Vec<Fingerprint>>,
+}
+
+impl Watcher {
+    pub fn new(input: &Path, output: &Path, dim: usize, options: RenderOptions) -> Watcher {
+        Watcher {
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            dim,
+            options,
+            stamps: Vec::new(),
+            fingerprints: None,
+        }
+    }
+
+    /// The files being watched: the map, and the files it stamps
+    pub fn files(&self) -> Vec<&Path> {
+        let mut files = vec![self.input.as_path()];
+        files.extend(self.stamps.iter().map(PathBuf::as_path));
+        files
+    }
+
+    /// Check the watched files, and if any have changed since the last poll,
+    /// or this is the first, recompile the map. Returns `None` if nothing
+    /// changed, otherwise whether the SVG was written or the error if not.
+    pub fn poll(&mut self) -> Option<Result<(), String>> {
+        let fingerprints = self.fingerprints();
+        if self.fingerprints.as_ref() == Some(&fingerprints) {
+            return None;
+        }
+        let result = self.compile();
+        // Keep the fingerprints from before compiling, so that anything saved
+        // while compiling is picked up next time. If the map now stamps other
+        // files, the next poll sees that as a change and compiles it again.
+        self.fingerprints = Some(fingerprints);
+        Some(result)
+    }
+
+    fn compile(&mut self) -> Result<(), String> {
+        let input = fs::read_to_string(&self.input)
+            .map_err(|e| format!("Couldn't read {}: {}", self.input.display(), e))?;
+        let base_dir = self.input.parent().unwrap_or(Path::new(""));
+        // Watch the stamps even if the map doesn't compile, because a
+        // missing stamp may be about to be created
+        if let Ok(ast) = parse(&input) {
+            self.stamps = ast
+                .nodes()
+                .filter_map(|node| match node.node_type() {
+                    AstNodeType::Stamp(stamp) => Some(base_dir.join(&stamp.path)),
+                    _ => None,
+                })
+                .collect();
+            self.stamps.sort();
+            self.stamps.dedup();
+        }
+        let map = compile_map(&input, base_dir)?;
+        let svg = map_to_svg_with_options(&map, self.dim, &self.options);
+        fs::write(&self.output, format!("{}\n", svg))
+            .map_err(|e| format!("Couldn't write {}: {}", self.output.display(), e))
+    }
+
+    fn fingerprints(&self) -> Vec<Fingerprint> {
+        self.files()
+            .into_iter()
+            .map(|file| {
+                let metadata = fs::metadata(file).ok()?;
+                Some((metadata.modified().ok()?, metadata.len()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ttmap_watch_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_recompiles_on_change() {
+        let dir = test_dir("change");
+        let input = dir.join("dungeon.map");
+        let output = dir.join("dungeon.svg");
+        fs::write(&input, "grid 2, 2\nrect at 0, 0 width 1 height 1\n").unwrap();
+        let mut watcher = Watcher::new(&input, &output, 10, RenderOptions::default());
+
+        assert_eq!(watcher.poll(), Some(Ok(())));
+        let first = fs::read_to_string(&output).unwrap();
+        assert!(first.starts_with("<svg"));
+        assert_eq!(watcher.poll(), None);
+
+        // A map that doesn't compile leaves the last good SVG
+        fs::write(&input, "grid 2, 2\nrect at 0, 0 width 12 height 1\n").unwrap();
+        let error = watcher.poll().unwrap().unwrap_err();
+        assert!(error.contains("ERROR: Out-of-bounds point"));
+        assert_eq!(fs::read_to_string(&output).unwrap(), first);
+        assert_eq!(watcher.poll(), None);
+
+        fs::write(
+            &input,
+            "grid 2, 2\nrect at 0, 0 width 2 height 2\nentity pillar within 1, 1\n",
+        )
+        .unwrap();
+        assert_eq!(watcher.poll(), Some(Ok(())));
+        assert_ne!(fs::read_to_string(&output).unwrap(), first);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watches_stamps() {
+        let dir = test_dir("stamps");
+        let input = dir.join("dungeon.map");
+        let output = dir.join("dungeon.svg");
+        let stamp = dir.join("throne.svg");
+        fs::write(&input, "grid 2, 2\nstamp \"throne.svg\" within 0, 0\n").unwrap();
+        let mut watcher = Watcher::new(&input, &output, 10, RenderOptions::default());
+
+        // The stamp is watched before it exists
+        assert!(watcher.poll().unwrap().is_err());
+        assert_eq!(watcher.files(), vec![input.as_path(), stamp.as_path()]);
+        assert!(!output.exists());
+
+        fs::write(
+            &stamp,
+            "<svg viewBox=\"0 0 10 10\"><rect width=\"10\" height=\"10\"/></svg>",
+        )
+        .unwrap();
+        assert_eq!(watcher.poll(), Some(Ok(())));
+        assert!(fs::read_to_string(&output)
+            .unwrap()
+            .contains("<rect width=\"10\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,14 +8,37 @@
  * Copyright (c) 2024 David Jackson
  */
 
+use std::io::Write;
 use std::path::Path;
-use ttmap::compiler::compile_svg;
+use ttmap::ast::{AbstractSyntaxTree, AstNode, AstNodeType, GridDimensionsNode, ShapeNode, Trivia};
+use ttmap::builder::MapBuilder;
+use ttmap::compass::{Compass, Corner};
+use ttmap::compiler::{
+    compile_ascii, compile_dot, compile_json, compile_map, compile_pdf, compile_pdf_tiles,
+    compile_rooms_dot, compile_svg_with_options, compile_tmx, format_source, parse_source,
+};
+use ttmap::decompiler::map_to_source;
+use ttmap::entities::{Entity, EntityPosition};
 use ttmap::files::read_file;
+use ttmap::formatter::print_ast;
+use ttmap::glyphs::{Glyph, GlyphPath};
+use ttmap::import::import_ascii;
+use ttmap::lsp::serve;
+use ttmap::map::{draw_map, map_to_svg, RenderOptions, WallThickness};
+use ttmap::print::Paper;
+use ttmap::render::{Colour, Point, Renderer, Style, TextAnchor};
+use ttmap::shapes::{Line, LineOrientation, Rect, Shape, ShapeBoolean};
+use ttmap::source_location::SourceLocation;
+use ttmap::theme::Theme;
 
 const DIMENSION: usize = 10;
 const TESTS_DIR: &str = "tests";
 const MAPS_DIR: &str = "maps";
 const SVGS_DIR: &str = "svgs";
+const ASCII_DIR: &str = "ascii";
+const JSON_DIR: &str = "json";
+const TMX_DIR: &str = "tmx";
+const DOT_DIR: &str = "dot";
 
 #[test]
 fn test_basic_rectangle() {
@@ -77,10 +100,126 @@ fn test_x_entity_within_cell() {
     run_test("x_entity_test");
 }
 
+#[test]
+fn test_pillar_entity_within_cell() {
+    run_test("pillar_entity_test");
+}
+
+#[test]
+fn test_statue_entity_within_cell() {
+    run_test("statue_entity_test");
+}
+
+#[test]
+fn test_chest_entity_within_cell() {
+    run_test("chest_entity_test");
+}
+
+#[test]
+fn test_table_entity_within_cell() {
+    run_test("table_entity_test");
+}
+
+#[test]
+fn test_bed_entity_within_cell() {
+    run_test("bed_entity_test");
+}
+
+#[test]
+fn test_altar_entity_within_cell() {
+    run_test("altar_entity_test");
+}
+
+#[test]
+fn test_well_entity_within_cell() {
+    run_test("well_entity_test");
+}
+
+#[test]
+fn test_fountain_entity_within_cell() {
+    run_test("fountain_entity_test");
+}
+
+#[test]
+fn test_trapdoor_entity_within_cell() {
+    run_test("trapdoor_entity_test");
+}
+
+#[test]
+fn test_brazier_entity_within_cell() {
+    run_test("brazier_entity_test");
+}
+
+#[test]
+fn test_barrel_entity_within_cell() {
+    run_test("barrel_entity_test");
+}
+
+#[test]
+fn test_door_entity_within_cell() {
+    run_test("door_entity_test");
+}
+
+#[test]
+fn test_rubble_entity_within_cell() {
+    run_test("rubble_entity_test");
+}
+
+#[test]
+fn test_trap_entity_within_cell() {
+    run_test("trap_entity_test");
+}
+
+#[test]
+fn test_square_entity_spanning_cells() {
+    run_test("multi_cell_entity_test");
+}
+
+#[test]
+fn test_custom_glyph_entities() {
+    run_test("custom_glyph_entity_test");
+}
+
+#[test]
+fn test_stamp_relative_to_map_file() {
+    run_test("stamp_test");
+}
+
+#[test]
+fn test_dark_theme() {
+    let options = RenderOptions {
+        theme: Theme::builtin("dark").unwrap(),
+        ..Default::default()
+    };
+    run_test_with_options("xor_rect_test", "dark_theme_test", &options);
+}
+
+#[test]
+fn test_thick_walls_at_t_junction() {
+    let options = RenderOptions {
+        wall_thickness: Some(WallThickness::Pixels(4)),
+        ..Default::default()
+    };
+    run_test_with_options("t_junction_test", "thick_t_junction_test", &options);
+}
+
+#[test]
+fn test_thick_walls_around_rooms() {
+    let options = RenderOptions {
+        wall_thickness: Some(WallThickness::Cells(0.3)),
+        ..Default::default()
+    };
+    run_test_with_options("xor_rect_test", "thick_xor_rect_test", &options);
+}
+
 fn run_test(test_name: &str) {
+    run_test_with_options(test_name, test_name, &RenderOptions::default());
+}
+
+fn run_test_with_options(map_name: &str, test_name: &str, options: &RenderOptions) {
     let tests_path = Path::new(TESTS_DIR);
 
-    let map_file_name = format!("{}.map", test_name);
+    let map_file_name = format!("{}.map", map_name);
     let maps_path = tests_path.join(Path::new(MAPS_DIR));
     let map_path = maps_path.join(Path::new(&map_file_name));
 
@@ -90,6 +229,578 @@ fn run_test(test_name: &str) {
     let expected_svg = read_file(svg_path.to_str().unwrap());
 
     let input = read_file(map_path.to_str().unwrap());
-    let svg = compile_svg(&input, DIMENSION);
+    let svg = compile_svg_with_options(&input, DIMENSION, &maps_path, options);
     assert_eq!(svg.trim(), expected_svg.trim());
 }
+
+#[test]
+fn test_hatching() {
+    let options = RenderOptions {
+        hatching: true,
+        seed: 1,
+        ..Default::default()
+    };
+    run_test_with_options("xor_rect_test", "hatched_xor_rect_test", &options);
+}
+
+#[test]
+fn test_hatching_around_lines() {
+    let options = RenderOptions {
+        hatching: true,
+        seed: 2,
+        ..Default::default()
+    };
+    run_test_with_options("t_junction_test", "hatched_t_junction_test", &options);
+}
+
+#[test]
+fn test_sketchy_walls() {
+    let options = RenderOptions {
+        sketchy: true,
+        seed: 3,
+        ..Default::default()
+    };
+    run_test_with_options("t_junction_test", "sketchy_t_junction_test", &options);
+}
+
+#[test]
+fn test_sketchy_entities() {
+    let options = RenderOptions {
+        sketchy: true,
+        seed: 4,
+        ..Default::default()
+    };
+    run_test_with_options("table_entity_test", "sketchy_table_entity_test", &options);
+}
+
+#[test]
+fn test_labels() {
+    let options = RenderOptions {
+        labels: true,
+        ..Default::default()
+    };
+    run_test_with_options("stair_entity_test", "labels_test", &options);
+}
+
+#[test]
+fn test_labels_with_background() {
+    let options = RenderOptions {
+        labels: true,
+        theme: Theme::builtin("parchment").unwrap(),
+        ..Default::default()
+    };
+    run_test_with_options("basic_test", "parchment_labels_test", &options);
+}
+
+#[test]
+fn test_title_legend_and_scale() {
+    run_test("handout_test");
+}
+
+#[test]
+fn test_scale_bar_without_legend() {
+    run_test("scale_only_test");
+}
+
+#[test]
+fn test_title_block_with_labels() {
+    let options = RenderOptions {
+        labels: true,
+        ..Default::default()
+    };
+    run_test_with_options("handout_test", "handout_labels_test", &options);
+}
+
+#[test]
+fn test_compass() {
+    run_test("compass_test");
+}
+
+#[test]
+fn test_rotated_compass() {
+    run_test("rotated_compass_test");
+}
+
+#[test]
+fn test_outside_compass() {
+    run_test("outside_compass_test");
+}
+
+#[test]
+fn test_ascii_xor_rect() {
+    run_ascii_test("xor_rect_test", "xor_rect_test", false);
+}
+
+#[test]
+fn test_ascii_t_junction() {
+    run_ascii_test("t_junction_test", "t_junction_test", false);
+}
+
+#[test]
+fn test_ascii_multi_cell_entity() {
+    run_ascii_test("multi_cell_entity_test", "multi_cell_entity_test", false);
+}
+
+#[test]
+fn test_ascii_handout() {
+    run_ascii_test("handout_test", "handout_test", false);
+}
+
+#[test]
+fn test_ascii_labels() {
+    run_ascii_test("handout_test", "handout_labels_test", true);
+}
+
+fn run_ascii_test(map_name: &str, test_name: &str, labels: bool) {
+    let tests_path = Path::new(TESTS_DIR);
+    let maps_path = tests_path.join(MAPS_DIR);
+    let map_path = maps_path.join(format!("{}.map", map_name));
+    let ascii_path = tests_path
+        .join(ASCII_DIR)
+        .join(format!("{}.txt", test_name));
+    let expected = read_file(ascii_path.to_str().unwrap());
+
+    let input = read_file(map_path.to_str().unwrap());
+    let ascii = compile_ascii(&input, &maps_path, labels).expect("Bad compile");
+    assert_eq!(ascii, expected);
+}
+
+#[test]
+fn test_json_handout() {
+    run_json_test("handout_test");
+}
+
+#[test]
+fn test_json_open_walls() {
+    run_json_test("right_angle_path_test");
+}
+
+fn run_json_test(test_name: &str) {
+    let tests_path = Path::new(TESTS_DIR);
+    let maps_path = tests_path.join(MAPS_DIR);
+    let map_path = maps_path.join(format!("{}.map", test_name));
+    let json_path = tests_path
+        .join(JSON_DIR)
+        .join(format!("{}.json", test_name));
+    let expected = read_file(json_path.to_str().unwrap());
+
+    let input = read_file(map_path.to_str().unwrap());
+    let json = compile_json(&input, &maps_path).expect("Bad compile");
+    assert_eq!(json, expected);
+}
+
+#[test]
+fn test_tmx_handout() {
+    let tests_path = Path::new(TESTS_DIR);
+    let maps_path = tests_path.join(MAPS_DIR);
+    let map_path = maps_path.join("handout_test.map");
+    let tmx_path = tests_path.join(TMX_DIR).join("handout_test.tmx");
+    let expected = read_file(tmx_path.to_str().unwrap());
+
+    let input = read_file(map_path.to_str().unwrap());
+    let tmx = compile_tmx(&input, DIMENSION, &maps_path).expect("Bad compile");
+    assert_eq!(tmx, expected);
+}
+
+#[test]
+fn test_dot_walls() {
+    let dot = run_dot_test("rooms_test", "walls_test", compile_dot);
+    assert!(dot.contains("\"0,0\" [pos=\"0,-0!\"]"));
+}
+
+#[test]
+fn test_dot_rooms() {
+    run_dot_test("rooms_test", "rooms_test", compile_rooms_dot);
+}
+
+#[test]
+fn test_language_server_session() {
+    let map_path = Path::new(TESTS_DIR).join(MAPS_DIR).join("handout_test.map");
+    let input = read_file(map_path.to_str().unwrap());
+    let uri = "file:///maps/handout_test.map";
+    let messages = [
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri, "languageId": "ttmap", "version": 1, "text": input}
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+            "textDocument": {"uri": uri}, "position": {"line": 5, "character": 21}
+        }}),
+        serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+        serde_json::json!({"jsonrpc": "2.0", "method": "exit"}),
+        // Nothing after `exit` is read
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "shutdown"}),
+    ];
+    let mut client = Vec::new();
+    for message in messages.iter() {
+        let message = message.to_string();
+        write!(
+            client,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )
+        .unwrap();
+    }
+
+    let mut server = Vec::new();
+    serve(client.as_slice(), &mut server).unwrap();
+    let output = String::from_utf8(server).unwrap();
+    let replies: Vec<serde_json::Value> = output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|frame| {
+            let (length, content) = frame.split_once("\r\n\r\n").unwrap();
+            assert_eq!(length.parse::<usize>().unwrap(), content.len());
+            serde_json::from_str(content).unwrap()
+        })
+        .collect();
+
+    assert_eq!(replies.len(), 4);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(
+        replies[0]["result"]["capabilities"]["definitionProvider"],
+        true
+    );
+    assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(replies[1]["params"]["diagnostics"], serde_json::json!([]));
+    assert_eq!(replies[2]["id"], 2);
+    assert_eq!(
+        replies[2]["result"]["contents"]["value"],
+        "Cell **B2** (x 1, y 1)"
+    );
+    assert_eq!(replies[3]["id"], 3);
+    assert_eq!(replies[3]["result"], serde_json::Value::Null);
+}
+
+fn run_dot_test<F>(map_name: &str, test_name: &str, compile: F) -> String
+where
+    F: Fn(&str, &Path) -> Result<String, String>,
+{
+    let tests_path = Path::new(TESTS_DIR);
+    let maps_path = tests_path.join(MAPS_DIR);
+    let map_path = maps_path.join(format!("{}.map", map_name));
+    let dot_path = tests_path.join(DOT_DIR).join(format!("{}.dot", test_name));
+    let expected = read_file(dot_path.to_str().unwrap());
+
+    let input = read_file(map_path.to_str().unwrap());
+    let dot = compile(&input, &maps_path).expect("Bad compile");
+    assert_eq!(dot, expected);
+    dot
+}
+
+#[test]
+fn test_import_ascii_xor_rect() {
+    run_import_test("xor_rect_test");
+}
+
+#[test]
+fn test_import_ascii_t_junction() {
+    run_import_test("t_junction_test");
+}
+
+#[test]
+fn test_import_ascii_multi_cell_entity() {
+    run_import_test("multi_cell_entity_test");
+}
+
+/// Importing a text drawing and drawing it again should give the same text
+fn run_import_test(test_name: &str) {
+    let tests_path = Path::new(TESTS_DIR);
+    let ascii_path = tests_path
+        .join(ASCII_DIR)
+        .join(format!("{}.txt", test_name));
+    let ascii = read_file(ascii_path.to_str().unwrap());
+
+    let source = import_ascii(&ascii).expect("Bad import");
+    let redrawn = compile_ascii(&source, Path::new(""), false).expect("Bad compile");
+    assert_eq!(redrawn, ascii);
+}
+
+/// Formatting a map shouldn't change how it's drawn, and formatting it
+/// again shouldn't change it any further
+#[test]
+fn test_formatting_preserves_maps() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    for entry in std::fs::read_dir(&maps_path).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("map") {
+            continue;
+        }
+        let input = read_file(path.to_str().unwrap());
+        let formatted = format_source(&input).expect("Bad format");
+        let options = RenderOptions::default();
+        assert_eq!(
+            compile_svg_with_options(&formatted, DIMENSION, &maps_path, &options),
+            compile_svg_with_options(&input, DIMENSION, &maps_path, &options),
+            "{}",
+            path.display()
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+}
+
+#[test]
+fn test_format_error() {
+    assert!(format_source("grid 4, 4\nrect at").is_err());
+}
+
+#[test]
+fn test_decompiling_preserves_maps() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    for entry in std::fs::read_dir(&maps_path).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("map") {
+            continue;
+        }
+        let input = read_file(path.to_str().unwrap());
+        let map = compile_map(&input, &maps_path).expect("Bad compile");
+        let decompiled = map_to_source(&map);
+        // Walls are traced in the order they were drawn, so the SVGs can
+        // differ, but the same walls and entities give the same text drawing
+        assert_eq!(
+            compile_ascii(&decompiled, &maps_path, false),
+            compile_ascii(&input, &maps_path, false),
+            "{}",
+            path.display()
+        );
+        assert!(!decompiled.contains("xor"));
+    }
+}
+
+#[test]
+fn test_print_ast() {
+    let ast = parse_source("grid 2,2\nrect at 0,0 width 1 height 1 # room\n").expect("Bad parse");
+    assert_eq!(
+        print_ast(&ast),
+        "grid 2, 2\nrect at 0, 0 width 1 height 1 # room\n"
+    );
+}
+
+#[test]
+fn test_map_builder() {
+    let source = "\
+grid 6, 5
+title \"Shrine\"
+rect at 1, 1 width 4 height 3
+xor line along bottom from 2, 3 length 2
+entity altar within 2, 1 to 3, 1
+entity circle at 3, 3 radius 1
+";
+    let map = MapBuilder::new(6, 5)
+        .title("Shrine")
+        .rect(Rect::new(Point::new(1, 1), 4, 3, ShapeBoolean::Or))
+        .line(Line::new(
+            LineOrientation::Bottom,
+            Point::new(2, 3),
+            2,
+            ShapeBoolean::Xor,
+        ))
+        .entity(Entity::spanning(Shape::Altar, Point::new(2, 1), 2, 1))
+        .entity(Entity::new(
+            Shape::Circle(1),
+            Point::new(3, 3),
+            EntityPosition::At,
+        ))
+        .build()
+        .expect("Bad map");
+    let expected = compile_map(source, Path::new(".")).expect("Bad compile");
+    assert_eq!(
+        map_to_svg(&map, DIMENSION),
+        map_to_svg(&expected, DIMENSION)
+    );
+}
+
+#[test]
+fn test_single_page_pdf() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    let input = read_file(maps_path.join("handout_test.map").to_str().unwrap());
+    let pdf =
+        compile_pdf(&input, DIMENSION, &maps_path, &RenderOptions::default()).expect("Bad compile");
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with("%PDF-1.4"));
+    // The page is the same size as the SVG would be
+    assert!(pdf.contains("/Count 1"));
+    assert!(pdf.contains("/MediaBox [0 0 112 110]"));
+    assert!(pdf.contains("(The Goblin Warren) Tj"));
+}
+
+#[test]
+fn test_tiled_pdf() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    let input = read_file(maps_path.join("handout_test.map").to_str().unwrap());
+    let pdf = compile_pdf_tiles(&input, &maps_path, &RenderOptions::default(), Paper::Letter)
+        .expect("Bad compile");
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with("%PDF-1.4"));
+    // The map and its title block are 11 by 11 inches, which takes 2 by 2
+    // letter pages
+    assert!(pdf.contains("/Count 4"));
+    assert!(pdf.contains("(The Goblin Warren) Tj"));
+}
+
+#[test]
+fn test_tiled_pdf_with_compile_error() {
+    let result = compile_pdf_tiles(
+        "grid 4, 4\nrect",
+        Path::new(""),
+        &RenderOptions::default(),
+        Paper::A4,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_pdf_with_stamps_is_refused() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    let input = read_file(maps_path.join("stamp_test.map").to_str().unwrap());
+    let options = RenderOptions::default();
+    let expected = "ERROR: Stamps can't be drawn in PDFs: \"art/throne.svg\"";
+    assert_eq!(
+        compile_pdf(&input, DIMENSION, &maps_path, &options).unwrap_err(),
+        expected
+    );
+    assert_eq!(
+        compile_pdf_tiles(&input, &maps_path, &options, Paper::A4).unwrap_err(),
+        expected
+    );
+}
+
+/// A renderer from outside the crate, which only counts what is drawn
+struct CountingRenderer {
+    width: usize,
+    height: usize,
+    circles: usize,
+    shapes: usize,
+    texts: Vec<String>,
+}
+
+impl Renderer for CountingRenderer {
+    fn new(width: usize, height: usize) -> Self {
+        CountingRenderer {
+            width,
+            height,
+            circles: 0,
+            shapes: 0,
+            texts: Vec::new(),
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn rect(mut self, _: Point, _: usize, _: usize, _: impl Into<Style>) -> Self {
+        self.shapes += 1;
+        self
+    }
+
+    fn path(mut self, _: Vec<Point>, _: impl Into<Style>) -> Self {
+        self.shapes += 1;
+        self
+    }
+
+    fn circle(mut self, _: usize, _: usize, _: usize, _: impl Into<Style>) -> Self {
+        self.circles += 1;
+        self
+    }
+
+    fn polygon(mut self, _: Vec<Point>, _: impl Into<Style>) -> Self {
+        self.shapes += 1;
+        self
+    }
+
+    fn outline(mut self, _: Vec<Vec<Point>>, _: impl Into<Style>) -> Self {
+        self.shapes += 1;
+        self
+    }
+
+    fn curve(mut self, _: (f64, f64), _: Vec<[(f64, f64); 3]>, _: impl Into<Style>) -> Self {
+        self.shapes += 1;
+        self
+    }
+
+    fn text(mut self, _: Point, text: &str, _: usize, _: TextAnchor, _: Colour) -> Self {
+        self.texts.push(text.to_string());
+        self
+    }
+
+    fn group(mut self, _: Point, inner: Self) -> Self {
+        self.circles += inner.circles;
+        self.shapes += inner.shapes;
+        self.texts.extend(inner.texts);
+        self
+    }
+}
+
+#[test]
+fn test_custom_renderer() {
+    let maps_path = Path::new(TESTS_DIR).join(MAPS_DIR);
+    let input = read_file(maps_path.join("handout_test.map").to_str().unwrap());
+    let map = compile_map(&input, &maps_path).expect("Bad compile");
+    let drawing: CountingRenderer = draw_map(&map, DIMENSION, &RenderOptions::default());
+    assert_eq!(drawing.width(), 112);
+    assert_eq!(drawing.height(), 110);
+    // Two pillars and the circle on the map, plus the pillar and circle in
+    // the legend
+    assert_eq!(drawing.circles, 5);
+    assert!(drawing.shapes > 0);
+    assert_eq!(
+        drawing.texts,
+        vec![
+            "The Goblin Warren",
+            "Pillar",
+            "Circle",
+            "Crate",
+            "1 square = 5 ft"
+        ]
+    );
+}
+
+#[test]
+fn test_print_built_ast() {
+    let at = |line, col| SourceLocation { line, col };
+    let paths = vec![GlyphPath::new(vec![(0.0, 0.0), (1.0, 1.0)], false)];
+    let mut ast = AbstractSyntaxTree::new();
+    ast.add_node(AstNode::new(
+        AstNodeType::GridDimensions(GridDimensionsNode::new(3, 3)),
+        at(1, 1),
+    ));
+    ast.add_node(AstNode::new(
+        AstNodeType::Compass(Compass::new(Corner::TopRight, 0.0)),
+        at(2, 1),
+    ));
+    ast.add_node(
+        AstNode::new(
+            AstNodeType::Glyph(Glyph::new("slash".to_string(), paths)),
+            at(3, 1),
+        )
+        .with_comments(vec![Trivia::Comment(" Rubble".to_string())], None),
+    );
+    ast.add_node(AstNode::new(
+        AstNodeType::Shape(ShapeNode::Rect(Rect::new(
+            ttmap::points::Point::new(0, 0),
+            3,
+            3,
+            ShapeBoolean::Or,
+        ))),
+        at(4, 1),
+    ));
+    assert_eq!(
+        print_ast(&ast),
+        "\
+grid 3, 3
+compass at top right
+
+# Rubble
+glyph slash { path 0,0 1,1 }
+
+rect at 0, 0 width 3 height 3
+"
+    );
+}